@@ -0,0 +1,618 @@
+//! Concentrated liquidity: liquidity is provided over a range of ticks instead of the whole curve.
+//!
+//! Prices are tracked as the square root of the price of x in y, as a Q64.64 fixed-point number.
+//! Tick `i` sits at price 1.0001^i. Within a range the pool trades on the constant-product curve
+//! of its active liquidity `L`, so between two square-root prices `a < b` that liquidity holds
+//! `L * (1/a - 1/b)` of x and `L * (b - a)` of y.
+use crate::{stable_swap::U256, MathError, FEE_DENOMINATOR};
+
+pub const MIN_TICK: i32 = -443_636;
+pub const MAX_TICK: i32 = 443_636;
+// Square-root prices at MIN_TICK and MAX_TICK
+pub const MIN_SQRT_PRICE: u128 = 4_295_048_017;
+pub const MAX_SQRT_PRICE: u128 = 79_226_673_515_401_279_992_447_579_062;
+
+// 1/sqrt(1.0001)^(2^i) as Q128 numbers, one per bit of |tick|
+const TICK_RATIOS: [u128; 19] = [
+    0xfffcb933bd6fad37aa2d162d1a594001,
+    0xfff97272373d413259a46990580e2139,
+    0xfff2e50f5f656932ef12357cf3c7fdcb,
+    0xffe5caca7e10e4e61c3624eaa0941ccf,
+    0xffcb9843d60f6159c9db58835c926643,
+    0xff973b41fa98c081472e6896dfb254bf,
+    0xff2ea16466c96a3843ec78b326b52860,
+    0xfe5dee046a99a2a811c461f1969c3052,
+    0xfcbe86c7900a88aedcffc83b479aa3a3,
+    0xf987a7253ac413176f2b074cf7815e53,
+    0xf3392b0822b70005940c7a398e4b70f2,
+    0xe7159475a2c29b7443b29c7fa6e889d8,
+    0xd097f3bdfd2022b8845ad8f792aa5825,
+    0xa9f746462d870fdf8a65dc1f90e061e4,
+    0x70d869a156d2a1b890bb3df62baf32f6,
+    0x31be135f97d08fd981231505542fcfa5,
+    0x9aa508b5b7a84e1c677de54f3e99bc8,
+    0x5d6af8dedb81196699c329225ee604,
+    0x2216e584f5fa1ea926041bedfe97,
+];
+
+// Pool-wide state the swap loop moves
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConcentratedState {
+    pub fee: u16,
+    pub sqrt_price: u128,
+    // Greatest tick at or below the price, except right after crossing a tick downwards,
+    // when it is that tick minus one
+    pub tick_current: i32,
+    pub liquidity: u128, // liquidity of the positions whose range contains the price
+    // Fees earned per unit of liquidity since the pool started, as Q64.64, wrapping
+    pub fee_growth_global_x: u128,
+    pub fee_growth_global_y: u128,
+}
+
+// A tick that bounds at least one position
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Tick {
+    pub index: i32,
+    pub liquidity_net: i128, // added to the active liquidity when the price crosses upwards
+    pub liquidity_gross: u128, // total liquidity of the positions bounded by this tick
+    // Fee growth on the other side of this tick from the current price, wrapping
+    pub fee_growth_outside_x: u128,
+    pub fee_growth_outside_y: u128,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SwapStep {
+    pub sqrt_price_next: u128,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConcentratedSwapQuote {
+    pub amount_in: u64, // consumed from the input, fee included; less than asked if the limit was hit
+    pub amount_out: u64,
+    pub fee: u64,
+}
+
+pub fn sqrt_price_at_tick(tick: i32) -> Result<u128, MathError> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return Err(MathError::InvalidTick);
+    }
+
+    let abs = tick.unsigned_abs();
+    let mut ratio = U256::one() << 128;
+    for (i, factor) in TICK_RATIOS.iter().enumerate() {
+        if abs & (1 << i) != 0 {
+            ratio = (ratio * U256::from(*factor)) >> 128;
+        }
+    }
+    if tick > 0 {
+        ratio = U256::MAX / ratio;
+    }
+
+    // Q128 down to Q64.64, rounded up so the price of a tick is never below its true value
+    let rounding = match (ratio & U256::from(u64::MAX)).is_zero() {
+        true => 0,
+        false => 1,
+    };
+    Ok((ratio >> 64).as_u128() + rounding)
+}
+
+// Greatest tick whose square-root price is at or below `sqrt_price`
+pub fn tick_at_sqrt_price(sqrt_price: u128) -> Result<i32, MathError> {
+    search_tick(sqrt_price, MIN_TICK, MAX_TICK)
+}
+
+// Same as `tick_at_sqrt_price` when the answer is known to lie in `lower..=upper`
+fn search_tick(sqrt_price: u128, lower: i32, upper: i32) -> Result<i32, MathError> {
+    if !(MIN_SQRT_PRICE..=MAX_SQRT_PRICE).contains(&sqrt_price) {
+        return Err(MathError::InvalidPrice);
+    }
+
+    let (mut lower, mut upper) = (lower, upper);
+    while lower < upper {
+        let mid = lower + (upper - lower + 1) / 2;
+        match sqrt_price_at_tick(mid)? <= sqrt_price {
+            true => lower = mid,
+            false => upper = mid - 1,
+        }
+    }
+    Ok(lower)
+}
+
+// x held by `liquidity` between two square-root prices
+pub fn amount_x_delta(
+    sqrt_price_a: u128,
+    sqrt_price_b: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<u128, MathError> {
+    let (lower, upper) = sorted(sqrt_price_a, sqrt_price_b);
+    if lower == 0 {
+        return Err(MathError::InvalidPrice);
+    }
+
+    let numerator = (U256::from(liquidity) << 64)
+        .checked_mul(U256::from(upper - lower))
+        .ok_or(MathError::Overflow)?;
+    let amount = match round_up {
+        true => div_ceil(div_ceil(numerator, U256::from(upper)), U256::from(lower)),
+        false => numerator / U256::from(upper) / U256::from(lower),
+    };
+    to_u128(amount)
+}
+
+// y held by `liquidity` between two square-root prices
+pub fn amount_y_delta(
+    sqrt_price_a: u128,
+    sqrt_price_b: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<u128, MathError> {
+    let (lower, upper) = sorted(sqrt_price_a, sqrt_price_b);
+
+    let product = U256::from(liquidity) * U256::from(upper - lower);
+    let amount = match round_up {
+        true => div_ceil(product, U256::one() << 64),
+        false => product >> 64,
+    };
+    to_u128(amount)
+}
+
+// Price after adding `amount` of the input token, rounded so the pool never gives out more
+fn next_sqrt_price_from_input(
+    sqrt_price: u128,
+    liquidity: u128,
+    amount: u64,
+    is_x: bool,
+) -> Result<u128, MathError> {
+    match is_x {
+        // L * P / (L + amount * P), which moves the price down
+        true => {
+            let liquidity = U256::from(liquidity) << 64;
+            let numerator = liquidity
+                .checked_mul(U256::from(sqrt_price))
+                .ok_or(MathError::Overflow)?;
+            let denominator = liquidity + U256::from(amount) * U256::from(sqrt_price);
+            to_u128(div_ceil(numerator, denominator))
+        }
+        // P + amount / L, which moves the price up
+        false => {
+            let delta = (U256::from(amount) << 64) / U256::from(liquidity);
+            to_u128(U256::from(sqrt_price) + delta)
+        }
+    }
+}
+
+// Trades as much of `amount_remaining` as fits before the price reaches `sqrt_price_target`.
+// The fee, in basis points, is charged on the input and the dust of a partial step goes to it
+pub fn swap_step(
+    sqrt_price: u128,
+    sqrt_price_target: u128,
+    liquidity: u128,
+    amount_remaining: u64,
+    fee: u16,
+) -> Result<SwapStep, MathError> {
+    if fee as u64 >= FEE_DENOMINATOR {
+        return Err(MathError::InvalidAmount);
+    }
+
+    let is_x = sqrt_price_target < sqrt_price;
+    let rate = FEE_DENOMINATOR - fee as u64;
+    let amount_less_fee =
+        (amount_remaining as u128 * rate as u128 / FEE_DENOMINATOR as u128) as u64;
+
+    let amount_to_target = match is_x {
+        true => amount_x_delta(sqrt_price_target, sqrt_price, liquidity, true)?,
+        false => amount_y_delta(sqrt_price, sqrt_price_target, liquidity, true)?,
+    };
+    let sqrt_price_next = match amount_less_fee as u128 >= amount_to_target {
+        true => sqrt_price_target,
+        false => next_sqrt_price_from_input(sqrt_price, liquidity, amount_less_fee, is_x)?,
+    };
+    let reached = sqrt_price_next == sqrt_price_target;
+
+    let amount_in = match (reached, is_x) {
+        (true, _) => amount_to_target,
+        (false, true) => amount_x_delta(sqrt_price_next, sqrt_price, liquidity, true)?,
+        (false, false) => amount_y_delta(sqrt_price, sqrt_price_next, liquidity, true)?,
+    };
+    let amount_out = match is_x {
+        true => amount_y_delta(sqrt_price_next, sqrt_price, liquidity, false)?,
+        false => amount_x_delta(sqrt_price, sqrt_price_next, liquidity, false)?,
+    };
+    let amount_in = u64::try_from(amount_in).map_err(|_| MathError::Overflow)?;
+    let amount_out = u64::try_from(amount_out).map_err(|_| MathError::Overflow)?;
+
+    let fee = match reached {
+        true => (amount_in as u128 * fee as u128).div_ceil(rate as u128) as u64,
+        false => amount_remaining
+            .checked_sub(amount_in)
+            .ok_or(MathError::Overflow)?,
+    };
+
+    Ok(SwapStep {
+        sqrt_price_next,
+        amount_in,
+        amount_out,
+        fee,
+    })
+}
+
+// Sells up to `amount_in` of x (or y when `is_x` is false), stopping early if the price
+// reaches `sqrt_price_limit`. `ticks` must be sorted by index; the ones crossed are updated
+pub fn swap(
+    state: &mut ConcentratedState,
+    ticks: &mut [Tick],
+    is_x: bool,
+    amount_in: u64,
+    sqrt_price_limit: u128,
+) -> Result<ConcentratedSwapQuote, MathError> {
+    if amount_in == 0 {
+        return Err(MathError::InvalidAmount);
+    }
+    // The limit has to lie strictly inside the price range, so the last tick is never crossed
+    let limit_valid = match is_x {
+        true => sqrt_price_limit < state.sqrt_price && sqrt_price_limit > MIN_SQRT_PRICE,
+        false => sqrt_price_limit > state.sqrt_price && sqrt_price_limit < MAX_SQRT_PRICE,
+    };
+    if !limit_valid {
+        return Err(MathError::InvalidPrice);
+    }
+
+    let mut remaining = amount_in;
+    let mut quote = ConcentratedSwapQuote::default();
+
+    while remaining > 0 && state.sqrt_price != sqrt_price_limit {
+        // Next initialized tick in the direction of the trade
+        let next = match is_x {
+            true => ticks.iter().rposition(|t| t.index <= state.tick_current),
+            false => ticks.iter().position(|t| t.index > state.tick_current),
+        };
+        let tick_next = match (next, is_x) {
+            (Some(i), _) => ticks[i].index,
+            (None, true) => MIN_TICK,
+            (None, false) => MAX_TICK,
+        };
+        let sqrt_price_tick = sqrt_price_at_tick(tick_next)?;
+        let sqrt_price_target = match is_x {
+            true => sqrt_price_tick.max(sqrt_price_limit),
+            false => sqrt_price_tick.min(sqrt_price_limit),
+        };
+
+        let step = swap_step(
+            state.sqrt_price,
+            sqrt_price_target,
+            state.liquidity,
+            remaining,
+            state.fee,
+        )?;
+        remaining -= step.amount_in + step.fee;
+        quote.amount_out = quote
+            .amount_out
+            .checked_add(step.amount_out)
+            .ok_or(MathError::Overflow)?;
+        quote.fee += step.fee;
+
+        // Fees accrue to the liquidity that was active during the step
+        if let Some(growth) = ((step.fee as u128) << 64).checked_div(state.liquidity) {
+            match is_x {
+                true => state.fee_growth_global_x = state.fee_growth_global_x.wrapping_add(growth),
+                false => state.fee_growth_global_y = state.fee_growth_global_y.wrapping_add(growth),
+            }
+        }
+
+        let sqrt_price_start = state.sqrt_price;
+        state.sqrt_price = step.sqrt_price_next;
+
+        if step.sqrt_price_next == sqrt_price_tick {
+            if let Some(i) = next {
+                let tick = &mut ticks[i];
+                tick.fee_growth_outside_x = state
+                    .fee_growth_global_x
+                    .wrapping_sub(tick.fee_growth_outside_x);
+                tick.fee_growth_outside_y = state
+                    .fee_growth_global_y
+                    .wrapping_sub(tick.fee_growth_outside_y);
+                let liquidity_net = match is_x {
+                    true => -tick.liquidity_net,
+                    false => tick.liquidity_net,
+                };
+                state.liquidity = add_liquidity_delta(state.liquidity, liquidity_net)?;
+            }
+            state.tick_current = match is_x {
+                true => tick_next - 1,
+                false => tick_next,
+            };
+        } else if step.sqrt_price_next != sqrt_price_start {
+            state.tick_current = match is_x {
+                true => search_tick(step.sqrt_price_next, tick_next, state.tick_current)?,
+                false => search_tick(step.sqrt_price_next, state.tick_current, tick_next)?,
+            };
+        }
+    }
+
+    quote.amount_in = amount_in - remaining;
+    Ok(quote)
+}
+
+// Fee growth per unit of liquidity between two ticks, as seen from the current tick
+pub fn fee_growth_inside(state: &ConcentratedState, lower: &Tick, upper: &Tick) -> (u128, u128) {
+    let (below_x, below_y) = match state.tick_current >= lower.index {
+        true => (lower.fee_growth_outside_x, lower.fee_growth_outside_y),
+        false => (
+            state
+                .fee_growth_global_x
+                .wrapping_sub(lower.fee_growth_outside_x),
+            state
+                .fee_growth_global_y
+                .wrapping_sub(lower.fee_growth_outside_y),
+        ),
+    };
+    let (above_x, above_y) = match state.tick_current < upper.index {
+        true => (upper.fee_growth_outside_x, upper.fee_growth_outside_y),
+        false => (
+            state
+                .fee_growth_global_x
+                .wrapping_sub(upper.fee_growth_outside_x),
+            state
+                .fee_growth_global_y
+                .wrapping_sub(upper.fee_growth_outside_y),
+        ),
+    };
+
+    (
+        state
+            .fee_growth_global_x
+            .wrapping_sub(below_x)
+            .wrapping_sub(above_x),
+        state
+            .fee_growth_global_y
+            .wrapping_sub(below_y)
+            .wrapping_sub(above_y),
+    )
+}
+
+// Adds a position's liquidity change to one of its bounding ticks
+pub fn update_tick(
+    tick: &mut Tick,
+    state: &ConcentratedState,
+    liquidity_delta: i128,
+    is_upper: bool,
+) -> Result<(), MathError> {
+    // A fresh tick at or below the price assumes all growth so far happened below it
+    if tick.liquidity_gross == 0 && tick.index <= state.tick_current {
+        tick.fee_growth_outside_x = state.fee_growth_global_x;
+        tick.fee_growth_outside_y = state.fee_growth_global_y;
+    }
+
+    tick.liquidity_gross = add_liquidity_delta(tick.liquidity_gross, liquidity_delta)?;
+    tick.liquidity_net = match is_upper {
+        true => tick.liquidity_net.checked_sub(liquidity_delta),
+        false => tick.liquidity_net.checked_add(liquidity_delta),
+    }
+    .ok_or(MathError::Overflow)?;
+    Ok(())
+}
+
+// Fees earned by `liquidity` since the fee growth inside its range was `fee_growth_inside_last`
+pub fn fees_earned(
+    liquidity: u128,
+    fee_growth_inside: u128,
+    fee_growth_inside_last: u128,
+) -> Result<u64, MathError> {
+    let growth = fee_growth_inside.wrapping_sub(fee_growth_inside_last);
+    let fees = (U256::from(growth) * U256::from(liquidity)) >> 64;
+    u64::try_from(to_u128(fees)?).map_err(|_| MathError::Overflow)
+}
+
+// x and y held by `liquidity` over a range at the current price. Round up for deposits
+// and down for withdrawals
+pub fn position_amounts(
+    state: &ConcentratedState,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u128,
+    round_up: bool,
+) -> Result<(u64, u64), MathError> {
+    let sqrt_price_lower = sqrt_price_at_tick(tick_lower)?;
+    let sqrt_price_upper = sqrt_price_at_tick(tick_upper)?;
+
+    let (x, y) = match (
+        state.tick_current < tick_lower,
+        state.tick_current < tick_upper,
+    ) {
+        // Price below the range: all x
+        (true, _) => (
+            amount_x_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?,
+            0,
+        ),
+        // Price inside the range: x above it, y below it
+        (false, true) => (
+            amount_x_delta(state.sqrt_price, sqrt_price_upper, liquidity, round_up)?,
+            amount_y_delta(sqrt_price_lower, state.sqrt_price, liquidity, round_up)?,
+        ),
+        // Price above the range: all y
+        (false, false) => (
+            0,
+            amount_y_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?,
+        ),
+    };
+
+    Ok((
+        u64::try_from(x).map_err(|_| MathError::Overflow)?,
+        u64::try_from(y).map_err(|_| MathError::Overflow)?,
+    ))
+}
+
+pub fn add_liquidity_delta(liquidity: u128, delta: i128) -> Result<u128, MathError> {
+    match delta >= 0 {
+        true => liquidity.checked_add(delta as u128),
+        false => liquidity.checked_sub(delta.unsigned_abs()),
+    }
+    .ok_or(MathError::InsufficientLiquidity)
+}
+
+fn sorted(a: u128, b: u128) -> (u128, u128) {
+    match a <= b {
+        true => (a, b),
+        false => (b, a),
+    }
+}
+
+fn div_ceil(numerator: U256, denominator: U256) -> U256 {
+    let (quotient, remainder) = numerator.div_mod(denominator);
+    match remainder.is_zero() {
+        true => quotient,
+        false => quotient + 1,
+    }
+}
+
+fn to_u128(value: U256) -> Result<u128, MathError> {
+    match value > U256::from(u128::MAX) {
+        true => Err(MathError::Overflow),
+        false => Ok(value.as_u128()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const Q64: u128 = 1 << 64;
+
+    fn pool(liquidity: u128) -> ConcentratedState {
+        ConcentratedState {
+            fee: 30,
+            sqrt_price: Q64,
+            tick_current: 0,
+            liquidity,
+            ..Default::default()
+        }
+    }
+
+    // Ticks for one position over `lower..upper` holding `liquidity`
+    fn position(state: &ConcentratedState, lower: i32, upper: i32, liquidity: u128) -> [Tick; 2] {
+        let mut ticks = [
+            Tick {
+                index: lower,
+                ..Default::default()
+            },
+            Tick {
+                index: upper,
+                ..Default::default()
+            },
+        ];
+        update_tick(&mut ticks[0], state, liquidity as i128, false).unwrap();
+        update_tick(&mut ticks[1], state, liquidity as i128, true).unwrap();
+        ticks
+    }
+
+    #[test]
+    fn sqrt_price_matches_the_tick_bounds() {
+        assert_eq!(sqrt_price_at_tick(0).unwrap(), Q64);
+        assert_eq!(sqrt_price_at_tick(MIN_TICK).unwrap(), MIN_SQRT_PRICE);
+        assert_eq!(sqrt_price_at_tick(MAX_TICK).unwrap(), MAX_SQRT_PRICE);
+        assert_eq!(
+            sqrt_price_at_tick(MAX_TICK + 1),
+            Err(MathError::InvalidTick)
+        );
+        assert_eq!(
+            sqrt_price_at_tick(MIN_TICK - 1),
+            Err(MathError::InvalidTick)
+        );
+    }
+
+    #[test]
+    fn tick_at_sqrt_price_inverts_sqrt_price_at_tick() {
+        for tick in [MIN_TICK, -200_000, -60, -1, 0, 1, 60, 200_000, MAX_TICK] {
+            let sqrt_price = sqrt_price_at_tick(tick).unwrap();
+            assert_eq!(tick_at_sqrt_price(sqrt_price).unwrap(), tick);
+            if tick < MAX_TICK {
+                assert!(sqrt_price < sqrt_price_at_tick(tick + 1).unwrap());
+                assert_eq!(tick_at_sqrt_price(sqrt_price + 1).unwrap(), tick);
+            }
+        }
+    }
+
+    #[test]
+    fn deltas_round_in_favour_of_the_pool() {
+        let lower = sqrt_price_at_tick(-100).unwrap();
+        let upper = sqrt_price_at_tick(100).unwrap();
+        let liquidity = 1_000_000_007;
+
+        let x_up = amount_x_delta(lower, upper, liquidity, true).unwrap();
+        let x_down = amount_x_delta(upper, lower, liquidity, false).unwrap();
+        let y_up = amount_y_delta(lower, upper, liquidity, true).unwrap();
+        let y_down = amount_y_delta(lower, upper, liquidity, false).unwrap();
+        assert_eq!(x_up, x_down + 1);
+        assert_eq!(y_up, y_down + 1);
+        // Symmetric range around a price of 1 holds about the same of each
+        assert!(x_up.abs_diff(y_up) <= 1);
+    }
+
+    #[test]
+    fn swap_within_a_range_trades_on_its_liquidity() {
+        let mut state = pool(0);
+        let mut ticks = position(&state, -600, 600, 1_000_000_000);
+        state.liquidity = 1_000_000_000;
+
+        let quote = swap(&mut state, &mut ticks, true, 1_000_000, MIN_SQRT_PRICE + 1).unwrap();
+        assert_eq!(quote.amount_in, 1_000_000);
+        assert_eq!(quote.fee, 3_000);
+        // Close to the constant-product output of 997_000 into 1e9 by 1e9
+        assert!(quote.amount_out > 996_000 && quote.amount_out < 997_000);
+        assert!(state.sqrt_price < Q64);
+        assert_eq!(state.tick_current, -20);
+        assert_eq!(state.liquidity, 1_000_000_000);
+    }
+
+    #[test]
+    fn swap_crosses_ticks_and_stops_at_the_limit() {
+        let mut state = pool(0);
+        let [lower, upper] = position(&state, -60, 60, 1_000_000_000);
+        state.liquidity = 1_000_000_000;
+        let mut ticks = [lower, upper];
+
+        // Far more than the range holds: everything past tick 60 has no liquidity
+        let limit = sqrt_price_at_tick(120).unwrap();
+        let quote = swap(&mut state, &mut ticks, false, 100_000_000, limit).unwrap();
+        assert!(quote.amount_in < 100_000_000);
+        assert_eq!(state.sqrt_price, limit);
+        assert_eq!(state.liquidity, 0);
+        assert_eq!(state.tick_current, 120);
+
+        // The whole y side of the range was bought
+        let bought = amount_x_delta(Q64, sqrt_price_at_tick(60).unwrap(), 1_000_000_000, false);
+        assert!(bought.unwrap() - quote.amount_out as u128 <= 1);
+
+        // Coming back down crosses tick 60 again and picks the liquidity back up
+        let quote = swap(&mut state, &mut ticks, true, 1_000, MIN_SQRT_PRICE + 1).unwrap();
+        assert_eq!(quote.amount_in, 1_000);
+        assert_eq!(state.liquidity, 1_000_000_000);
+        assert!(state.tick_current < 60);
+    }
+
+    #[test]
+    fn fees_accrue_only_inside_the_traded_range() {
+        let mut state = pool(0);
+        let [a_lower, a_upper] = position(&state, -60, 60, 1_000_000_000);
+        let [b_lower, b_upper] = position(&state, 60, 600, 1_000_000_000);
+        state.liquidity = 1_000_000_000;
+        // Tick 60 bounds both positions
+        let mut shared = a_upper;
+        shared.liquidity_gross += b_lower.liquidity_gross;
+        shared.liquidity_net += b_lower.liquidity_net;
+        let mut ticks = [a_lower, shared, b_upper];
+
+        swap(&mut state, &mut ticks, true, 100_000, MIN_SQRT_PRICE + 1).unwrap();
+        assert!(state.tick_current < 0 && state.tick_current >= -60);
+
+        let (a_x, _) = fee_growth_inside(&state, &ticks[0], &ticks[1]);
+        let (b_x, _) = fee_growth_inside(&state, &ticks[1], &ticks[2]);
+        let earned = fees_earned(1_000_000_000, a_x, 0).unwrap();
+        assert!(300 - earned <= 1);
+        assert_eq!(b_x, 0);
+    }
+}
@@ -0,0 +1,66 @@
+use crate::MathError;
+
+// Amount out for `amount_in` after the fee has been taken, rounded down in favour of the pool
+// so that x * y never decreases
+pub fn swap_amount_out(
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_in: u64,
+) -> Result<u64, MathError> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    let new_reserve_in = (reserve_in as u128)
+        .checked_add(amount_in as u128)
+        .ok_or(MathError::Overflow)?;
+
+    Ok((reserve_out as u128 * amount_in as u128 / new_reserve_in) as u64)
+}
+
+// Marginal prices of x in y and of y in x as Q64.64 fixed point numbers, which on this curve
+// are just the reserve ratios
+pub fn spot_prices(reserve_x: u64, reserve_y: u64) -> Result<(u128, u128), MathError> {
+    if reserve_x == 0 || reserve_y == 0 {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    Ok((
+        ((reserve_y as u128) << 64) / reserve_x as u128,
+        ((reserve_x as u128) << 64) / reserve_y as u128,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn product_does_not_decrease_after_swap() {
+        for (reserve_in, reserve_out, amount_in) in [
+            (1_000_000, 1_000_000, 1_000),
+            (1_000_000, 7, 999_999),
+            (3, 1_000_000_000, 1),
+            (u64::MAX / 2, u64::MAX / 3, u64::MAX / 2),
+        ] {
+            let out = swap_amount_out(reserve_in, reserve_out, amount_in).unwrap();
+            let before = reserve_in as u128 * reserve_out as u128;
+            let after = (reserve_in as u128 + amount_in as u128) * (reserve_out - out) as u128;
+            assert!(after >= before);
+        }
+    }
+
+    #[test]
+    fn empty_pool_cannot_be_swapped_against() {
+        assert_eq!(
+            swap_amount_out(0, 1_000, 10),
+            Err(MathError::InsufficientLiquidity)
+        );
+        assert_eq!(spot_prices(0, 1_000), Err(MathError::InsufficientLiquidity));
+    }
+
+    #[test]
+    fn spot_prices_are_the_reserve_ratios() {
+        assert_eq!(spot_prices(1_000, 4_000).unwrap(), (4 << 64, 1 << 62));
+    }
+}
@@ -0,0 +1,78 @@
+use crate::{stable_swap::U256, MathError, FEE_DENOMINATOR};
+
+// Recent price movement decays linearly to zero over this many seconds without trades
+pub const VOLATILITY_WINDOW: i64 = 600;
+// Share of the recent price movement, in percent, added on top of the minimum fee
+pub const VOLATILITY_FEE_SHARE: u64 = 10;
+// Ceiling on the tracked movement (1,000%), far past any useful `fee_max`
+pub const MAX_VOLATILITY: u64 = 100_000;
+
+// Tracked movement `volatility`, last updated at `volatility_ts`, as seen at `now`
+pub fn decay(volatility: u64, volatility_ts: i64, now: i64) -> u64 {
+    let elapsed = now.saturating_sub(volatility_ts).max(0);
+    if elapsed >= VOLATILITY_WINDOW {
+        return 0;
+    }
+    (volatility as u128 * (VOLATILITY_WINDOW - elapsed) as u128 / VOLATILITY_WINDOW as u128) as u64
+}
+
+// Fee for a given recent price movement, both in basis points
+pub fn fee(fee_min: u16, fee_max: u16, volatility: u64) -> u16 {
+    let fee = (fee_min as u64).saturating_add(volatility * VOLATILITY_FEE_SHARE / 100);
+    fee.min(fee_max as u64).max(fee_min as u64) as u16
+}
+
+// Relative change between two Q64.64 prices of x in y, in basis points. Take them from
+// `PoolConfig::spot_prices`, since only on constant product pools does the price follow the
+// reserve ratio
+pub fn price_move(price_before: u128, price_after: u128) -> Result<u64, MathError> {
+    if price_before == 0 {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    let (before, after) = (U256::from(price_before), U256::from(price_after));
+
+    let delta = match after > before {
+        true => after - before,
+        false => before - after,
+    };
+    let bps = delta * U256::from(FEE_DENOMINATOR) / before;
+    Ok(match bps > U256::from(MAX_VOLATILITY) {
+        true => MAX_VOLATILITY,
+        false => bps.as_u64(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volatility_decays_linearly_over_the_window() {
+        assert_eq!(decay(1_000, 100, 100), 1_000);
+        assert_eq!(decay(1_000, 100, 100 + VOLATILITY_WINDOW / 2), 500);
+        assert_eq!(decay(1_000, 100, 100 + VOLATILITY_WINDOW), 0);
+        // A clock behind the last update does not inflate it
+        assert_eq!(decay(1_000, 100, 50), 1_000);
+    }
+
+    #[test]
+    fn fee_grows_with_volatility_within_bounds() {
+        assert_eq!(fee(5, 100, 0), 5);
+        // A 5% move adds 10% of 500 bps
+        assert_eq!(fee(5, 100, 500), 55);
+        assert_eq!(fee(5, 100, 5_000), 100);
+        assert_eq!(fee(5, 100, MAX_VOLATILITY), 100);
+    }
+
+    #[test]
+    fn price_move_is_relative_to_the_starting_price() {
+        const ONE: u128 = 1 << 64;
+        // Price of x from 1 to 1.5625 and back
+        assert_eq!(price_move(ONE, ONE / 16 * 25).unwrap(), 5_625);
+        assert_eq!(price_move(ONE / 16 * 25, ONE).unwrap(), 3_600);
+        assert_eq!(price_move(2 * ONE, 2 * ONE).unwrap(), 0);
+        assert_eq!(price_move(ONE, 1_000 * ONE).unwrap(), MAX_VOLATILITY);
+        assert_eq!(price_move(0, ONE), Err(MathError::InsufficientLiquidity));
+    }
+}
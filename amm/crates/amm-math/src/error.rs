@@ -0,0 +1,9 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MathError {
+    Overflow,
+    InvalidAmount,
+    InsufficientLiquidity,
+    InitialLiquidityTooLow,
+    InvalidTick,
+    InvalidPrice,
+}
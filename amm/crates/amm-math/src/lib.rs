@@ -0,0 +1,26 @@
+//! Pricing and liquidity math for the `amm` program.
+//!
+//! The program executes deposits, withdrawals and swaps through the `quote_*` functions below,
+//! so an off-chain quote built from a decoded `Config` and a snapshot of the vaults and LP supply
+//! matches what the program will do. Amounts are what the vaults send and receive, before any
+//! Token-2022 transfer fee.
+#![cfg_attr(not(test), no_std)]
+
+pub mod concentrated;
+pub mod constant_product;
+pub mod dynamic_fee;
+mod error;
+mod pool;
+mod quote;
+pub mod stable_swap;
+
+pub use error::MathError;
+pub use pool::{Curve, PoolConfig, Snapshot};
+pub use quote::*;
+
+// LP permanently locked in the pool on the first deposit, so the LP supply can never be
+// inflated from a dust amount to make shares too expensive to round fairly
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+// Fees are expressed in basis points
+pub const FEE_DENOMINATOR: u64 = 10_000;
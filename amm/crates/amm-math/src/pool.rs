@@ -0,0 +1,105 @@
+use crate::{constant_product, dynamic_fee, stable_swap, MathError, FEE_DENOMINATOR};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Curve {
+    ConstantProduct,
+    StableSwap,
+}
+
+// The pricing fields of the program's `Config` account
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolConfig {
+    pub curve: Curve,
+    pub fee: u16,
+    pub protocol_fee: u16, // share of `fee` kept by the protocol, in basis points
+    pub protocol_fees_x: u64,
+    pub protocol_fees_y: u64,
+    pub amp_initial: u64,
+    pub amp_target: u64,
+    pub ramp_start_ts: i64,
+    pub ramp_stop_ts: i64,
+    pub dynamic_fee: bool, // when set, swaps pay a fee between `fee_min` and `fee_max` instead of `fee`
+    pub fee_min: u16,
+    pub fee_max: u16,
+    pub volatility: u64, // recent price movement in basis points, as of `volatility_ts`
+    pub volatility_ts: i64,
+}
+
+// Token balances of the pool's vaults and the LP mint supply at the time of the quote
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    pub vault_x: u64,
+    pub vault_y: u64,
+    pub lp_supply: u64,
+}
+
+impl PoolConfig {
+    // Amplification coefficient of a StableSwap pool at `now`
+    pub fn amp(&self, now: i64) -> u64 {
+        if now >= self.ramp_stop_ts || self.ramp_stop_ts <= self.ramp_start_ts {
+            return self.amp_target;
+        }
+
+        let elapsed = now.saturating_sub(self.ramp_start_ts).max(0) as u128;
+        let duration = (self.ramp_stop_ts - self.ramp_start_ts) as u128;
+        let (initial, target) = (self.amp_initial as u128, self.amp_target as u128);
+
+        match target > initial {
+            true => (initial + (target - initial) * elapsed / duration) as u64,
+            false => (initial - (initial - target) * elapsed / duration) as u64,
+        }
+    }
+
+    // Protocol fees sit in the vaults until collected, so they are excluded from the pool reserves
+    pub fn reserves(&self, vault_x: u64, vault_y: u64) -> Result<(u64, u64), MathError> {
+        let x = vault_x
+            .checked_sub(self.protocol_fees_x)
+            .ok_or(MathError::Overflow)?;
+        let y = vault_y
+            .checked_sub(self.protocol_fees_y)
+            .ok_or(MathError::Overflow)?;
+        Ok((x, y))
+    }
+
+    // Marginal prices of x in y and of y in x on the pool's curve at `now`, as Q64.64 fixed point
+    // numbers. Only constant product pools are priced at the reserve ratio
+    pub fn spot_prices(
+        &self,
+        reserve_x: u64,
+        reserve_y: u64,
+        now: i64,
+    ) -> Result<(u128, u128), MathError> {
+        match self.curve {
+            Curve::ConstantProduct => constant_product::spot_prices(reserve_x, reserve_y),
+            Curve::StableSwap => stable_swap::spot_prices(self.amp(now), reserve_x, reserve_y),
+        }
+    }
+
+    // Fee rate in basis points that a swap at `now` pays
+    pub fn swap_fee_rate(&self, now: i64) -> u16 {
+        match self.dynamic_fee {
+            true => dynamic_fee::fee(self.fee_min, self.fee_max, self.volatility(now)),
+            false => self.fee,
+        }
+    }
+
+    // Recent price movement at `now`, decayed since it was last recorded
+    pub fn volatility(&self, now: i64) -> u64 {
+        dynamic_fee::decay(self.volatility, self.volatility_ts, now)
+    }
+
+    // Swap fee taken out of `amount_in` at `now`, rounded down
+    pub fn swap_fee(&self, amount_in: u64, now: i64) -> u64 {
+        (amount_in as u128 * self.swap_fee_rate(now) as u128 / FEE_DENOMINATOR as u128) as u64
+    }
+
+    // Flash loan fee at the base `fee`, rounded up so that small loans are never free
+    pub fn flash_loan_fee(&self, amount: u64) -> u64 {
+        (amount as u128 * self.fee as u128).div_ceil(FEE_DENOMINATOR as u128) as u64
+    }
+
+    // Part of a swap fee that is set aside for the protocol
+    pub fn protocol_fee_amount(&self, fee: u64) -> u64 {
+        (fee as u128 * self.protocol_fee as u128 / FEE_DENOMINATOR as u128) as u64
+    }
+}
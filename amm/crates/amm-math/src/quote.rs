@@ -0,0 +1,420 @@
+use crate::{
+    constant_product, stable_swap, stable_swap::U256, Curve, MathError, PoolConfig, Snapshot,
+    MINIMUM_LIQUIDITY,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SwapQuote {
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,          // taken out of `amount_in` and left in the pool
+    pub protocol_fee: u64, // part of `fee` set aside for the protocol
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DepositQuote {
+    pub x: u64,
+    pub y: u64,
+    pub lp: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WithdrawQuote {
+    pub x: u64,
+    pub y: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DepositSingleQuote {
+    pub swap: SwapQuote,
+    pub lp: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WithdrawSingleQuote {
+    pub swap: SwapQuote,
+    pub amount_out: u64,
+}
+
+// LP minted to the first depositor: the geometric mean of the deposited amounts, minus the
+// minimum liquidity that gets locked
+pub fn quote_initial_deposit(x: u64, y: u64) -> Result<DepositQuote, MathError> {
+    let liquidity = (U256::from(x) * U256::from(y)).integer_sqrt().low_u64();
+    if liquidity <= MINIMUM_LIQUIDITY {
+        return Err(MathError::InitialLiquidityTooLow);
+    }
+
+    Ok(DepositQuote {
+        x,
+        y,
+        lp: liquidity - MINIMUM_LIQUIDITY,
+    })
+}
+
+// Liquidity is added pro rata on both curves, rounded up in favour of the pool
+pub fn quote_deposit(
+    config: &PoolConfig,
+    snapshot: &Snapshot,
+    lp_amount: u64,
+) -> Result<DepositQuote, MathError> {
+    let (reserve_x, reserve_y) = config.reserves(snapshot.vault_x, snapshot.vault_y)?;
+    let (x, y) = deposit_amounts(reserve_x, reserve_y, snapshot.lp_supply, lp_amount)?;
+
+    Ok(DepositQuote {
+        x,
+        y,
+        lp: lp_amount,
+    })
+}
+
+// Liquidity is removed pro rata on both curves, rounded down in favour of the pool
+pub fn quote_withdraw(
+    config: &PoolConfig,
+    snapshot: &Snapshot,
+    lp_amount: u64,
+) -> Result<WithdrawQuote, MathError> {
+    let (reserve_x, reserve_y) = config.reserves(snapshot.vault_x, snapshot.vault_y)?;
+    let (x, y) = withdraw_amounts(reserve_x, reserve_y, snapshot.lp_supply, lp_amount)?;
+
+    Ok(WithdrawQuote { x, y })
+}
+
+pub fn quote_swap(
+    config: &PoolConfig,
+    snapshot: &Snapshot,
+    is_x: bool,
+    amount_in: u64,
+    now: i64,
+) -> Result<SwapQuote, MathError> {
+    let (reserve_x, reserve_y) = config.reserves(snapshot.vault_x, snapshot.vault_y)?;
+    swap(config, reserve_x, reserve_y, is_x, amount_in, now)
+}
+
+// Single-sided deposit: part of `amount_in` is swapped against the pool so that the rest and the
+// swap output are in the pool's post-swap ratio, then both are added as liquidity. The split is
+// found by bisection on the curve itself, so it works the same for every curve type.
+pub fn quote_deposit_single(
+    config: &PoolConfig,
+    snapshot: &Snapshot,
+    is_x: bool,
+    amount_in: u64,
+    now: i64,
+) -> Result<DepositSingleQuote, MathError> {
+    let (reserve_x, reserve_y) = config.reserves(snapshot.vault_x, snapshot.vault_y)?;
+    let (reserve_in, reserve_out) = match is_x {
+        true => (reserve_x, reserve_y),
+        false => (reserve_y, reserve_x),
+    };
+
+    // Largest swap that still leaves the remaining input at least proportional to the output
+    let (mut low, mut high) = (0, amount_in);
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+        let quote = swap(config, reserve_x, reserve_y, is_x, mid, now)?;
+        let (pool_in, pool_out) = post_swap_reserves(reserve_in, reserve_out, &quote)?;
+
+        match (amount_in - mid) as u128 * pool_out as u128
+            >= quote.amount_out as u128 * pool_in as u128
+        {
+            true => low = mid,
+            false => high = mid - 1,
+        }
+    }
+    if low == 0 {
+        return Err(MathError::InvalidAmount);
+    }
+
+    let quote = swap(config, reserve_x, reserve_y, is_x, low, now)?;
+    let (pool_in, pool_out) = post_swap_reserves(reserve_in, reserve_out, &quote)?;
+
+    let lp = mul_div(amount_in - low, snapshot.lp_supply, pool_in)?.min(mul_div(
+        quote.amount_out,
+        snapshot.lp_supply,
+        pool_out,
+    )?);
+
+    Ok(DepositSingleQuote { swap: quote, lp })
+}
+
+// Single-sided withdrawal: liquidity is removed pro rata, then the unwanted side is swapped
+// against what is left in the pool
+pub fn quote_withdraw_single(
+    config: &PoolConfig,
+    snapshot: &Snapshot,
+    is_x: bool,
+    lp_amount: u64,
+    now: i64,
+) -> Result<WithdrawSingleQuote, MathError> {
+    let (reserve_x, reserve_y) = config.reserves(snapshot.vault_x, snapshot.vault_y)?;
+    let (x, y) = withdraw_amounts(reserve_x, reserve_y, snapshot.lp_supply, lp_amount)?;
+
+    let (keep, sell) = match is_x {
+        true => (x, y),
+        false => (y, x),
+    };
+
+    let quote = match sell {
+        0 => SwapQuote::default(),
+        _ => swap(config, reserve_x - x, reserve_y - y, !is_x, sell, now)?,
+    };
+
+    let amount_out = keep
+        .checked_add(quote.amount_out)
+        .ok_or(MathError::Overflow)?;
+
+    Ok(WithdrawSingleQuote {
+        swap: quote,
+        amount_out,
+    })
+}
+
+fn swap(
+    config: &PoolConfig,
+    reserve_x: u64,
+    reserve_y: u64,
+    is_x: bool,
+    amount_in: u64,
+    now: i64,
+) -> Result<SwapQuote, MathError> {
+    let (reserve_in, reserve_out) = match is_x {
+        true => (reserve_x, reserve_y),
+        false => (reserve_y, reserve_x),
+    };
+
+    let fee = config.swap_fee(amount_in, now);
+
+    let amount_out = match config.curve {
+        Curve::ConstantProduct => {
+            constant_product::swap_amount_out(reserve_in, reserve_out, amount_in - fee)?
+        }
+        Curve::StableSwap => {
+            stable_swap::swap_amount_out(config.amp(now), reserve_in, reserve_out, amount_in - fee)?
+        }
+    };
+
+    Ok(SwapQuote {
+        amount_in,
+        amount_out,
+        fee,
+        protocol_fee: config.protocol_fee_amount(fee),
+    })
+}
+
+fn deposit_amounts(
+    reserve_x: u64,
+    reserve_y: u64,
+    lp_supply: u64,
+    amount: u64,
+) -> Result<(u64, u64), MathError> {
+    if lp_supply == 0 {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    let amount_of = |reserve: u64| {
+        u64::try_from((reserve as u128 * amount as u128).div_ceil(lp_supply as u128))
+            .map_err(|_| MathError::Overflow)
+    };
+
+    Ok((amount_of(reserve_x)?, amount_of(reserve_y)?))
+}
+
+fn withdraw_amounts(
+    reserve_x: u64,
+    reserve_y: u64,
+    lp_supply: u64,
+    amount: u64,
+) -> Result<(u64, u64), MathError> {
+    if amount > lp_supply {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    Ok((
+        mul_div(reserve_x, amount, lp_supply)?,
+        mul_div(reserve_y, amount, lp_supply)?,
+    ))
+}
+
+// Reserves backing LP after a swap, with the protocol's share of the fee set aside
+fn post_swap_reserves(
+    reserve_in: u64,
+    reserve_out: u64,
+    quote: &SwapQuote,
+) -> Result<(u64, u64), MathError> {
+    let pool_in = reserve_in
+        .checked_add(quote.amount_in - quote.protocol_fee)
+        .ok_or(MathError::Overflow)?;
+    let pool_out = reserve_out
+        .checked_sub(quote.amount_out)
+        .ok_or(MathError::Overflow)?;
+    Ok((pool_in, pool_out))
+}
+
+fn mul_div(a: u64, b: u64, c: u64) -> Result<u64, MathError> {
+    if c == 0 {
+        return Err(MathError::InsufficientLiquidity);
+    }
+    u64::try_from(a as u128 * b as u128 / c as u128).map_err(|_| MathError::Overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Pool {
+        x: u64,
+        y: u64,
+        supply: u64,
+    }
+
+    impl Pool {
+        fn first_deposit(x: u64, y: u64) -> (Pool, u64) {
+            let lp = quote_initial_deposit(x, y).unwrap().lp;
+            let pool = Pool {
+                x,
+                y,
+                supply: lp + MINIMUM_LIQUIDITY,
+            };
+            (pool, lp)
+        }
+
+        fn snapshot(&self) -> Snapshot {
+            Snapshot {
+                vault_x: self.x,
+                vault_y: self.y,
+                lp_supply: self.supply,
+            }
+        }
+
+        fn deposit(&mut self, lp: u64) -> (u64, u64) {
+            let quote =
+                quote_deposit(&config(Curve::ConstantProduct, 0), &self.snapshot(), lp).unwrap();
+            self.x += quote.x;
+            self.y += quote.y;
+            self.supply += lp;
+            (quote.x, quote.y)
+        }
+
+        fn withdraw(&mut self, lp: u64) -> (u64, u64) {
+            let quote =
+                quote_withdraw(&config(Curve::ConstantProduct, 0), &self.snapshot(), lp).unwrap();
+            self.x -= quote.x;
+            self.y -= quote.y;
+            self.supply -= lp;
+            (quote.x, quote.y)
+        }
+
+        fn donate(&mut self, x: u64, y: u64) {
+            self.x += x;
+            self.y += y;
+        }
+    }
+
+    fn config(curve: Curve, fee: u16) -> PoolConfig {
+        PoolConfig {
+            curve,
+            fee,
+            protocol_fee: 0,
+            protocol_fees_x: 0,
+            protocol_fees_y: 0,
+            amp_initial: 100,
+            amp_target: 100,
+            ramp_start_ts: 0,
+            ramp_stop_ts: 0,
+            dynamic_fee: false,
+            fee_min: fee,
+            fee_max: fee,
+            volatility: 0,
+            volatility_ts: 0,
+        }
+    }
+
+    #[test]
+    fn first_deposit_mints_geometric_mean_minus_locked_liquidity() {
+        assert_eq!(
+            quote_initial_deposit(4_000_000, 1_000_000).unwrap().lp,
+            1_999_000
+        );
+        assert_eq!(
+            quote_initial_deposit(1_000_000, 1_000_000).unwrap().lp,
+            999_000
+        );
+    }
+
+    #[test]
+    fn dust_first_deposit_is_rejected() {
+        assert!(quote_initial_deposit(1, 1).is_err());
+        assert!(quote_initial_deposit(1_000, 1_000).is_err());
+        assert_eq!(quote_initial_deposit(1_001, 1_001).unwrap().lp, 1);
+    }
+
+    #[test]
+    fn locked_liquidity_cannot_be_withdrawn() {
+        let (mut pool, lp) = Pool::first_deposit(1_000_000, 1_000_000);
+        pool.withdraw(lp);
+
+        assert_eq!(pool.supply, MINIMUM_LIQUIDITY);
+        assert_eq!((pool.x, pool.y), (1_000, 1_000));
+    }
+
+    #[test]
+    fn inflation_attack_is_unprofitable() {
+        // The attacker seeds the pool with the smallest deposit that mints any LP at all
+        let (mut pool, attacker_lp) = Pool::first_deposit(1_001, 1_001);
+        assert_eq!(attacker_lp, 1);
+
+        // ...then donates straight into the vaults to pump the value of each LP token
+        let donation = 1_000_000_000;
+        pool.donate(donation, donation);
+
+        // The victim deposits into the inflated pool
+        let victim_lp = 1_000;
+        let (victim_x, victim_y) = pool.deposit(victim_lp);
+
+        // The attacker exits with their single LP token
+        let (attacker_x, attacker_y) = pool.withdraw(attacker_lp);
+        let attacker_in = 1_001 + donation;
+        assert!(attacker_x < attacker_in / 1_000 && attacker_y < attacker_in / 1_000);
+
+        // The donation is shared with the locked liquidity and the victim, who loses nothing
+        // beyond rounding
+        let (out_x, out_y) = pool.withdraw(victim_lp);
+        assert!(out_x + 1 >= victim_x && out_y + 1 >= victim_y);
+    }
+
+    #[test]
+    fn zap_in_leaves_little_unused_input() {
+        for curve in [Curve::ConstantProduct, Curve::StableSwap] {
+            let config = config(curve, 30);
+            let (mut pool, _) = Pool::first_deposit(10_000_000, 10_000_000);
+            let zap = quote_deposit_single(&config, &pool.snapshot(), true, 1_000_000, 0).unwrap();
+
+            // Swapping roughly half leaves both sides in the post-swap ratio
+            assert!(zap.swap.amount_in > 450_000 && zap.swap.amount_in < 550_000);
+
+            // The swap output goes straight back in, so only the input side grows
+            pool.x += 1_000_000;
+            pool.supply += zap.lp;
+
+            // The LP minted redeems for almost exactly what was added on each side
+            let (x, y) = pool.withdraw(zap.lp);
+            let (added_x, added_y) = (1_000_000 - zap.swap.amount_in, zap.swap.amount_out);
+            assert!(x <= added_x && x * 1_000 >= added_x * 999);
+            assert!(y <= added_y && y * 1_000 >= added_y * 999);
+        }
+    }
+
+    #[test]
+    fn zap_in_then_out_never_profits() {
+        for curve in [Curve::ConstantProduct, Curve::StableSwap] {
+            let config = config(curve, 30);
+            let (mut pool, _) = Pool::first_deposit(10_000_000, 20_000_000);
+            let zap = quote_deposit_single(&config, &pool.snapshot(), false, 2_000_000, 0).unwrap();
+
+            pool.y += 2_000_000;
+            pool.supply += zap.lp;
+
+            let out = quote_withdraw_single(&config, &pool.snapshot(), false, zap.lp, 0).unwrap();
+            assert!(out.amount_out < 2_000_000);
+        }
+    }
+}
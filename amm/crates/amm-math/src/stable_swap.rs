@@ -0,0 +1,296 @@
+use crate::MathError;
+
+#[allow(clippy::assign_op_pattern, clippy::manual_div_ceil)]
+mod u256 {
+    uint::construct_uint! {
+        pub struct U256(4);
+    }
+    uint::construct_uint! {
+        pub struct U512(8);
+    }
+}
+
+pub use u256::{U256, U512};
+
+pub const MIN_AMP: u64 = 1;
+pub const MAX_AMP: u64 = 1_000_000;
+// Amplification changes must be spread over at least a day and stay within 10x per ramp
+pub const MIN_RAMP_DURATION: i64 = 86_400;
+pub const MAX_AMP_CHANGE: u64 = 10;
+
+const N_COINS: u64 = 2;
+const MAX_ITERATIONS: usize = 255;
+
+// Curve-style StableSwap invariant for two coins:
+//   A * n^n * (x + y) + D = A * D * n^n + D^(n + 1) / (n^n * x * y)
+// Solved for D with Newton's method, as in Curve's `get_D`
+pub fn compute_d(amp: u64, reserve_x: u64, reserve_y: u64) -> Result<u128, MathError> {
+    let sum = reserve_x as u128 + reserve_y as u128;
+    if sum == 0 {
+        return Ok(0);
+    }
+    if reserve_x == 0 || reserve_y == 0 {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    let ann = U256::from(amp) * N_COINS * N_COINS;
+    let sum = U256::from(sum);
+    let x = U256::from(reserve_x) * N_COINS;
+    let y = U256::from(reserve_y) * N_COINS;
+
+    let mut d = sum;
+    for _ in 0..MAX_ITERATIONS {
+        // d_p = D^3 / (4 * x * y)
+        let d_p = checked_mul_div(d, d, x)?;
+        let d_p = checked_mul_div(d_p, d, y)?;
+
+        let numerator = ann
+            .checked_mul(sum)
+            .and_then(|n| n.checked_add(d_p * N_COINS))
+            .ok_or(MathError::Overflow)?;
+        let denominator = (ann - 1)
+            .checked_mul(d)
+            .and_then(|n| n.checked_add(d_p * (N_COINS + 1)))
+            .ok_or(MathError::Overflow)?;
+
+        let d_prev = d;
+        d = checked_mul_div(numerator, d, denominator)?;
+
+        if d.abs_diff(d_prev) <= U256::one() {
+            break;
+        }
+    }
+
+    u128::try_from(d).map_err(|_| MathError::Overflow)
+}
+
+// Reserve of the other side that keeps the invariant at `d` once one side holds `reserve_in`,
+// as in Curve's `get_y`: y^2 + (b - D) * y = c
+pub fn compute_y(amp: u64, reserve_in: u64, d: u128) -> Result<u64, MathError> {
+    if reserve_in == 0 {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    let ann = U256::from(amp) * N_COINS * N_COINS;
+    let d = U256::from(d);
+
+    // c = D^3 / (4 * x * Ann), b = x + D / Ann
+    let c = checked_mul_div(d, d, U256::from(reserve_in) * N_COINS)?;
+    let c = checked_mul_div(c, d, ann * N_COINS)?;
+    let b = U256::from(reserve_in) + d / ann;
+
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let numerator = y
+            .checked_mul(y)
+            .and_then(|n| n.checked_add(c))
+            .ok_or(MathError::Overflow)?;
+        let denominator = (y * 2u64 + b)
+            .checked_sub(d)
+            .filter(|denominator| !denominator.is_zero())
+            .ok_or(MathError::Overflow)?;
+
+        let y_prev = y;
+        y = numerator / denominator;
+
+        if y.abs_diff(y_prev) <= U256::one() {
+            break;
+        }
+    }
+
+    u128::try_from(y)
+        .ok()
+        .and_then(|y| u64::try_from(y).ok())
+        .ok_or(MathError::Overflow)
+}
+
+// Amount out for `amount_in` after the fee has been taken, rounded down in favour of the pool
+pub fn swap_amount_out(
+    amp: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    amount_in: u64,
+) -> Result<u64, MathError> {
+    let d = compute_d(amp, reserve_in, reserve_out)?;
+    let new_reserve_in = reserve_in
+        .checked_add(amount_in)
+        .ok_or(MathError::Overflow)?;
+    let new_reserve_out = compute_y(amp, new_reserve_in, d)?;
+
+    Ok(reserve_out
+        .saturating_sub(new_reserve_out)
+        .saturating_sub(1))
+}
+
+// Marginal prices of x in y and of y in x as Q64.64 fixed point numbers, from the slope of the
+// invariant at the current reserves. Near the peg they stay close to 1 even when the reserve
+// ratio is far from it:
+//   -dy/dx = (Ann + D^3 / (4 * x^2 * y)) / (Ann + D^3 / (4 * x * y^2))
+//          = y * (4 * Ann * x^2 * y + D^3) / (x * (4 * Ann * x * y^2 + D^3))
+pub fn spot_prices(amp: u64, reserve_x: u64, reserve_y: u64) -> Result<(u128, u128), MathError> {
+    if reserve_x == 0 || reserve_y == 0 {
+        return Err(MathError::InsufficientLiquidity);
+    }
+
+    // Wide enough that nothing below can overflow: x * N is at most 2^281
+    let d = U512::from(compute_d(amp, reserve_x, reserve_y)?);
+    let ann = U512::from(amp) * N_COINS * N_COINS;
+    let (x, y) = (U512::from(reserve_x), U512::from(reserve_y));
+    let d_cubed = d * d * d;
+
+    let numerator = y * (ann * 4u64 * x * x * y + d_cubed);
+    let denominator = x * (ann * 4u64 * x * y * y + d_cubed);
+    Ok((
+        q64_ratio(numerator, denominator)?,
+        q64_ratio(denominator, numerator)?,
+    ))
+}
+
+fn q64_ratio(numerator: U512, denominator: U512) -> Result<u128, MathError> {
+    u128::try_from((numerator << 64) / denominator).map_err(|_| MathError::Overflow)
+}
+
+fn checked_mul_div(a: U256, b: U256, c: U256) -> Result<U256, MathError> {
+    a.checked_mul(b)
+        .and_then(|n| n.checked_div(c))
+        .ok_or(MathError::Overflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constant_product;
+
+    const RESERVE: u64 = 1_000_000_000_000;
+
+    fn constant_product_out(reserve_in: u64, reserve_out: u64, amount_in: u64) -> u64 {
+        constant_product::swap_amount_out(reserve_in, reserve_out, amount_in).unwrap()
+    }
+
+    #[test]
+    fn d_of_balanced_pool_is_sum_of_reserves() {
+        for amp in [1, 100, 10_000] {
+            let d = compute_d(amp, RESERVE, RESERVE).unwrap();
+            assert!(d.abs_diff(2 * RESERVE as u128) <= 1);
+        }
+    }
+
+    #[test]
+    fn compute_y_inverts_compute_d() {
+        let d = compute_d(100, RESERVE, RESERVE / 2).unwrap();
+        let y = compute_y(100, RESERVE, d).unwrap();
+        assert!(y.abs_diff(RESERVE / 2) <= 1);
+    }
+
+    #[test]
+    fn invariant_does_not_decrease_after_swap() {
+        let amount_in = RESERVE / 10;
+        let out = swap_amount_out(100, RESERVE, RESERVE, amount_in).unwrap();
+
+        let before = compute_d(100, RESERVE, RESERVE).unwrap();
+        let after = compute_d(100, RESERVE + amount_in, RESERVE - out).unwrap();
+        assert!(after >= before);
+    }
+
+    #[test]
+    fn stable_swap_beats_constant_product_on_balanced_pool() {
+        for amount_in in [RESERVE / 1_000, RESERVE / 100, RESERVE / 10] {
+            let stable = swap_amount_out(100, RESERVE, RESERVE, amount_in).unwrap();
+            let constant = constant_product_out(RESERVE, RESERVE, amount_in);
+
+            assert!(stable > constant);
+            // Pegged assets never trade above parity
+            assert!(stable <= amount_in);
+        }
+    }
+
+    #[test]
+    fn stable_swap_trades_close_to_parity() {
+        let amount_in = RESERVE / 100;
+        let stable = swap_amount_out(100, RESERVE, RESERVE, amount_in).unwrap();
+        let constant = constant_product_out(RESERVE, RESERVE, amount_in);
+
+        // ~1% of the pool moves the constant product price by ~1%, StableSwap by far less
+        assert!(amount_in - stable < (amount_in - constant) / 50);
+    }
+
+    #[test]
+    fn amplification_moves_from_constant_product_towards_constant_sum() {
+        let amount_in = RESERVE / 10;
+        let mut previous = constant_product_out(RESERVE, RESERVE, amount_in);
+
+        for amp in [1, 10, 100, 10_000] {
+            let out = swap_amount_out(amp, RESERVE, RESERVE, amount_in).unwrap();
+            assert!(out > previous);
+            assert!(out <= amount_in);
+            previous = out;
+        }
+    }
+
+    #[test]
+    fn mismatched_decimals_break_the_peg() {
+        // 1M tokens a side, but y has three more decimals than x
+        let (reserve_x, reserve_y) = (1_000_000_000_000, 1_000_000_000_000_000);
+        let one_x = 1_000_000;
+        let one_y = 1_000_000_000;
+
+        // One whole x buys a fraction of a whole y, because the curve sees the raw amounts as a
+        // pool holding a thousand times more y than x rather than a balanced one
+        let out = swap_amount_out(100, reserve_x, reserve_y, one_x).unwrap();
+        assert!(out < one_y / 2);
+
+        // With matching precision the same trade stays at the peg
+        let out = swap_amount_out(100, reserve_x, reserve_x, one_x).unwrap();
+        assert!(out <= one_x && out > one_x * 999 / 1_000);
+    }
+
+    #[test]
+    fn spot_price_of_a_balanced_pool_is_one() {
+        for amp in [1, 100, 10_000] {
+            assert_eq!(
+                spot_prices(amp, RESERVE, RESERVE).unwrap(),
+                (1 << 64, 1 << 64)
+            );
+        }
+    }
+
+    #[test]
+    fn spot_price_matches_a_small_swap() {
+        for (amp, reserve_x, reserve_y) in [
+            (1, RESERVE, 2 * RESERVE),
+            (100, RESERVE, 2 * RESERVE),
+            (100, 10 * RESERVE, RESERVE),
+            (10_000, RESERVE, 3 * RESERVE),
+        ] {
+            let (price_x, price_y) = spot_prices(amp, reserve_x, reserve_y).unwrap();
+            let amount_in = reserve_x / 1_000_000;
+            let out = swap_amount_out(amp, reserve_x, reserve_y, amount_in).unwrap();
+
+            // The swap pays a hair under the marginal price for moving it
+            let swap_price = ((out as u128) << 64) / amount_in as u128;
+            assert!(swap_price <= price_x);
+            assert!(price_x - swap_price < price_x / 10_000);
+            // Prices in both directions are inverses of each other
+            let product = price_x as f64 * price_y as f64 / 2f64.powi(128);
+            assert!((product - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn spot_price_stays_near_the_peg_while_the_reserve_ratio_moves() {
+        // Twice as much y as x: constant product would price x at 2 y
+        let (price_x, _) = spot_prices(100, RESERVE, 2 * RESERVE).unwrap();
+        assert!(price_x > 1 << 64);
+        assert!(price_x < (1 << 64) / 100 * 101);
+    }
+
+    #[test]
+    fn imbalanced_pool_prices_the_scarce_side_higher() {
+        let amount_in = RESERVE / 1_000;
+        // Buying the scarce side yields less than buying the abundant side
+        let scarce = swap_amount_out(100, RESERVE, RESERVE / 10, amount_in).unwrap();
+        let abundant = swap_amount_out(100, RESERVE / 10, RESERVE, amount_in).unwrap();
+        assert!(scarce < amount_in);
+        assert!(abundant > amount_in);
+    }
+}
@@ -0,0 +1,391 @@
+// Property tests driving random sequences of user actions through the quote functions the
+// program executes with, checking that no sequence lets anyone extract value from the pool.
+
+use amm_math::{
+    dynamic_fee, quote_deposit, quote_deposit_single, quote_initial_deposit, quote_swap,
+    quote_withdraw, quote_withdraw_single, stable_swap, stable_swap::U256, Curve, PoolConfig,
+    Snapshot, MINIMUM_LIQUIDITY,
+};
+use proptest::prelude::*;
+
+// User 0 seeds the pool and then never acts again, so it is the passive LP
+const USERS: usize = 4;
+const BALANCE: u64 = 1_000_000_000_000_000;
+
+#[derive(Clone, Debug)]
+enum Action {
+    Deposit {
+        user: usize,
+        lp: u64,
+    },
+    Withdraw {
+        user: usize,
+        share: u64,
+    },
+    Swap {
+        user: usize,
+        is_x: bool,
+        amount: u64,
+    },
+    DepositSingle {
+        user: usize,
+        is_x: bool,
+        amount: u64,
+    },
+    WithdrawSingle {
+        user: usize,
+        is_x: bool,
+        share: u64,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct User {
+    x: u64,
+    y: u64,
+    lp: u64,
+}
+
+struct Pool {
+    config: PoolConfig,
+    vault_x: u64,
+    vault_y: u64,
+    lp_supply: u64,
+    users: [User; USERS],
+}
+
+impl Pool {
+    fn new(config: PoolConfig, x: u64, y: u64) -> Option<Self> {
+        let quote = quote_initial_deposit(x, y).ok()?;
+
+        let mut users = [User {
+            x: BALANCE,
+            y: BALANCE,
+            lp: 0,
+        }; USERS];
+        users[0].x -= x;
+        users[0].y -= y;
+        users[0].lp = quote.lp;
+
+        Some(Self {
+            config,
+            vault_x: x,
+            vault_y: y,
+            lp_supply: quote.lp + MINIMUM_LIQUIDITY,
+            users,
+        })
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            vault_x: self.vault_x,
+            vault_y: self.vault_y,
+            lp_supply: self.lp_supply,
+        }
+    }
+
+    fn reserves(&self) -> (u64, u64) {
+        self.config.reserves(self.vault_x, self.vault_y).unwrap()
+    }
+
+    fn accrue_protocol_fee(&mut self, is_x: bool, protocol_fee: u64) {
+        match is_x {
+            true => self.config.protocol_fees_x += protocol_fee,
+            false => self.config.protocol_fees_y += protocol_fee,
+        }
+    }
+
+    fn share(&self, user: usize, share: u64) -> u64 {
+        (self.users[user].lp as u128 * share as u128 / 10_000) as u64
+    }
+
+    // Applies `action` if the program would accept it, returning whether it did
+    fn apply(&mut self, action: &Action) -> bool {
+        match *action {
+            Action::Deposit { user, lp } => {
+                let Ok(quote) = quote_deposit(&self.config, &self.snapshot(), lp) else {
+                    return false;
+                };
+                let u = &mut self.users[user];
+                if quote.x == 0 || quote.y == 0 || quote.x > u.x || quote.y > u.y {
+                    return false;
+                }
+                u.x -= quote.x;
+                u.y -= quote.y;
+                u.lp += lp;
+                self.vault_x += quote.x;
+                self.vault_y += quote.y;
+                self.lp_supply += lp;
+            }
+            Action::Withdraw { user, share } => {
+                let lp = self.share(user, share);
+                if lp == 0 {
+                    return false;
+                }
+                let quote = quote_withdraw(&self.config, &self.snapshot(), lp).unwrap();
+                let u = &mut self.users[user];
+                u.x += quote.x;
+                u.y += quote.y;
+                u.lp -= lp;
+                self.vault_x -= quote.x;
+                self.vault_y -= quote.y;
+                self.lp_supply -= lp;
+            }
+            Action::Swap { user, is_x, amount } => {
+                let u = self.users[user];
+                if amount > if is_x { u.x } else { u.y } {
+                    return false;
+                }
+                let Ok(quote) = quote_swap(&self.config, &self.snapshot(), is_x, amount, 0) else {
+                    return false;
+                };
+                if quote.amount_out == 0 {
+                    return false;
+                }
+                let u = &mut self.users[user];
+                match is_x {
+                    true => {
+                        u.x -= amount;
+                        u.y += quote.amount_out;
+                        self.vault_x += amount;
+                        self.vault_y -= quote.amount_out;
+                    }
+                    false => {
+                        u.y -= amount;
+                        u.x += quote.amount_out;
+                        self.vault_y += amount;
+                        self.vault_x -= quote.amount_out;
+                    }
+                }
+                self.accrue_protocol_fee(is_x, quote.protocol_fee);
+            }
+            Action::DepositSingle { user, is_x, amount } => {
+                let u = self.users[user];
+                if amount > if is_x { u.x } else { u.y } {
+                    return false;
+                }
+                let Ok(quote) =
+                    quote_deposit_single(&self.config, &self.snapshot(), is_x, amount, 0)
+                else {
+                    return false;
+                };
+                if quote.lp == 0 {
+                    return false;
+                }
+                let u = &mut self.users[user];
+                match is_x {
+                    true => {
+                        u.x -= amount;
+                        self.vault_x += amount;
+                    }
+                    false => {
+                        u.y -= amount;
+                        self.vault_y += amount;
+                    }
+                }
+                u.lp += quote.lp;
+                self.lp_supply += quote.lp;
+                self.accrue_protocol_fee(is_x, quote.swap.protocol_fee);
+            }
+            Action::WithdrawSingle { user, is_x, share } => {
+                let lp = self.share(user, share);
+                if lp == 0 || lp >= self.lp_supply {
+                    return false;
+                }
+                let Ok(quote) = quote_withdraw_single(&self.config, &self.snapshot(), is_x, lp, 0)
+                else {
+                    return false;
+                };
+                if quote.amount_out == 0 {
+                    return false;
+                }
+                let u = &mut self.users[user];
+                match is_x {
+                    true => {
+                        u.x += quote.amount_out;
+                        self.vault_x -= quote.amount_out;
+                    }
+                    false => {
+                        u.y += quote.amount_out;
+                        self.vault_y -= quote.amount_out;
+                    }
+                }
+                u.lp -= lp;
+                self.lp_supply -= lp;
+                self.accrue_protocol_fee(!is_x, quote.swap.protocol_fee);
+            }
+        }
+        true
+    }
+
+    // Curve invariant backing the LP supply: x * y, or D for StableSwap
+    fn invariant(&self) -> U256 {
+        let (x, y) = self.reserves();
+        match self.config.curve {
+            Curve::ConstantProduct => U256::from(x) * U256::from(y),
+            Curve::StableSwap => {
+                U256::from(stable_swap::compute_d(self.config.amp(0), x, y).unwrap())
+            }
+        }
+    }
+
+    // Invariant per LP token, as a cross-multiplication against another pool state so it stays exact.
+    // StableSwap's D is solved iteratively and is only exact to within one unit.
+    fn value_per_share_at_least(&self, before: &(U256, u64)) -> bool {
+        let (invariant, supply) = *before;
+        match self.config.curve {
+            // k grows with the square of the supply
+            Curve::ConstantProduct => {
+                self.invariant() * U256::from(supply) * U256::from(supply)
+                    >= invariant * U256::from(self.lp_supply) * U256::from(self.lp_supply)
+            }
+            Curve::StableSwap => {
+                (self.invariant() + U256::from(2)) * U256::from(supply)
+                    >= invariant * U256::from(self.lp_supply)
+            }
+        }
+    }
+}
+
+fn config() -> impl Strategy<Value = PoolConfig> {
+    (
+        prop_oneof![Just(Curve::ConstantProduct), Just(Curve::StableSwap)],
+        0..=1_000u16,
+        0..=10_000u16,
+        1..=10_000u64,
+        any::<bool>(),
+        0..=1_000u16,
+        0..=dynamic_fee::MAX_VOLATILITY,
+    )
+        .prop_map(
+            |(curve, fee, protocol_fee, amp, dynamic_fee, fee_max, volatility)| PoolConfig {
+                curve,
+                fee,
+                protocol_fee,
+                protocol_fees_x: 0,
+                protocol_fees_y: 0,
+                amp_initial: amp,
+                amp_target: amp,
+                ramp_start_ts: 0,
+                ramp_stop_ts: 0,
+                dynamic_fee,
+                fee_min: fee.min(fee_max),
+                fee_max: fee.max(fee_max),
+                volatility,
+                volatility_ts: 0,
+            },
+        )
+}
+
+fn action() -> impl Strategy<Value = Action> {
+    let user = 1..USERS;
+    let amount = 1..1_000_000_000_000u64;
+    let share = 1..=10_000u64;
+    prop_oneof![
+        (user.clone(), amount.clone()).prop_map(|(user, lp)| Action::Deposit { user, lp }),
+        (user.clone(), share.clone()).prop_map(|(user, share)| Action::Withdraw { user, share }),
+        (user.clone(), any::<bool>(), amount.clone())
+            .prop_map(|(user, is_x, amount)| Action::Swap { user, is_x, amount }),
+        (user.clone(), any::<bool>(), amount)
+            .prop_map(|(user, is_x, amount)| Action::DepositSingle { user, is_x, amount }),
+        (user, any::<bool>(), share).prop_map(|(user, is_x, share)| Action::WithdrawSingle {
+            user,
+            is_x,
+            share
+        }),
+    ]
+}
+
+// Deposits and withdrawals by a single LP cycling in and out of the pool
+fn cycling_action() -> impl Strategy<Value = Action> {
+    prop_oneof![
+        (1..1_000_000_000_000u64).prop_map(|lp| Action::Deposit { user: 1, lp }),
+        (1..=10_000u64).prop_map(|share| Action::Withdraw { user: 1, share }),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn pool_invariants_hold_for_any_sequence(
+        config in config(),
+        x in 1_001..1_000_000_000_000u64,
+        y in 1_001..1_000_000_000_000u64,
+        actions in prop::collection::vec(action(), 1..32),
+    ) {
+        let Some(mut pool) = Pool::new(config, x, y) else {
+            return Ok(());
+        };
+
+        for action in &actions {
+            let before = (pool.invariant(), pool.lp_supply);
+            let (reserve_x, reserve_y) = pool.reserves();
+
+            if !pool.apply(action) {
+                continue;
+            }
+
+            // Swaps only ever grow the curve invariant
+            if let Action::Swap { .. } = action {
+                prop_assert!(pool.invariant() >= before.0, "invariant decreased: {:?}", action);
+            }
+
+            // The passive LP's share is never diluted
+            prop_assert!(pool.value_per_share_at_least(&before), "share value decreased: {:?}", action);
+
+            // Pro rata deposits and withdrawals never move value per share on either side
+            if let Action::Deposit { .. } | Action::Withdraw { .. } = action {
+                let (new_x, new_y) = pool.reserves();
+                prop_assert!(new_x as u128 * before.1 as u128 >= reserve_x as u128 * pool.lp_supply as u128);
+                prop_assert!(new_y as u128 * before.1 as u128 >= reserve_y as u128 * pool.lp_supply as u128);
+            }
+
+            // Tokens are only moved between users and the vaults
+            prop_assert_eq!(
+                pool.users.iter().map(|u| u.x as u128).sum::<u128>() + pool.vault_x as u128,
+                USERS as u128 * BALANCE as u128
+            );
+            prop_assert_eq!(
+                pool.users.iter().map(|u| u.y as u128).sum::<u128>() + pool.vault_y as u128,
+                USERS as u128 * BALANCE as u128
+            );
+
+            // Every LP token is held by a user or locked in the pool, and all of them together
+            // redeem for no more than the reserves, leaving the locked liquidity backed
+            prop_assert_eq!(
+                pool.users.iter().map(|u| u.lp).sum::<u64>() + MINIMUM_LIQUIDITY,
+                pool.lp_supply
+            );
+            let circulating = quote_withdraw(&pool.config, &pool.snapshot(), pool.lp_supply - MINIMUM_LIQUIDITY).unwrap();
+            let (reserve_x, reserve_y) = pool.reserves();
+            prop_assert!(circulating.x < reserve_x && circulating.y < reserve_y);
+        }
+    }
+
+    #[test]
+    fn cycling_liquidity_never_pays_out_more_than_deposited(
+        config in config(),
+        x in 1_001..1_000_000_000_000u64,
+        y in 1_001..1_000_000_000_000u64,
+        actions in prop::collection::vec(cycling_action(), 1..32),
+    ) {
+        let Some(mut pool) = Pool::new(config, x, y) else {
+            return Ok(());
+        };
+        let passive = quote_withdraw(&pool.config, &pool.snapshot(), pool.users[0].lp).unwrap();
+
+        for action in &actions {
+            pool.apply(action);
+        }
+        pool.apply(&Action::Withdraw { user: 1, share: 10_000 });
+
+        // Rounding always favours the pool, so the LP ends with at most what it started with
+        // on both sides, and whatever it lost went to the passive LP
+        prop_assert!(pool.users[1].x <= BALANCE && pool.users[1].y <= BALANCE);
+        prop_assert_eq!(pool.users[1].lp, 0);
+
+        pool.apply(&Action::Withdraw { user: 0, share: 10_000 });
+        prop_assert!(pool.users[0].x >= BALANCE - x + passive.x);
+        prop_assert!(pool.users[0].y >= BALANCE - y + passive.y);
+        prop_assert_eq!(pool.lp_supply, MINIMUM_LIQUIDITY);
+    }
+}
@@ -1,5 +1,5 @@
+use amm_math::MathError;
 use anchor_lang::error_code;
-use constant_product_curve::CurveError;
 
 #[error_code]
 pub enum AmmError {
@@ -19,18 +19,57 @@ pub enum AmmError {
     SlippageExceeded,
     #[msg("Insufficient Liquidity")]
     InsufficientLiquidity,
+    #[msg("Invalid Fee")]
+    InvalidFee,
+    #[msg("Invalid Authority")]
+    InvalidAuthority,
+    #[msg("Pool is Immutable")]
+    PoolImmutable,
+    #[msg("Overflow")]
+    Overflow,
+    #[msg("Observation is older than the oldest stored observation")]
+    ObservationTooOld,
+    #[msg("A flash loan is already active")]
+    FlashLoanActive,
+    #[msg("No active flash loan")]
+    NoFlashLoan,
+    #[msg("Flash loan is not repaid in the same transaction")]
+    FlashLoanNotRepaid,
+    #[msg("Invalid Curve")]
+    InvalidCurve,
+    #[msg("Invalid Amplification Coefficient")]
+    InvalidAmp,
+    #[msg("Invalid Amplification Ramp")]
+    InvalidRamp,
+    #[msg("Initial liquidity is below the minimum")]
+    InitialLiquidityTooLow,
+    #[msg("Route accounts do not form a valid path")]
+    InvalidRoute,
+    #[msg("Pool still has outstanding liquidity")]
+    PoolNotEmpty,
+    #[msg("Order accounts do not match the order")]
+    InvalidOrder,
+    #[msg("No order can be filled at the current pool price")]
+    OrderNotFillable,
+    #[msg("Invalid Tick")]
+    InvalidTick,
+    #[msg("Invalid Price")]
+    InvalidPrice,
+    #[msg("Pool has no room for another initialized tick")]
+    TooManyTicks,
+    #[msg("Position is worth less than the minimum")]
+    PositionTooSmall,
 }
 
-impl From<CurveError> for AmmError {
-    fn from(error: CurveError) -> AmmError {
+impl From<MathError> for AmmError {
+    fn from(error: MathError) -> AmmError {
         match error {
-            CurveError::InvalidPrecision => AmmError::InvalidAmount,
-            CurveError::Overflow => AmmError::InvalidAmount,
-            CurveError::Underflow => AmmError::InvalidAmount,
-            CurveError::InvalidFeeAmount => AmmError::InvalidAmount,
-            CurveError::InsufficientBalance => AmmError::InsufficientBalance,
-            CurveError::ZeroBalance => AmmError::InvalidAmount,
-            CurveError::SlippageLimitExceeded => AmmError::SlippageExceeded,
+            MathError::Overflow => AmmError::Overflow,
+            MathError::InvalidAmount => AmmError::InvalidAmount,
+            MathError::InsufficientLiquidity => AmmError::InsufficientLiquidity,
+            MathError::InitialLiquidityTooLow => AmmError::InitialLiquidityTooLow,
+            MathError::InvalidTick => AmmError::InvalidTick,
+            MathError::InvalidPrice => AmmError::InvalidPrice,
         }
     }
 }
@@ -0,0 +1,273 @@
+use anchor_lang::prelude::*;
+
+use crate::state::{Config, CurveType};
+
+// Pool reserves (excluding uncollected protocol fees) and LP supply around a state change
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default)]
+pub struct PoolState {
+    pub reserve_x: u64,
+    pub reserve_y: u64,
+    pub lp_supply: u64,
+}
+
+impl PoolState {
+    pub fn new(config: &Config, vault_x: u64, vault_y: u64, lp_supply: u64) -> Result<Self> {
+        let (reserve_x, reserve_y) = config.reserves(vault_x, vault_y)?;
+        Ok(Self {
+            reserve_x,
+            reserve_y,
+            lp_supply,
+        })
+    }
+
+    pub fn reserves(&self) -> (u64, u64) {
+        (self.reserve_x, self.reserve_y)
+    }
+}
+
+#[event]
+pub struct PoolInitialized {
+    pub config: Pubkey,
+    pub seed: u64,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub mint_lp: Pubkey,
+    pub authority: Option<Pubkey>,
+    pub fee: u16,
+    pub curve: CurveType,
+    pub amp: u64,
+}
+
+// `x`, `y` and `lp` are what the user sent and received, before any transfer fee
+#[event]
+pub struct LiquidityAdded {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub x: u64,
+    pub y: u64,
+    pub lp: u64,
+    pub before: PoolState,
+    pub after: PoolState,
+}
+
+#[event]
+pub struct LiquidityRemoved {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub x: u64,
+    pub y: u64,
+    pub lp: u64,
+    pub before: PoolState,
+    pub after: PoolState,
+}
+
+#[event]
+pub struct Swapped {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub is_x: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    pub fee_rate: u16, // basis points of `amount_in` charged, which varies with the dynamic fee
+    pub protocol_fee: u64,
+    pub before: PoolState,
+    pub after: PoolState,
+}
+
+#[event]
+pub struct FlashLoanBorrowed {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub is_x: bool,
+    pub amount: u64,
+}
+
+#[event]
+pub struct FlashLoanRepaid {
+    pub config: Pubkey,
+    pub user: Pubkey,
+    pub is_x: bool,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+#[event]
+pub struct PoolLocked {
+    pub config: Pubkey,
+    pub locked: bool,
+}
+
+#[event]
+pub struct FeeUpdated {
+    pub config: Pubkey,
+    pub fee: u16,
+    pub protocol_fee: u16,
+}
+
+#[event]
+pub struct DynamicFeeUpdated {
+    pub config: Pubkey,
+    pub enabled: bool,
+    pub fee_min: u16,
+    pub fee_max: u16,
+}
+
+#[event]
+pub struct AmpRampStarted {
+    pub config: Pubkey,
+    pub amp_initial: u64,
+    pub amp_target: u64,
+    pub ramp_start_ts: i64,
+    pub ramp_stop_ts: i64,
+}
+
+#[event]
+pub struct AuthorityTransferred {
+    pub config: Pubkey,
+    pub old_authority: Option<Pubkey>,
+    pub new_authority: Option<Pubkey>,
+}
+
+#[event]
+pub struct ProtocolFeesCollected {
+    pub config: Pubkey,
+    pub recipient: Pubkey,
+    pub x: u64,
+    pub y: u64,
+}
+
+// `x` and `y` are what was left in the vaults and swept to the authority
+#[event]
+pub struct PoolClosed {
+    pub config: Pubkey,
+    pub authority: Pubkey,
+    pub x: u64,
+    pub y: u64,
+}
+
+#[event]
+pub struct OrderPlaced {
+    pub config: Pubkey,
+    pub order: Pubkey,
+    pub owner: Pubkey,
+    pub is_x: bool,
+    pub amount_in: u64,
+    pub min_amount_out: u64,
+    pub bounty: u64,
+}
+
+#[event]
+pub struct OrderCancelled {
+    pub config: Pubkey,
+    pub order: Pubkey,
+    pub owner: Pubkey,
+    pub amount_in: u64,
+}
+
+// Emitted after the `Swapped` event for the same fill
+#[event]
+pub struct OrderFilled {
+    pub config: Pubkey,
+    pub order: Pubkey,
+    pub owner: Pubkey,
+    pub keeper: Pubkey,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub bounty: u64,
+}
+
+#[event]
+pub struct FarmInitialized {
+    pub config: Pubkey,
+    pub farm: Pubkey,
+    pub reward_mint: Pubkey,
+    pub reward_rate: u64,
+}
+
+// `amount` is what the farm received, after any transfer fee
+#[event]
+pub struct FarmFunded {
+    pub farm: Pubkey,
+    pub amount: u64,
+    pub rewards_remaining: u64,
+}
+
+#[event]
+pub struct RewardRateUpdated {
+    pub farm: Pubkey,
+    pub reward_rate: u64,
+}
+
+// `staked` is the owner's stake after the change
+#[event]
+pub struct LpStaked {
+    pub farm: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub staked: u64,
+}
+
+#[event]
+pub struct LpUnstaked {
+    pub farm: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub staked: u64,
+}
+
+#[event]
+pub struct RewardsHarvested {
+    pub farm: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct ConcentratedPoolInitialized {
+    pub pool: Pubkey,
+    pub seed: u64,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub fee: u16,
+    pub tick_spacing: u16,
+    pub sqrt_price: u128,
+    pub tick_current: i32,
+}
+
+// `x` and `y` are what the vaults received or sent; `liquidity` is the position's after the change
+#[event]
+pub struct PositionModified {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity_delta: i128,
+    pub liquidity: u128,
+    pub x: u64,
+    pub y: u64,
+}
+
+#[event]
+pub struct PositionFeesCollected {
+    pub pool: Pubkey,
+    pub position: Pubkey,
+    pub owner: Pubkey,
+    pub x: u64,
+    pub y: u64,
+}
+
+// `sqrt_price`, `tick_current` and `liquidity` are the pool's after the swap
+#[event]
+pub struct ConcentratedSwapped {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub is_x: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub fee: u64,
+    pub sqrt_price: u128,
+    pub tick_current: i32,
+    pub liquidity: u128,
+}
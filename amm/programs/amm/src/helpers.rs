@@ -0,0 +1,39 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token_2022,
+    token_interface::{
+        get_mint_extension_data, spl_token_2022::extension::transfer_fee::TransferFeeConfig, Mint,
+    },
+};
+
+use crate::error::AmmError;
+
+fn transfer_fee_config(mint: &InterfaceAccount<Mint>) -> Option<TransferFeeConfig> {
+    let mint_info = mint.to_account_info();
+    match *mint_info.owner == token_2022::ID {
+        true => get_mint_extension_data::<TransferFeeConfig>(&mint_info).ok(),
+        false => None,
+    }
+}
+
+// Amount that reaches the destination when `amount` is sent, after any Token-2022 transfer fee
+pub fn amount_after_transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    let fee = match transfer_fee_config(mint) {
+        Some(config) => config
+            .calculate_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(AmmError::Overflow)?,
+        None => 0,
+    };
+    Ok(amount.checked_sub(fee).ok_or(AmmError::Overflow)?)
+}
+
+// Amount that has to be sent so that `amount` reaches the destination after any Token-2022 transfer fee
+pub fn amount_before_transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    let fee = match transfer_fee_config(mint) {
+        Some(config) => config
+            .calculate_inverse_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(AmmError::Overflow)?,
+        None => 0,
+    };
+    Ok(amount.checked_add(fee).ok_or(AmmError::Overflow)?)
+}
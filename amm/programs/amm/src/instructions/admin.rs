@@ -0,0 +1,137 @@
+use amm_math::stable_swap::{MAX_AMP, MAX_AMP_CHANGE, MIN_AMP, MIN_RAMP_DURATION};
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::AmmError,
+    events::{AmpRampStarted, AuthorityTransferred, DynamicFeeUpdated, FeeUpdated, PoolLocked},
+    state::{Config, CurveType},
+};
+
+#[derive(Accounts)]
+pub struct Admin<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        // A pool initialized without an authority can never be changed
+        constraint = config.authority.is_some() @ AmmError::PoolImmutable,
+        constraint = config.authority == Some(authority.key()) @ AmmError::InvalidAuthority,
+    )]
+    pub config: Account<'info, Config>,
+}
+
+impl<'info> Admin<'info> {
+    pub fn lock(&mut self) -> Result<()> {
+        self.config.locked = true;
+        self.emit_pool_locked();
+        Ok(())
+    }
+
+    pub fn unlock(&mut self) -> Result<()> {
+        self.config.locked = false;
+        self.emit_pool_locked();
+        Ok(())
+    }
+
+    pub fn update_fee(&mut self, fee: u16) -> Result<()> {
+        require!(fee <= 10_000, AmmError::InvalidFee);
+        self.config.fee = fee;
+        self.emit_fee_updated();
+        Ok(())
+    }
+
+    pub fn update_protocol_fee(&mut self, protocol_fee: u16) -> Result<()> {
+        require!(protocol_fee <= 10_000, AmmError::InvalidFee);
+        self.config.protocol_fee = protocol_fee;
+        self.emit_fee_updated();
+        Ok(())
+    }
+
+    // Switches swaps between the fixed `fee` and a fee between `fee_min` and `fee_max` that
+    // grows with recent price movement
+    pub fn set_dynamic_fee(&mut self, enabled: bool, fee_min: u16, fee_max: u16) -> Result<()> {
+        require!(
+            fee_min <= fee_max && fee_max <= 10_000,
+            AmmError::InvalidFee
+        );
+        self.config.dynamic_fee = enabled;
+        self.config.fee_min = fee_min;
+        self.config.fee_max = fee_max;
+
+        emit!(DynamicFeeUpdated {
+            config: self.config.key(),
+            enabled,
+            fee_min,
+            fee_max,
+        });
+        Ok(())
+    }
+
+    // Moves the StableSwap amplification linearly from its current value to `target_amp` by `stop_ts`
+    pub fn ramp_amp(&mut self, target_amp: u64, stop_ts: i64) -> Result<()> {
+        require!(
+            self.config.curve == CurveType::StableSwap,
+            AmmError::InvalidCurve
+        );
+        require!(
+            (MIN_AMP..=MAX_AMP).contains(&target_amp),
+            AmmError::InvalidAmp
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            stop_ts >= now.saturating_add(MIN_RAMP_DURATION),
+            AmmError::InvalidRamp
+        );
+
+        let current_amp = self.config.pool_config().amp(now);
+        require!(
+            target_amp <= current_amp.saturating_mul(MAX_AMP_CHANGE)
+                && current_amp <= target_amp.saturating_mul(MAX_AMP_CHANGE),
+            AmmError::InvalidRamp
+        );
+
+        self.config.amp_initial = current_amp;
+        self.config.amp_target = target_amp;
+        self.config.ramp_start_ts = now;
+        self.config.ramp_stop_ts = stop_ts;
+
+        emit!(AmpRampStarted {
+            config: self.config.key(),
+            amp_initial: current_amp,
+            amp_target: target_amp,
+            ramp_start_ts: now,
+            ramp_stop_ts: stop_ts,
+        });
+        Ok(())
+    }
+
+    pub fn transfer_authority(&mut self, new_authority: Option<Pubkey>) -> Result<()> {
+        let old_authority = self.config.authority;
+        self.config.authority = new_authority;
+
+        emit!(AuthorityTransferred {
+            config: self.config.key(),
+            old_authority,
+            new_authority,
+        });
+        Ok(())
+    }
+
+    fn emit_pool_locked(&self) {
+        emit!(PoolLocked {
+            config: self.config.key(),
+            locked: self.config.locked,
+        });
+    }
+
+    fn emit_fee_updated(&self) {
+        emit!(FeeUpdated {
+            config: self.config.key(),
+            fee: self.config.fee,
+            protocol_fee: self.config.protocol_fee,
+        });
+    }
+}
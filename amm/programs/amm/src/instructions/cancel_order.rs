@@ -0,0 +1,132 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+        TransferChecked,
+    },
+};
+
+use crate::{events::OrderCancelled, state::Order};
+
+#[derive(Accounts)]
+pub struct CancelOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_in: InterfaceAccount<'info, Mint>,
+
+    // Closing returns the rent and the unpaid bounty
+    #[account(
+        mut,
+        close = owner,
+        has_one = owner,
+        has_one = mint_in,
+        seeds = [
+            b"order",
+            order.config.as_ref(),
+            owner.key().as_ref(),
+            order.id.to_le_bytes().as_ref(),
+        ],
+        bump = order.bump,
+    )]
+    pub order: Account<'info, Order>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_in,
+        associated_token::authority = order,
+        associated_token::token_program = token_program,
+    )]
+    pub order_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint_in,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program,
+    )]
+    pub owner_in: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CancelOrder<'info> {
+    // Allowed on locked pools too, since the tokens never left the order
+    pub fn cancel_order(&mut self) -> Result<()> {
+        let amount = self.order_vault.amount;
+        if amount > 0 {
+            self.withdraw_token(amount)?;
+        }
+        self.close_vault()?;
+
+        emit!(OrderCancelled {
+            config: self.order.config,
+            order: self.order.key(),
+            owner: self.owner.key(),
+            amount_in: amount,
+        });
+        Ok(())
+    }
+
+    fn withdraw_token(&self, amount: u64) -> Result<()> {
+        let cpi_accounts = TransferChecked {
+            from: self.order_vault.to_account_info(),
+            mint: self.mint_in.to_account_info(),
+            to: self.owner_in.to_account_info(),
+            authority: self.order.to_account_info(),
+        };
+
+        let id = self.order.id.to_le_bytes();
+        let seeds = &[
+            b"order",
+            self.order.config.as_ref(),
+            self.order.owner.as_ref(),
+            &id[..],
+            &[self.order.bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(cpi_context, amount, self.mint_in.decimals)
+    }
+
+    fn close_vault(&self) -> Result<()> {
+        let cpi_accounts = CloseAccount {
+            account: self.order_vault.to_account_info(),
+            destination: self.owner.to_account_info(),
+            authority: self.order.to_account_info(),
+        };
+
+        let id = self.order.id.to_le_bytes();
+        let seeds = &[
+            b"order",
+            self.order.config.as_ref(),
+            self.order.owner.as_ref(),
+            &id[..],
+            &[self.order.bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        close_account(cpi_context)
+    }
+}
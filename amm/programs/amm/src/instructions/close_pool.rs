@@ -0,0 +1,247 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        close_account, set_authority, spl_token_2022::instruction::AuthorityType, transfer_checked,
+        CloseAccount, Mint, SetAuthority, TokenAccount, TokenInterface, TransferChecked,
+    },
+};
+
+use crate::{
+    error::AmmError,
+    events::PoolClosed,
+    state::{Config, Farm, Registry},
+};
+
+#[derive(Accounts)]
+pub struct ClosePool<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        close = authority,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        constraint = config.authority.is_some() @ AmmError::PoolImmutable,
+        constraint = config.authority == Some(authority.key()) @ AmmError::InvalidAuthority,
+    )]
+    pub config: Account<'info, Config>,
+
+    // Frees the pair and fee tier for a new pool
+    #[account(
+        mut,
+        close = authority,
+        has_one = config @ AmmError::InvalidConfig,
+    )]
+    pub registry: Account<'info, Registry>,
+
+    /// CHECK: The pool's farm, which only exists once `init_farm` has run, so it is
+    /// deserialized in the handler when present
+    #[account(
+        seeds = [b"farm", config.key().as_ref()],
+        bump,
+    )]
+    pub farm: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = config,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_lp: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = mint_x,
+        associated_token::authority = authority,
+        associated_token::token_program = token_program,
+    )]
+    pub authority_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = mint_y,
+        associated_token::authority = authority,
+        associated_token::token_program = token_program,
+    )]
+    pub authority_y: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ClosePool<'info> {
+    pub fn close_pool(&mut self) -> Result<()> {
+        require!(
+            self.config.flash_loan_amount == 0,
+            AmmError::FlashLoanActive
+        );
+        // Empty means no LP token is left, not even the locked minimum liquidity, and the
+        // vaults hold nothing but uncollected protocol fees. Reserves behind the locked
+        // liquidity or donations belong to no one the authority could pay out, so a pool
+        // holding them stays open
+        let (x, y) = (self.config.protocol_fees_x, self.config.protocol_fees_y);
+        require!(
+            self.mint_lp.supply == 0 && self.vault_x.amount == x && self.vault_y.amount == y,
+            AmmError::PoolNotEmpty
+        );
+        // Staked LP or unemitted rewards would be stranded without the pool. Rewards that
+        // were already emitted stay harvestable, since `harvest` does not need the pool
+        if !self.farm.data_is_empty() {
+            let farm = Farm::try_deserialize(&mut &self.farm.try_borrow_data()?[..])?;
+            require!(
+                farm.total_staked == 0 && farm.rewards_remaining == 0,
+                AmmError::PoolNotEmpty
+            );
+        }
+
+        if x > 0 {
+            self.withdraw_token(true, x)?;
+        }
+        if y > 0 {
+            self.withdraw_token(false, y)?;
+        }
+
+        self.revoke_lp_mint()?;
+        self.close_vault(self.vault_lp.to_account_info())?;
+        self.close_vault(self.vault_x.to_account_info())?;
+        self.close_vault(self.vault_y.to_account_info())?;
+
+        emit!(PoolClosed {
+            config: self.config.key(),
+            authority: self.authority.key(),
+            x,
+            y,
+        });
+        Ok(())
+    }
+
+    fn withdraw_token(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, decimals) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                self.authority_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                self.authority_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.config.to_account_info(),
+        };
+
+        let seeds = &[
+            b"config",
+            &self.config.seed.to_le_bytes()[..],
+            &[self.config.config_bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(cpi_context, amount, decimals)
+    }
+
+    // Token mints can't be closed, so the empty LP mint stays on chain. Without a mint
+    // authority no LP token can ever be minted from it again
+    fn revoke_lp_mint(&self) -> Result<()> {
+        let cpi_accounts = SetAuthority {
+            current_authority: self.config.to_account_info(),
+            account_or_mint: self.mint_lp.to_account_info(),
+        };
+
+        let seeds = &[
+            b"config",
+            &self.config.seed.to_le_bytes()[..],
+            &[self.config.config_bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        set_authority(cpi_context, AuthorityType::MintTokens, None)
+    }
+
+    fn close_vault(&self, vault: AccountInfo<'info>) -> Result<()> {
+        let cpi_accounts = CloseAccount {
+            account: vault,
+            destination: self.authority.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+
+        let seeds = &[
+            b"config",
+            &self.config.seed.to_le_bytes()[..],
+            &[self.config.config_bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        close_account(cpi_context)
+    }
+}
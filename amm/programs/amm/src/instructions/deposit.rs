@@ -1,12 +1,18 @@
+use amm_math::{Snapshot, MINIMUM_LIQUIDITY};
 use anchor_lang::prelude::*;
 
-use crate::error::AmmError;
+use crate::{
+    error::AmmError,
+    events::{LiquidityAdded, PoolState},
+    helpers::{amount_after_transfer_fee, amount_before_transfer_fee},
+};
 
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{mint_to, transfer, Mint, MintTo, Token, TokenAccount, Transfer},
+    token_interface::{
+        mint_to, transfer_checked, Mint, MintTo, TokenAccount, TokenInterface, TransferChecked,
+    },
 };
-use constant_product_curve::ConstantProduct;
 
 use crate::state::Config;
 
@@ -15,10 +21,17 @@ pub struct Deposit<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
 
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
 
     #[account(
+        mut,
         has_one = mint_x,
         has_one = mint_y,
         seeds = [b"config", config.seed.to_le_bytes().as_ref()],
@@ -30,78 +43,105 @@ pub struct Deposit<'info> {
         mut,
         seeds = [b"lp", config.key().as_ref()],
         bump = config.lp_bump,
+        mint::token_program = token_program,
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = config,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_lp: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = mint_x,
         associated_token::authority = config,
+        associated_token::token_program = token_program,
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = mint_y,
         associated_token::authority = config,
+        associated_token::token_program = token_program,
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = mint_x,
         associated_token::authority = user,
+        associated_token::token_program = token_program,
     )]
-    pub user_x: Account<'info, TokenAccount>,
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = mint_y,
         associated_token::authority = user,
+        associated_token::token_program = token_program,
     )]
-    pub user_y: Account<'info, TokenAccount>,
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         init_if_needed,
         payer = user,
         associated_token::mint = mint_lp,
         associated_token::authority = user,
+        associated_token::token_program = token_program,
     )]
-    pub user_lp: Account<'info, TokenAccount>,
+    pub user_lp: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> Deposit<'info> {
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            vault_x: self.vault_x.amount,
+            vault_y: self.vault_y.amount,
+            lp_supply: self.mint_lp.supply,
+        }
+    }
+
     fn deposit_token(&self, is_x: bool, amount: u64) -> Result<()> {
-        let (from, to) = match is_x {
+        let (from, to, mint, decimals) = match is_x {
             true => (
                 self.user_x.to_account_info(),
                 self.vault_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
             ),
             false => (
                 self.user_y.to_account_info(),
                 self.vault_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
             ),
         };
 
-        let cpi_accounts = Transfer {
+        let cpi_accounts = TransferChecked {
             from,
+            mint,
             to,
             authority: self.user.to_account_info(),
         };
 
         let cpi_context = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
 
-        transfer(cpi_context, amount)
+        transfer_checked(cpi_context, amount, decimals)
     }
 
-    fn mint_lp_token(&self, amount: u64) -> Result<()> {
+    fn mint_lp_token(&self, to: AccountInfo<'info>, amount: u64) -> Result<()> {
         let cpi_accounts: MintTo<'_> = MintTo {
             mint: self.mint_lp.to_account_info(),
-            to: self.user_lp.to_account_info(),
+            to,
             authority: self.config.to_account_info(),
         };
 
@@ -119,33 +159,149 @@ impl<'info> Deposit<'info> {
         mint_to(cpi_context, amount)
     }
     pub fn deposit(&mut self, amount: u64, max_x: u64, max_y: u64) -> Result<()> {
-        require!(self.config.locked == false, AmmError::PoolLocked);
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(
+            self.config.flash_loan_amount == 0,
+            AmmError::FlashLoanActive
+        );
         require!(amount != 0, AmmError::InvalidAmount);
 
-        let (x, y) = match self.mint_lp.supply == 0
-            && self.vault_x.amount == 0
-            && self.vault_y.amount == 0
-        {
-            true => (max_x, max_y),
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+        let before = self.pool_state()?;
+
+        self.config
+            .update_oracle(reserve_x, reserve_y, Clock::get()?.unix_timestamp)?;
+
+        // `x` and `y` are what the user sends; with a transfer-fee mint the vault receives less
+        let first_deposit = self.mint_lp.supply == 0;
+        let (x, y, lp) = match first_deposit {
+            true => {
+                // The first depositor sets the price and gets the geometric mean of what the
+                // vault receives, so `amount` is only the minimum LP they accept
+                let quote = amm_math::quote_initial_deposit(
+                    amount_after_transfer_fee(&self.mint_x, max_x)?,
+                    amount_after_transfer_fee(&self.mint_y, max_y)?,
+                )
+                .map_err(AmmError::from)?;
+                require!(quote.lp >= amount, AmmError::SlippageExceeded);
+
+                (max_x, max_y, quote.lp)
+            }
             false => {
-                let amounts = ConstantProduct::xy_deposit_amounts_from_l(
-                    self.vault_x.amount,
-                    self.vault_y.amount,
-                    self.mint_lp.supply,
+                let quote =
+                    amm_math::quote_deposit(&self.config.pool_config(), &self.snapshot(), amount)
+                        .map_err(AmmError::from)?;
+
+                // Gross the amounts up so the vault receives what the curve asks for
+                (
+                    amount_before_transfer_fee(&self.mint_x, quote.x)?,
+                    amount_before_transfer_fee(&self.mint_y, quote.y)?,
                     amount,
-                    6,
                 )
-                .unwrap();
-
-                (amounts.x, amounts.y)
             }
         };
 
         require!(x <= max_x && y <= max_y, AmmError::SlippageExceeded);
+        require!(
+            amount_after_transfer_fee(&self.mint_x, x)? != 0
+                && amount_after_transfer_fee(&self.mint_y, y)? != 0,
+            AmmError::InvalidAmount
+        );
 
         self.deposit_token(true, x)?;
         self.deposit_token(false, y)?;
 
-        self.mint_lp_token(amount)
+        // Permanently lock the minimum liquidity in the pool's own LP account
+        if first_deposit {
+            self.mint_lp_token(self.vault_lp.to_account_info(), MINIMUM_LIQUIDITY)?;
+        }
+
+        self.mint_lp_token(self.user_lp.to_account_info(), lp)?;
+
+        self.emit_liquidity_added(x, y, lp, before)
+    }
+
+    pub fn deposit_single(&mut self, is_x: bool, amount_in: u64, min_lp_out: u64) -> Result<()> {
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(
+            self.config.flash_loan_amount == 0,
+            AmmError::FlashLoanActive
+        );
+        require!(amount_in != 0, AmmError::InvalidAmount);
+        // The first deposit sets the price, so it has to bring both sides
+        require!(self.mint_lp.supply != 0, AmmError::InsufficientLiquidity);
+
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+        let before = self.pool_state()?;
+
+        let now = Clock::get()?.unix_timestamp;
+        self.config.update_oracle(reserve_x, reserve_y, now)?;
+
+        let mint_in = match is_x {
+            true => &self.mint_x,
+            false => &self.mint_y,
+        };
+        let received = amount_after_transfer_fee(mint_in, amount_in)?;
+
+        // Part of the input is swapped into the other side at the pool fee, and both halves
+        // stay in the vaults, so only the input side is transferred
+        let quote = amm_math::quote_deposit_single(
+            &self.config.pool_config(),
+            &self.snapshot(),
+            is_x,
+            received,
+            now,
+        )
+        .map_err(AmmError::from)?;
+
+        require!(quote.lp != 0, AmmError::InvalidAmount);
+        require!(quote.lp >= min_lp_out, AmmError::SlippageExceeded);
+
+        self.config
+            .accrue_protocol_fee(is_x, quote.swap.protocol_fee)?;
+
+        self.deposit_token(is_x, amount_in)?;
+        self.mint_lp_token(self.user_lp.to_account_info(), quote.lp)?;
+
+        let (x, y) = match is_x {
+            true => (amount_in, 0),
+            false => (0, amount_in),
+        };
+        self.emit_liquidity_added(x, y, quote.lp, before)?;
+
+        // The internal swap moves the price like any other
+        let after = self.pool_state()?;
+        self.config
+            .record_price_move(before.reserves(), after.reserves(), now)
+    }
+
+    fn pool_state(&self) -> Result<PoolState> {
+        PoolState::new(
+            &self.config,
+            self.vault_x.amount,
+            self.vault_y.amount,
+            self.mint_lp.supply,
+        )
+    }
+
+    fn emit_liquidity_added(&mut self, x: u64, y: u64, lp: u64, before: PoolState) -> Result<()> {
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        self.mint_lp.reload()?;
+
+        emit!(LiquidityAdded {
+            config: self.config.key(),
+            user: self.user.key(),
+            x,
+            y,
+            lp,
+            before,
+            after: self.pool_state()?,
+        });
+        Ok(())
     }
 }
@@ -0,0 +1,335 @@
+use amm_math::Snapshot;
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token_interface::{
+        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+        TransferChecked,
+    },
+};
+
+use crate::{
+    error::AmmError,
+    events::{OrderFilled, PoolState, Swapped},
+    helpers::amount_after_transfer_fee,
+    state::{Config, Order},
+};
+
+// Remaining accounts per order: order, order_vault, owner, owner_out
+pub const ORDER_ACCOUNTS: usize = 4;
+pub const MAX_ORDERS: usize = 8;
+
+#[derive(Accounts)]
+pub struct FillOrders<'info> {
+    #[account(mut)]
+    pub keeper: Signer<'info>,
+
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+struct Fill<'info> {
+    order: Account<'info, Order>,
+    order_vault: InterfaceAccount<'info, TokenAccount>,
+    owner: &'info AccountInfo<'info>,
+    owner_out: InterfaceAccount<'info, TokenAccount>,
+}
+
+impl<'info> Fill<'info> {
+    fn load(
+        accounts: &'info [AccountInfo<'info>],
+        config: &Pubkey,
+        token_program: &Pubkey,
+    ) -> Result<Self> {
+        let order = Account::<Order>::try_from(&accounts[0])?;
+        let order_vault = InterfaceAccount::<TokenAccount>::try_from(&accounts[1])?;
+        let owner = &accounts[2];
+        let owner_out = InterfaceAccount::<TokenAccount>::try_from(&accounts[3])?;
+
+        require_keys_eq!(order.config, *config, AmmError::InvalidOrder);
+        require_keys_eq!(owner.key(), order.owner, AmmError::InvalidOrder);
+        require_keys_eq!(
+            order_vault.key(),
+            get_associated_token_address_with_program_id(
+                &order.key(),
+                &order.mint_in,
+                token_program
+            ),
+            AmmError::InvalidOrder
+        );
+        require_keys_eq!(owner_out.mint, order.mint_out, AmmError::InvalidOrder);
+        require_keys_eq!(owner_out.owner, order.owner, AmmError::InvalidOrder);
+        require_keys_eq!(*accounts[3].owner, *token_program, AmmError::InvalidOrder);
+
+        Ok(Self {
+            order,
+            order_vault,
+            owner,
+            owner_out,
+        })
+    }
+}
+
+impl<'info> FillOrders<'info> {
+    // Fills every order whose limit the pool price currently meets and skips the rest
+    pub fn fill_orders(&mut self, remaining_accounts: &'info [AccountInfo<'info>]) -> Result<()> {
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(
+            self.config.flash_loan_amount == 0,
+            AmmError::FlashLoanActive
+        );
+
+        let order_count = remaining_accounts.len() / ORDER_ACCOUNTS;
+        require!(
+            order_count > 0
+                && order_count <= MAX_ORDERS
+                && order_count * ORDER_ACCOUNTS == remaining_accounts.len(),
+            AmmError::InvalidOrder
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let mut filled = 0;
+        for accounts in remaining_accounts.chunks(ORDER_ACCOUNTS) {
+            let fill = Fill::load(accounts, &self.config.key(), &self.token_program.key())?;
+            if self.fill_order(fill, now)? {
+                filled += 1;
+            }
+        }
+
+        require!(filled > 0, AmmError::OrderNotFillable);
+        Ok(())
+    }
+
+    fn fill_order(&mut self, mut fill: Fill<'info>, now: i64) -> Result<bool> {
+        let is_x = fill.order.is_x;
+        let (mint_in, mint_out) = match is_x {
+            true => (&self.mint_x, &self.mint_y),
+            false => (&self.mint_y, &self.mint_x),
+        };
+
+        // Sells the whole vault, so tokens sent to it by anyone else cannot block the close,
+        // priced exactly like a `swap` at the current reserves
+        let amount_in = fill.order_vault.amount;
+        let received = amount_after_transfer_fee(mint_in, amount_in)?;
+        let snapshot = Snapshot {
+            vault_x: self.vault_x.amount,
+            vault_y: self.vault_y.amount,
+            lp_supply: self.mint_lp.supply,
+        };
+        let quote =
+            amm_math::quote_swap(&self.config.pool_config(), &snapshot, is_x, received, now)
+                .map_err(AmmError::from)?;
+
+        if quote.amount_in == 0
+            || quote.amount_out == 0
+            || amount_after_transfer_fee(mint_out, quote.amount_out)? < fill.order.min_amount_out
+        {
+            return Ok(false);
+        }
+
+        let before = PoolState::new(
+            &self.config,
+            self.vault_x.amount,
+            self.vault_y.amount,
+            self.mint_lp.supply,
+        )?;
+        self.config
+            .update_oracle(before.reserve_x, before.reserve_y, now)?;
+        self.config.accrue_protocol_fee(is_x, quote.protocol_fee)?;
+
+        self.deposit_order_token(&fill, amount_in)?;
+        self.withdraw_token(&fill, quote.amount_out)?;
+        self.close_order(&mut fill)?;
+
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+
+        let after = PoolState::new(
+            &self.config,
+            self.vault_x.amount,
+            self.vault_y.amount,
+            self.mint_lp.supply,
+        )?;
+        let fee_rate = self.config.pool_config().swap_fee_rate(now);
+        self.config
+            .record_price_move(before.reserves(), after.reserves(), now)?;
+
+        emit!(Swapped {
+            config: self.config.key(),
+            user: fill.order.owner,
+            is_x,
+            amount_in,
+            amount_out: quote.amount_out,
+            fee: quote.fee,
+            fee_rate,
+            protocol_fee: quote.protocol_fee,
+            before,
+            after,
+        });
+        emit!(OrderFilled {
+            config: self.config.key(),
+            order: fill.order.key(),
+            owner: fill.order.owner,
+            keeper: self.keeper.key(),
+            amount_in,
+            amount_out: quote.amount_out,
+            bounty: fill.order.bounty,
+        });
+        Ok(true)
+    }
+
+    fn deposit_order_token(&self, fill: &Fill<'info>, amount: u64) -> Result<()> {
+        let (to, mint, decimals) = match fill.order.is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from: fill.order_vault.to_account_info(),
+            mint,
+            to,
+            authority: fill.order.to_account_info(),
+        };
+
+        let id = fill.order.id.to_le_bytes();
+        let seeds = &[
+            b"order",
+            fill.order.config.as_ref(),
+            fill.order.owner.as_ref(),
+            &id[..],
+            &[fill.order.bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(cpi_context, amount, decimals)
+    }
+
+    fn withdraw_token(&self, fill: &Fill<'info>, amount: u64) -> Result<()> {
+        let (from, mint, decimals) = match fill.order.is_x {
+            true => (
+                self.vault_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+            ),
+            false => (
+                self.vault_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to: fill.owner_out.to_account_info(),
+            authority: self.config.to_account_info(),
+        };
+
+        let seeds = &[
+            b"config",
+            &self.config.seed.to_le_bytes()[..],
+            &[self.config.config_bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(cpi_context, amount, decimals)
+    }
+
+    // Pays the bounty to the keeper and returns the rent of the order and its vault to the owner
+    fn close_order(&self, fill: &mut Fill<'info>) -> Result<()> {
+        let cpi_accounts = CloseAccount {
+            account: fill.order_vault.to_account_info(),
+            destination: fill.owner.clone(),
+            authority: fill.order.to_account_info(),
+        };
+
+        let id = fill.order.id.to_le_bytes();
+        let seeds = &[
+            b"order",
+            fill.order.config.as_ref(),
+            fill.order.owner.as_ref(),
+            &id[..],
+            &[fill.order.bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        close_account(cpi_context)?;
+
+        if fill.order.bounty > 0 {
+            fill.order.sub_lamports(fill.order.bounty)?;
+            self.keeper.add_lamports(fill.order.bounty)?;
+        }
+
+        // Orders loaded from remaining accounts are not closed by Anchor
+        fill.order.close(fill.owner.clone())
+    }
+}
@@ -0,0 +1,250 @@
+use anchor_lang::{
+    prelude::*,
+    solana_program::sysvar::instructions::{
+        load_current_index_checked, load_instruction_at_checked,
+    },
+    Discriminator,
+};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{
+    error::AmmError,
+    events::{FlashLoanBorrowed, FlashLoanRepaid},
+    helpers::amount_before_transfer_fee,
+    instruction::FlashRepay,
+    state::Config,
+};
+
+// Position of `config` in the `FlashLoan` accounts, used to match the repay instruction
+const CONFIG_ACCOUNT_INDEX: usize = 3;
+
+#[derive(Accounts)]
+pub struct FlashLoan<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = config,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = config,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_x,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_y,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    /// CHECK: This is safe
+    pub instruction_sysvar: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> FlashLoan<'info> {
+    pub fn flash_borrow(&mut self, is_x: bool, amount: u64) -> Result<()> {
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(amount > 0, AmmError::InvalidAmount);
+        require!(
+            self.config.flash_loan_amount == 0,
+            AmmError::FlashLoanActive
+        );
+
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+
+        let reserve = match is_x {
+            true => reserve_x,
+            false => reserve_y,
+        };
+        require!(amount <= reserve, AmmError::InsufficientLiquidity);
+
+        self.verify_repay_instruction()?;
+
+        self.config
+            .update_oracle(reserve_x, reserve_y, Clock::get()?.unix_timestamp)?;
+
+        self.config.flash_loan_amount = amount;
+        self.config.flash_loan_is_x = is_x;
+
+        self.withdraw_token(is_x, amount)?;
+
+        emit!(FlashLoanBorrowed {
+            config: self.config.key(),
+            user: self.user.key(),
+            is_x,
+            amount,
+        });
+        Ok(())
+    }
+
+    pub fn flash_repay(&mut self) -> Result<()> {
+        let amount = self.config.flash_loan_amount;
+        require!(amount > 0, AmmError::NoFlashLoan);
+
+        let is_x = self.config.flash_loan_is_x;
+
+        // The fee stays in the vault and is not set aside for the protocol, so it accrues to LPs
+        let fee = self.config.pool_config().flash_loan_fee(amount);
+
+        let owed = amount.checked_add(fee).ok_or(AmmError::Overflow)?;
+
+        let mint = match is_x {
+            true => &self.mint_x,
+            false => &self.mint_y,
+        };
+        let owed = amount_before_transfer_fee(mint, owed)?;
+
+        self.config.flash_loan_amount = 0;
+        self.config.flash_loan_is_x = false;
+
+        self.deposit_token(is_x, owed)?;
+
+        emit!(FlashLoanRepaid {
+            config: self.config.key(),
+            user: self.user.key(),
+            is_x,
+            amount,
+            fee,
+        });
+        Ok(())
+    }
+
+    // Scan the rest of the transaction for a `flash_repay` against this pool. Once it is known to be
+    // there, the loan is guaranteed to be repaid, since a failing repay reverts the whole transaction.
+    fn verify_repay_instruction(&self) -> Result<()> {
+        let ixs = self.instruction_sysvar.to_account_info();
+        let current = load_current_index_checked(&ixs)? as usize;
+
+        let mut index = current + 1;
+        while let Ok(ix) = load_instruction_at_checked(index, &ixs) {
+            if ix.program_id == crate::ID
+                && ix.data.starts_with(FlashRepay::DISCRIMINATOR)
+                && ix
+                    .accounts
+                    .get(CONFIG_ACCOUNT_INDEX)
+                    .is_some_and(|account| account.pubkey == self.config.key())
+            {
+                return Ok(());
+            }
+            index += 1;
+        }
+
+        err!(AmmError::FlashLoanNotRepaid)
+    }
+
+    fn deposit_token(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, decimals) = match is_x {
+            true => (
+                self.user_x.to_account_info(),
+                self.vault_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+            ),
+            false => (
+                self.user_y.to_account_info(),
+                self.vault_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.user.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        transfer_checked(cpi_context, amount, decimals)
+    }
+
+    fn withdraw_token(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, decimals) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                self.user_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                self.user_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.config.to_account_info(),
+        };
+
+        let seeds = &[
+            b"config",
+            &self.config.seed.to_le_bytes()[..],
+            &[self.config.config_bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(cpi_context, amount, decimals)
+    }
+}
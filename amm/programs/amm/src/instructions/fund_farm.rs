@@ -0,0 +1,109 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+use crate::{
+    error::AmmError,
+    events::{FarmFunded, RewardRateUpdated},
+    state::{Config, Farm},
+};
+
+#[derive(Accounts)]
+pub struct FundFarm<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+        constraint = config.authority.is_some() @ AmmError::PoolImmutable,
+        constraint = config.authority == Some(authority.key()) @ AmmError::InvalidAuthority,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        mut,
+        has_one = config,
+        has_one = reward_mint,
+        seeds = [b"farm", config.key().as_ref()],
+        bump = farm.bump,
+    )]
+    pub farm: Account<'info, Farm>,
+
+    #[account(
+        mint::token_program = reward_token_program
+    )]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm,
+        associated_token::token_program = reward_token_program,
+    )]
+    pub farm_reward: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = reward_mint,
+        token::authority = authority,
+        token::token_program = reward_token_program,
+    )]
+    pub authority_reward: InterfaceAccount<'info, TokenAccount>,
+
+    pub reward_token_program: Interface<'info, TokenInterface>,
+}
+
+impl<'info> FundFarm<'info> {
+    pub fn fund_farm(&mut self, amount: u64) -> Result<()> {
+        require!(amount > 0, AmmError::InvalidAmount);
+
+        // Rewards emitted so far are owed at the old funding level
+        self.farm.update(Clock::get()?.unix_timestamp)?;
+
+        let balance = self.farm_reward.amount;
+        self.deposit_reward(amount)?;
+        self.farm_reward.reload()?;
+
+        // Only what the farm actually received, after any transfer fee, can be emitted
+        let received = self.farm_reward.amount - balance;
+        self.farm.rewards_remaining = self
+            .farm
+            .rewards_remaining
+            .checked_add(received)
+            .ok_or(AmmError::Overflow)?;
+
+        emit!(FarmFunded {
+            farm: self.farm.key(),
+            amount: received,
+            rewards_remaining: self.farm.rewards_remaining,
+        });
+        Ok(())
+    }
+
+    pub fn set_reward_rate(&mut self, reward_rate: u64) -> Result<()> {
+        // Time up to now is emitted at the old rate
+        self.farm.update(Clock::get()?.unix_timestamp)?;
+        self.farm.reward_rate = reward_rate;
+
+        emit!(RewardRateUpdated {
+            farm: self.farm.key(),
+            reward_rate,
+        });
+        Ok(())
+    }
+
+    fn deposit_reward(&self, amount: u64) -> Result<()> {
+        let cpi_accounts = TransferChecked {
+            from: self.authority_reward.to_account_info(),
+            mint: self.reward_mint.to_account_info(),
+            to: self.farm_reward.to_account_info(),
+            authority: self.authority.to_account_info(),
+        };
+
+        let cpi_context =
+            CpiContext::new(self.reward_token_program.to_account_info(), cpi_accounts);
+
+        transfer_checked(cpi_context, amount, self.reward_mint.decimals)
+    }
+}
@@ -0,0 +1,100 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{
+    error::AmmError,
+    events::RewardsHarvested,
+    state::{Farm, Stake},
+};
+
+#[derive(Accounts)]
+pub struct Harvest<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = reward_mint,
+        seeds = [b"farm", farm.config.as_ref()],
+        bump = farm.bump,
+    )]
+    pub farm: Account<'info, Farm>,
+
+    #[account(
+        mut,
+        has_one = farm,
+        seeds = [b"stake", farm.key().as_ref(), user.key().as_ref()],
+        bump = stake.bump,
+    )]
+    pub stake: Account<'info, Stake>,
+
+    #[account(
+        mint::token_program = reward_token_program
+    )]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        associated_token::mint = reward_mint,
+        associated_token::authority = farm,
+        associated_token::token_program = reward_token_program,
+    )]
+    pub farm_reward: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = reward_mint,
+        associated_token::authority = user,
+        associated_token::token_program = reward_token_program,
+    )]
+    pub user_reward: InterfaceAccount<'info, TokenAccount>,
+
+    pub reward_token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> Harvest<'info> {
+    pub fn harvest(&mut self) -> Result<()> {
+        self.farm.update(Clock::get()?.unix_timestamp)?;
+        self.stake.settle(&self.farm)?;
+
+        let amount = self.stake.rewards_owed;
+        require!(amount > 0, AmmError::InvalidAmount);
+        self.stake.rewards_owed = 0;
+
+        self.withdraw_reward(amount)?;
+
+        emit!(RewardsHarvested {
+            farm: self.farm.key(),
+            owner: self.user.key(),
+            amount,
+        });
+        Ok(())
+    }
+
+    fn withdraw_reward(&self, amount: u64) -> Result<()> {
+        let cpi_accounts = TransferChecked {
+            from: self.farm_reward.to_account_info(),
+            mint: self.reward_mint.to_account_info(),
+            to: self.user_reward.to_account_info(),
+            authority: self.farm.to_account_info(),
+        };
+
+        let seeds = &[b"farm", self.farm.config.as_ref(), &[self.farm.bump]];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.reward_token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(cpi_context, amount, self.reward_mint.decimals)
+    }
+}
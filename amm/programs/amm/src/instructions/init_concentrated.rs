@@ -0,0 +1,106 @@
+use amm_math::concentrated::{tick_at_sqrt_price, MAX_SQRT_PRICE, MAX_TICK, MIN_SQRT_PRICE};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{Mint, TokenAccount, TokenInterface},
+};
+
+use crate::{error::AmmError, events::ConcentratedPoolInitialized, state::ConcentratedPool};
+
+#[derive(Accounts)]
+#[instruction(seed: u64)]
+pub struct InitConcentrated<'info> {
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = initializer,
+        space = 8 + ConcentratedPool::INIT_SPACE,
+        seeds = [b"concentrated", seed.to_le_bytes().as_ref()],
+        bump
+    )]
+    pub pool: Account<'info, ConcentratedPool>,
+
+    #[account(
+        init,
+        payer = initializer,
+        associated_token::mint = mint_x,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = initializer,
+        associated_token::mint = mint_y,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> InitConcentrated<'info> {
+    pub fn init_concentrated(
+        &mut self,
+        seed: u64,
+        fee: u16,
+        tick_spacing: u16,
+        sqrt_price: u128, // starting price, see `ConcentratedPool::sqrt_price`
+        bumps: InitConcentratedBumps,
+    ) -> Result<()> {
+        // The swap math divides by what is left of the input after the fee
+        require!(fee < 10_000, AmmError::InvalidFee);
+        require!(
+            tick_spacing > 0 && tick_spacing as i32 <= MAX_TICK,
+            AmmError::InvalidTick
+        );
+        require!(
+            sqrt_price > MIN_SQRT_PRICE && sqrt_price < MAX_SQRT_PRICE,
+            AmmError::InvalidPrice
+        );
+
+        let tick_current = tick_at_sqrt_price(sqrt_price).map_err(AmmError::from)?;
+
+        self.pool.set_inner(ConcentratedPool {
+            seed,
+            mint_x: self.mint_x.key(),
+            mint_y: self.mint_y.key(),
+            fee,
+            tick_spacing,
+            sqrt_price,
+            tick_current,
+            liquidity: 0,
+            fee_growth_global_x: 0,
+            fee_growth_global_y: 0,
+            bump: bumps.pool,
+            ticks: Vec::new(),
+        });
+
+        emit!(ConcentratedPoolInitialized {
+            pool: self.pool.key(),
+            seed,
+            mint_x: self.mint_x.key(),
+            mint_y: self.mint_y.key(),
+            fee,
+            tick_spacing,
+            sqrt_price,
+            tick_current,
+        });
+        Ok(())
+    }
+}
@@ -1,20 +1,33 @@
+use amm_math::stable_swap::{MAX_AMP, MIN_AMP};
 use anchor_lang::prelude::*;
 
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{Mint, Token, TokenAccount},
+    token_interface::{Mint, TokenAccount, TokenInterface},
 };
 
-use crate::state::Config;
+use crate::{
+    error::AmmError,
+    events::PoolInitialized,
+    state::{Config, CurveType, Observation, Registry, OBSERVATIONS},
+};
 
 #[derive(Accounts)]
-#[instruction(seed:u64)]
+#[instruction(seed: u64, fee: u16)]
 pub struct Initialize<'info> {
     #[account(mut)]
     pub initializer: Signer<'info>,
 
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
+    // A pool has a single token program, so both mints must belong to it: a legacy SPL Token
+    // mint cannot be paired with a Token-2022 one
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
 
     #[account(
         init,
@@ -23,24 +36,36 @@ pub struct Initialize<'info> {
         bump,
         mint::decimals = 6,
         mint::authority = config,
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init,
+        payer = initializer,
+        associated_token::mint = mint_lp,
+        associated_token::authority = config,
+        associated_token::token_program = token_program,
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub vault_lp: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         init,
         payer = initializer,
         associated_token::mint = mint_x,
         associated_token::authority = config,
+        associated_token::token_program = token_program,
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         init,
         payer = initializer,
         associated_token::mint = mint_y,
         associated_token::authority = config,
+        associated_token::token_program = token_program,
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         init,
@@ -51,7 +76,22 @@ pub struct Initialize<'info> {
     )]
     pub config: Account<'info, Config>,
 
-    pub token_program: Program<'info, Token>,
+    // Fails if the pair already has a pool at this fee tier
+    #[account(
+        init,
+        payer = initializer,
+        space = 8 + Registry::INIT_SPACE,
+        seeds = [
+            b"registry",
+            mint_x.key().min(mint_y.key()).as_ref(),
+            mint_x.key().max(mint_y.key()).as_ref(),
+            fee.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub registry: Account<'info, Registry>,
+
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
@@ -62,17 +102,75 @@ impl<'info> Initialize<'info> {
         seed: u64,
         fee: u16, // u16 because we use basis points here so can scale to 10k for precision
         authority: Option<Pubkey>,
+        curve: CurveType,
+        amp: u64, // only used by StableSwap pools
         bumps: InitializeBumps,
     ) -> Result<()> {
+        require!(fee <= 10_000, AmmError::InvalidFee);
+
+        let amp = match curve {
+            CurveType::ConstantProduct => 0,
+            CurveType::StableSwap => {
+                require!((MIN_AMP..=MAX_AMP).contains(&amp), AmmError::InvalidAmp);
+                // The invariant pegs raw amounts 1:1, so both sides need the same precision
+                require!(
+                    self.mint_x.decimals == self.mint_y.decimals,
+                    AmmError::InvalidConfig
+                );
+                amp
+            }
+        };
+
         self.config.set_inner(Config {
             seed,
             authority,
             mint_x: self.mint_x.key(),
             mint_y: self.mint_y.key(),
             fee,
+            protocol_fee: 0,
+            protocol_fees_x: 0,
+            protocol_fees_y: 0,
+            curve,
+            amp_initial: amp,
+            amp_target: amp,
+            ramp_start_ts: 0,
+            ramp_stop_ts: 0,
             locked: false,
             config_bump: bumps.config,
             lp_bump: bumps.mint_lp,
+            price_x_cumulative: 0,
+            price_y_cumulative: 0,
+            last_update_ts: 0,
+            observation_index: 0,
+            observations: [Observation::default(); OBSERVATIONS],
+            flash_loan_amount: 0,
+            flash_loan_is_x: false,
+            dynamic_fee: false,
+            fee_min: fee,
+            fee_max: fee,
+            volatility: 0,
+            volatility_ts: 0,
+        });
+        self.config.init_oracle(Clock::get()?.unix_timestamp);
+
+        self.registry.set_inner(Registry {
+            config: self.config.key(),
+            mint_a: self.mint_x.key().min(self.mint_y.key()),
+            mint_b: self.mint_x.key().max(self.mint_y.key()),
+            fee_tier: fee,
+            bump: bumps.registry,
+        });
+
+        emit!(PoolInitialized {
+            config: self.config.key(),
+            seed,
+            mint_x: self.mint_x.key(),
+            mint_y: self.mint_y.key(),
+            mint_lp: self.mint_lp.key(),
+            authority,
+            fee,
+            curve,
+            amp,
         });
         Ok(())
     }
@@ -1,7 +1,41 @@
+pub mod admin;
+pub mod cancel_order;
+pub mod close_pool;
+pub mod collect_protocol_fees;
 pub mod deposit;
+pub mod fill_orders;
+pub mod flash_loan;
+pub mod fund_farm;
+pub mod harvest;
+pub mod init_concentrated;
+pub mod init_farm;
 pub mod initialize;
+pub mod modify_position;
+pub mod observe;
+pub mod place_order;
+pub mod route_swap;
+pub mod stake_lp;
+pub mod swap;
+pub mod swap_concentrated;
 pub mod withdraw;
 
+pub use admin::*;
+pub use cancel_order::*;
+pub use close_pool::*;
+pub use collect_protocol_fees::*;
 pub use deposit::*;
+pub use fill_orders::*;
+pub use flash_loan::*;
+pub use fund_farm::*;
+pub use harvest::*;
+pub use init_concentrated::*;
+pub use init_farm::*;
 pub use initialize::*;
+pub use modify_position::*;
+pub use observe::*;
+pub use place_order::*;
+pub use route_swap::*;
+pub use stake_lp::*;
+pub use swap::*;
+pub use swap_concentrated::*;
 pub use withdraw::*;
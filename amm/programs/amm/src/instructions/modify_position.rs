@@ -0,0 +1,424 @@
+use amm_math::concentrated::{
+    add_liquidity_delta, fee_growth_inside, fees_earned, position_amounts, update_tick, Tick,
+    MAX_TICK, MIN_TICK,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{
+    error::AmmError,
+    events::{PositionFeesCollected, PositionModified},
+    helpers::{amount_after_transfer_fee, amount_before_transfer_fee},
+    state::{ConcentratedPool, Position, MIN_POSITION_AMOUNT},
+};
+
+#[derive(Accounts)]
+#[instruction(tick_lower: i32, tick_upper: i32)]
+pub struct ModifyPosition<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"concentrated", pool.seed.to_le_bytes().as_ref()],
+        bump = pool.bump,
+    )]
+    pub pool: Account<'info, ConcentratedPool>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        space = 8 + Position::INIT_SPACE,
+        seeds = [
+            b"position",
+            pool.key().as_ref(),
+            owner.key().as_ref(),
+            tick_lower.to_le_bytes().as_ref(),
+            tick_upper.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub position: Account<'info, Position>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint_x,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program,
+    )]
+    pub owner_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = mint_y,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program,
+    )]
+    pub owner_y: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> ModifyPosition<'info> {
+    // `max_x` and `max_y` cap what the owner sends, including any transfer fee
+    pub fn increase_liquidity(
+        &mut self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+        max_x: u64,
+        max_y: u64,
+        bumps: ModifyPositionBumps,
+    ) -> Result<()> {
+        require!(liquidity > 0, AmmError::InvalidAmount);
+
+        // A fresh position account starts zeroed
+        if self.position.owner == Pubkey::default() {
+            self.open(tick_lower, tick_upper, bumps)?;
+        }
+
+        let liquidity_delta = i128::try_from(liquidity).map_err(|_| AmmError::Overflow)?;
+        let (x, y) = self.modify(liquidity_delta)?;
+        self.require_min_size()?;
+
+        // The vaults have to receive the full amounts, so any transfer fee is on top
+        let x = amount_before_transfer_fee(&self.mint_x, x)?;
+        let y = amount_before_transfer_fee(&self.mint_y, y)?;
+        require!(x <= max_x && y <= max_y, AmmError::SlippageExceeded);
+
+        self.deposit_token(true, x)?;
+        self.deposit_token(false, y)?;
+
+        self.emit_modified(liquidity_delta, x, y);
+        Ok(())
+    }
+
+    // `min_x` and `min_y` are what the owner must receive, after any transfer fee.
+    // Fees earned so far stay in the position for `collect_fees`
+    pub fn decrease_liquidity(
+        &mut self,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+        min_x: u64,
+        min_y: u64,
+    ) -> Result<()> {
+        self.require_open(tick_lower, tick_upper)?;
+        require!(liquidity > 0, AmmError::InvalidAmount);
+        require!(
+            self.position.liquidity >= liquidity,
+            AmmError::InsufficientBalance
+        );
+
+        let liquidity_delta = -i128::try_from(liquidity).map_err(|_| AmmError::Overflow)?;
+        let (x, y) = self.modify(liquidity_delta)?;
+        self.require_min_size()?;
+        self.pool.prune_ticks();
+
+        require!(
+            amount_after_transfer_fee(&self.mint_x, x)? >= min_x
+                && amount_after_transfer_fee(&self.mint_y, y)? >= min_y,
+            AmmError::SlippageExceeded
+        );
+
+        self.withdraw_token(true, x)?;
+        self.withdraw_token(false, y)?;
+
+        self.emit_modified(liquidity_delta, x, y);
+        Ok(())
+    }
+
+    // Pays out the fees owed to the position, and closes it once it holds no liquidity
+    pub fn collect_fees(&mut self, tick_lower: i32, tick_upper: i32) -> Result<()> {
+        self.require_open(tick_lower, tick_upper)?;
+
+        if self.position.liquidity > 0 {
+            let (lower, upper) = self.position_ticks()?;
+            let inside = fee_growth_inside(&self.pool.state(), &lower, &upper);
+            self.settle_fees(inside)?;
+        }
+
+        let (x, y) = (self.position.fees_owed_x, self.position.fees_owed_y);
+        require!(
+            x > 0 || y > 0 || self.position.liquidity == 0,
+            AmmError::InvalidAmount
+        );
+        self.position.fees_owed_x = 0;
+        self.position.fees_owed_y = 0;
+
+        self.withdraw_token(true, x)?;
+        self.withdraw_token(false, y)?;
+
+        emit!(PositionFeesCollected {
+            pool: self.pool.key(),
+            position: self.position.key(),
+            owner: self.owner.key(),
+            x,
+            y,
+        });
+
+        if self.position.liquidity == 0 {
+            self.position.close(self.owner.to_account_info())?;
+        }
+        Ok(())
+    }
+
+    fn open(&mut self, tick_lower: i32, tick_upper: i32, bumps: ModifyPositionBumps) -> Result<()> {
+        let spacing = self.pool.tick_spacing as i32;
+        require!(
+            tick_lower < tick_upper
+                && tick_lower >= MIN_TICK
+                && tick_upper <= MAX_TICK
+                && tick_lower % spacing == 0
+                && tick_upper % spacing == 0,
+            AmmError::InvalidTick
+        );
+
+        self.position.set_inner(Position {
+            pool: self.pool.key(),
+            owner: self.owner.key(),
+            tick_lower,
+            tick_upper,
+            liquidity: 0,
+            fee_growth_inside_x_last: 0,
+            fee_growth_inside_y_last: 0,
+            fees_owed_x: 0,
+            fees_owed_y: 0,
+            bump: bumps.position,
+        });
+        Ok(())
+    }
+
+    // A zeroed position was only just created by `init_if_needed`, so the range has no position
+    fn require_open(&self, tick_lower: i32, tick_upper: i32) -> Result<()> {
+        require!(
+            self.position.owner == self.owner.key()
+                && self.position.tick_lower == tick_lower
+                && self.position.tick_upper == tick_upper,
+            AmmError::InvalidTick
+        );
+        Ok(())
+    }
+
+    // Applies a liquidity change to the position, its ticks and the pool, and returns the
+    // amounts of x and y it moves, rounded in favour of the pool
+    fn modify(&mut self, liquidity_delta: i128) -> Result<(u64, u64)> {
+        let (tick_lower, tick_upper) = (self.position.tick_lower, self.position.tick_upper);
+        let mut state = self.pool.state();
+
+        // The upper tick sorts after the lower one, so inserting it leaves `lower` in place
+        let lower = self.pool.tick_slot(tick_lower)?;
+        let upper = self.pool.tick_slot(tick_upper)?;
+        let mut lower_tick: Tick = self.pool.ticks[lower].into();
+        let mut upper_tick: Tick = self.pool.ticks[upper].into();
+        update_tick(&mut lower_tick, &state, liquidity_delta, false).map_err(AmmError::from)?;
+        update_tick(&mut upper_tick, &state, liquidity_delta, true).map_err(AmmError::from)?;
+        self.pool.ticks[lower] = lower_tick.into();
+        self.pool.ticks[upper] = upper_tick.into();
+
+        // Fees earned at the old liquidity are settled before it changes
+        self.settle_fees(fee_growth_inside(&state, &lower_tick, &upper_tick))?;
+        self.position.liquidity = add_liquidity_delta(self.position.liquidity, liquidity_delta)
+            .map_err(AmmError::from)?;
+
+        if (tick_lower..tick_upper).contains(&state.tick_current) {
+            state.liquidity =
+                add_liquidity_delta(state.liquidity, liquidity_delta).map_err(AmmError::from)?;
+        }
+
+        let amounts = position_amounts(
+            &state,
+            tick_lower,
+            tick_upper,
+            liquidity_delta.unsigned_abs(),
+            liquidity_delta > 0,
+        )
+        .map_err(AmmError::from)?;
+
+        self.pool.apply(&state);
+        Ok(amounts)
+    }
+
+    // An open position has to be worth at least `MIN_POSITION_AMOUNT` of x or y at the current
+    // price; emptying it entirely is always allowed
+    fn require_min_size(&self) -> Result<()> {
+        if self.position.liquidity == 0 {
+            return Ok(());
+        }
+
+        let (x, y) = position_amounts(
+            &self.pool.state(),
+            self.position.tick_lower,
+            self.position.tick_upper,
+            self.position.liquidity,
+            false,
+        )
+        .map_err(AmmError::from)?;
+        require!(
+            x >= MIN_POSITION_AMOUNT || y >= MIN_POSITION_AMOUNT,
+            AmmError::PositionTooSmall
+        );
+        Ok(())
+    }
+
+    fn position_ticks(&self) -> Result<(Tick, Tick)> {
+        let lower = self.pool.tick(self.position.tick_lower);
+        let upper = self.pool.tick(self.position.tick_upper);
+        match (lower, upper) {
+            (Some(lower), Some(upper)) => Ok((lower, upper)),
+            _ => err!(AmmError::InvalidTick),
+        }
+    }
+
+    fn settle_fees(&mut self, (inside_x, inside_y): (u128, u128)) -> Result<()> {
+        let position = &mut self.position;
+        let earned_x = fees_earned(
+            position.liquidity,
+            inside_x,
+            position.fee_growth_inside_x_last,
+        )
+        .map_err(AmmError::from)?;
+        let earned_y = fees_earned(
+            position.liquidity,
+            inside_y,
+            position.fee_growth_inside_y_last,
+        )
+        .map_err(AmmError::from)?;
+
+        position.fees_owed_x = position
+            .fees_owed_x
+            .checked_add(earned_x)
+            .ok_or(AmmError::Overflow)?;
+        position.fees_owed_y = position
+            .fees_owed_y
+            .checked_add(earned_y)
+            .ok_or(AmmError::Overflow)?;
+        position.fee_growth_inside_x_last = inside_x;
+        position.fee_growth_inside_y_last = inside_y;
+        Ok(())
+    }
+
+    fn emit_modified(&self, liquidity_delta: i128, x: u64, y: u64) {
+        emit!(PositionModified {
+            pool: self.pool.key(),
+            position: self.position.key(),
+            owner: self.owner.key(),
+            tick_lower: self.position.tick_lower,
+            tick_upper: self.position.tick_upper,
+            liquidity_delta,
+            liquidity: self.position.liquidity,
+            x,
+            y,
+        });
+    }
+
+    fn deposit_token(&self, is_x: bool, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let (from, to, mint, decimals) = match is_x {
+            true => (
+                self.owner_x.to_account_info(),
+                self.vault_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+            ),
+            false => (
+                self.owner_y.to_account_info(),
+                self.vault_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.owner.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        transfer_checked(cpi_context, amount, decimals)
+    }
+
+    fn withdraw_token(&self, is_x: bool, amount: u64) -> Result<()> {
+        if amount == 0 {
+            return Ok(());
+        }
+
+        let (from, to, mint, decimals) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                self.owner_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                self.owner_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.pool.to_account_info(),
+        };
+
+        let seeds = &[
+            b"concentrated",
+            &self.pool.seed.to_le_bytes()[..],
+            &[self.pool.bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(cpi_context, amount, decimals)
+    }
+}
@@ -0,0 +1,149 @@
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{
+    error::AmmError,
+    events::OrderPlaced,
+    state::{Config, Order},
+};
+
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct PlaceOrder<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_in: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_out: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        init,
+        payer = owner,
+        space = 8 + Order::INIT_SPACE,
+        seeds = [
+            b"order",
+            config.key().as_ref(),
+            owner.key().as_ref(),
+            id.to_le_bytes().as_ref(),
+        ],
+        bump
+    )]
+    pub order: Account<'info, Order>,
+
+    #[account(
+        init,
+        payer = owner,
+        associated_token::mint = mint_in,
+        associated_token::authority = order,
+        associated_token::token_program = token_program,
+    )]
+    pub order_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_in,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program,
+    )]
+    pub owner_in: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> PlaceOrder<'info> {
+    pub fn place_order(
+        &mut self,
+        id: u64,
+        amount_in: u64,
+        min_amount_out: u64,
+        bounty: u64,
+        bumps: PlaceOrderBumps,
+    ) -> Result<()> {
+        require!(amount_in > 0 && min_amount_out > 0, AmmError::InvalidAmount);
+
+        let is_x = match (self.mint_in.key(), self.mint_out.key()) {
+            (a, b) if a == self.config.mint_x && b == self.config.mint_y => true,
+            (a, b) if a == self.config.mint_y && b == self.config.mint_x => false,
+            _ => return err!(AmmError::InvalidConfig),
+        };
+
+        self.deposit_token(amount_in)?;
+        if bounty > 0 {
+            self.deposit_bounty(bounty)?;
+        }
+
+        // The order sells what its vault actually holds, after any transfer fee
+        self.order_vault.reload()?;
+        let amount_in = self.order_vault.amount;
+        require!(amount_in > 0, AmmError::InvalidAmount);
+
+        self.order.set_inner(Order {
+            config: self.config.key(),
+            owner: self.owner.key(),
+            id,
+            mint_in: self.mint_in.key(),
+            mint_out: self.mint_out.key(),
+            is_x,
+            amount_in,
+            min_amount_out,
+            bounty,
+            bump: bumps.order,
+        });
+
+        emit!(OrderPlaced {
+            config: self.config.key(),
+            order: self.order.key(),
+            owner: self.owner.key(),
+            is_x,
+            amount_in,
+            min_amount_out,
+            bounty,
+        });
+        Ok(())
+    }
+
+    fn deposit_token(&self, amount: u64) -> Result<()> {
+        let cpi_accounts = TransferChecked {
+            from: self.owner_in.to_account_info(),
+            mint: self.mint_in.to_account_info(),
+            to: self.order_vault.to_account_info(),
+            authority: self.owner.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        transfer_checked(cpi_context, amount, self.mint_in.decimals)
+    }
+
+    // Held on the order account on top of its rent until the order is filled or cancelled
+    fn deposit_bounty(&self, bounty: u64) -> Result<()> {
+        let cpi_accounts = Transfer {
+            from: self.owner.to_account_info(),
+            to: self.order.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new(self.system_program.to_account_info(), cpi_accounts);
+
+        transfer(cpi_context, bounty)
+    }
+}
@@ -0,0 +1,300 @@
+use amm_math::Snapshot;
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::get_associated_token_address_with_program_id,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{
+    error::AmmError,
+    events::{PoolState, Swapped},
+    helpers::amount_after_transfer_fee,
+    state::Config,
+};
+
+// Remaining accounts per hop: config, mint_lp, mint_in, mint_out, vault_in, vault_out
+pub const HOP_ACCOUNTS: usize = 6;
+pub const MAX_HOPS: usize = 4;
+
+#[derive(Accounts)]
+pub struct RouteSwap<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        token::authority = user,
+        token::token_program = token_program,
+    )]
+    pub user_in: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::authority = user,
+        token::token_program = token_program,
+    )]
+    pub user_out: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+struct Hop<'info> {
+    config: Account<'info, Config>,
+    mint_lp: InterfaceAccount<'info, Mint>,
+    mint_in: InterfaceAccount<'info, Mint>,
+    mint_out: InterfaceAccount<'info, Mint>,
+    vault_in: InterfaceAccount<'info, TokenAccount>,
+    vault_out: InterfaceAccount<'info, TokenAccount>,
+    is_x: bool,
+}
+
+impl<'info> Hop<'info> {
+    fn load(accounts: &'info [AccountInfo<'info>], token_program: &Pubkey) -> Result<Self> {
+        let config = Account::<Config>::try_from(&accounts[0])?;
+        let mint_lp = InterfaceAccount::<Mint>::try_from(&accounts[1])?;
+        let mint_in = InterfaceAccount::<Mint>::try_from(&accounts[2])?;
+        let mint_out = InterfaceAccount::<Mint>::try_from(&accounts[3])?;
+        let vault_in = InterfaceAccount::<TokenAccount>::try_from(&accounts[4])?;
+        let vault_out = InterfaceAccount::<TokenAccount>::try_from(&accounts[5])?;
+
+        // Same PDAs the single-pool instructions derive through their seeds constraints
+        let config_key = Pubkey::create_program_address(
+            &[
+                b"config",
+                &config.seed.to_le_bytes()[..],
+                &[config.config_bump],
+            ],
+            &crate::ID,
+        )
+        .map_err(|_| AmmError::InvalidConfig)?;
+        require_keys_eq!(config.key(), config_key, AmmError::InvalidConfig);
+
+        let lp_key = Pubkey::create_program_address(
+            &[b"lp", config.key().as_ref(), &[config.lp_bump]],
+            &crate::ID,
+        )
+        .map_err(|_| AmmError::InvalidConfig)?;
+        require_keys_eq!(mint_lp.key(), lp_key, AmmError::InvalidConfig);
+
+        let is_x = match (mint_in.key(), mint_out.key()) {
+            (a, b) if a == config.mint_x && b == config.mint_y => true,
+            (a, b) if a == config.mint_y && b == config.mint_x => false,
+            _ => return err!(AmmError::InvalidRoute),
+        };
+
+        for mint in [&accounts[2], &accounts[3]] {
+            require_keys_eq!(*mint.owner, *token_program, AmmError::InvalidRoute);
+        }
+
+        require_keys_eq!(
+            vault_in.key(),
+            get_associated_token_address_with_program_id(
+                &config.key(),
+                &mint_in.key(),
+                token_program
+            ),
+            AmmError::InvalidConfig
+        );
+        require_keys_eq!(
+            vault_out.key(),
+            get_associated_token_address_with_program_id(
+                &config.key(),
+                &mint_out.key(),
+                token_program
+            ),
+            AmmError::InvalidConfig
+        );
+
+        Ok(Self {
+            config,
+            mint_lp,
+            mint_in,
+            mint_out,
+            vault_in,
+            vault_out,
+            is_x,
+        })
+    }
+}
+
+impl<'info> RouteSwap<'info> {
+    pub fn route_swap(
+        &mut self,
+        remaining_accounts: &'info [AccountInfo<'info>],
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> Result<()> {
+        require!(amount_in > 0, AmmError::InvalidAmount);
+
+        let hop_count = remaining_accounts.len() / HOP_ACCOUNTS;
+        require!(
+            hop_count > 0
+                && hop_count <= MAX_HOPS
+                && hop_count * HOP_ACCOUNTS == remaining_accounts.len(),
+            AmmError::InvalidRoute
+        );
+
+        // Load every pool up front, so each hop is priced on its vaults before the route
+        // starts moving tokens into them
+        let mut hops = remaining_accounts
+            .chunks(HOP_ACCOUNTS)
+            .map(|accounts| Hop::load(accounts, &self.token_program.key()))
+            .collect::<Result<Vec<_>>>()?;
+
+        for (i, hop) in hops.iter().enumerate() {
+            require!(!hop.config.locked, AmmError::PoolLocked);
+            require!(hop.config.flash_loan_amount == 0, AmmError::FlashLoanActive);
+            // A pool may only appear once, otherwise its snapshot would be stale
+            require!(
+                hops[..i].iter().all(|h| h.config.key() != hop.config.key()),
+                AmmError::InvalidRoute
+            );
+        }
+
+        require_keys_eq!(
+            self.user_in.mint,
+            hops[0].mint_in.key(),
+            AmmError::InvalidRoute
+        );
+        require_keys_eq!(
+            self.user_out.mint,
+            hops[hops.len() - 1].mint_out.key(),
+            AmmError::InvalidRoute
+        );
+        for pair in hops.windows(2) {
+            require_keys_eq!(
+                pair[0].mint_out.key(),
+                pair[1].mint_in.key(),
+                AmmError::InvalidRoute
+            );
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+
+        self.deposit_token(&hops[0], amount_in)?;
+
+        // Each hop sends its output straight into the next pool's vault
+        let mut amount = amount_in;
+        let mut swaps = Vec::with_capacity(hops.len());
+        for i in 0..hops.len() {
+            let hop = &mut hops[i];
+
+            let (vault_x, vault_y) = match hop.is_x {
+                true => (hop.vault_in.amount, hop.vault_out.amount),
+                false => (hop.vault_out.amount, hop.vault_in.amount),
+            };
+            let before = PoolState::new(&hop.config, vault_x, vault_y, hop.mint_lp.supply)?;
+            let (reserve_x, reserve_y) = (before.reserve_x, before.reserve_y);
+
+            hop.config.update_oracle(reserve_x, reserve_y, now)?;
+
+            let received = amount_after_transfer_fee(&hop.mint_in, amount)?;
+
+            let snapshot = Snapshot {
+                vault_x,
+                vault_y,
+                lp_supply: hop.mint_lp.supply,
+            };
+            let quote = amm_math::quote_swap(
+                &hop.config.pool_config(),
+                &snapshot,
+                hop.is_x,
+                received,
+                now,
+            )
+            .map_err(AmmError::from)?;
+
+            require!(
+                quote.amount_in != 0 && quote.amount_out != 0,
+                AmmError::InvalidAmount
+            );
+
+            hop.config
+                .accrue_protocol_fee(hop.is_x, quote.protocol_fee)?;
+
+            let to = match hops.get(i + 1) {
+                Some(next) => next.vault_in.to_account_info(),
+                None => self.user_out.to_account_info(),
+            };
+            self.withdraw_token(&hops[i], to, quote.amount_out)?;
+
+            swaps.push((amount, quote, before));
+            amount = quote.amount_out;
+        }
+
+        require!(
+            amount_after_transfer_fee(&hops[hops.len() - 1].mint_out, amount)? >= min_amount_out,
+            AmmError::SlippageExceeded
+        );
+
+        for (hop, (amount_in, quote, before)) in hops.iter_mut().zip(swaps) {
+            hop.vault_in.reload()?;
+            hop.vault_out.reload()?;
+            let (vault_x, vault_y) = match hop.is_x {
+                true => (hop.vault_in.amount, hop.vault_out.amount),
+                false => (hop.vault_out.amount, hop.vault_in.amount),
+            };
+
+            let after = PoolState::new(&hop.config, vault_x, vault_y, hop.mint_lp.supply)?;
+            let fee_rate = hop.config.pool_config().swap_fee_rate(now);
+            hop.config
+                .record_price_move(before.reserves(), after.reserves(), now)?;
+
+            // Pools loaded from remaining accounts are not persisted by Anchor
+            hop.config.exit(&crate::ID)?;
+
+            emit!(Swapped {
+                config: hop.config.key(),
+                user: self.user.key(),
+                is_x: hop.is_x,
+                amount_in,
+                amount_out: quote.amount_out,
+                fee: quote.fee,
+                fee_rate,
+                protocol_fee: quote.protocol_fee,
+                before,
+                after,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn deposit_token(&self, hop: &Hop<'info>, amount: u64) -> Result<()> {
+        let cpi_accounts = TransferChecked {
+            from: self.user_in.to_account_info(),
+            mint: hop.mint_in.to_account_info(),
+            to: hop.vault_in.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        transfer_checked(cpi_context, amount, hop.mint_in.decimals)
+    }
+
+    fn withdraw_token(&self, hop: &Hop<'info>, to: AccountInfo<'info>, amount: u64) -> Result<()> {
+        let cpi_accounts = TransferChecked {
+            from: hop.vault_out.to_account_info(),
+            mint: hop.mint_out.to_account_info(),
+            to,
+            authority: hop.config.to_account_info(),
+        };
+
+        let seeds = &[
+            b"config",
+            &hop.config.seed.to_le_bytes()[..],
+            &[hop.config.config_bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(cpi_context, amount, hop.mint_out.decimals)
+    }
+}
@@ -0,0 +1,167 @@
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{
+    error::AmmError,
+    events::{LpStaked, LpUnstaked},
+    state::{Config, Farm, Stake},
+};
+
+#[derive(Accounts)]
+pub struct StakeLp<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        seeds = [b"config", config.seed.to_le_bytes().as_ref()],
+        bump = config.config_bump,
+    )]
+    pub config: Account<'info, Config>,
+
+    #[account(
+        seeds = [b"lp", config.key().as_ref()],
+        bump = config.lp_bump,
+        mint::token_program = token_program,
+    )]
+    pub mint_lp: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        has_one = config,
+        seeds = [b"farm", config.key().as_ref()],
+        bump = farm.bump,
+    )]
+    pub farm: Account<'info, Farm>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + Stake::INIT_SPACE,
+        seeds = [b"stake", farm.key().as_ref(), user.key().as_ref()],
+        bump
+    )]
+    pub stake: Account<'info, Stake>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_lp,
+        associated_token::authority = farm,
+        associated_token::token_program = token_program,
+    )]
+    pub farm_lp: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_lp,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_lp: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> StakeLp<'info> {
+    pub fn stake_lp(&mut self, amount: u64, bumps: StakeLpBumps) -> Result<()> {
+        require!(amount > 0, AmmError::InvalidAmount);
+
+        // A fresh stake account starts zeroed
+        if self.stake.owner == Pubkey::default() {
+            self.stake.farm = self.farm.key();
+            self.stake.owner = self.user.key();
+            self.stake.bump = bumps.stake;
+        }
+
+        self.settle()?;
+
+        self.deposit_lp(amount)?;
+
+        self.stake.amount = self
+            .stake
+            .amount
+            .checked_add(amount)
+            .ok_or(AmmError::Overflow)?;
+        self.farm.total_staked = self
+            .farm
+            .total_staked
+            .checked_add(amount)
+            .ok_or(AmmError::Overflow)?;
+        self.stake.checkpoint(&self.farm)?;
+
+        emit!(LpStaked {
+            farm: self.farm.key(),
+            owner: self.user.key(),
+            amount,
+            staked: self.stake.amount,
+        });
+        Ok(())
+    }
+
+    // Rewards accrued up to now stay in the stake account for `harvest`
+    pub fn unstake_lp(&mut self, amount: u64) -> Result<()> {
+        require!(amount > 0, AmmError::InvalidAmount);
+        require!(self.stake.amount >= amount, AmmError::InsufficientBalance);
+
+        self.settle()?;
+
+        self.withdraw_lp(amount)?;
+
+        self.stake.amount -= amount;
+        self.farm.total_staked -= amount;
+        self.stake.checkpoint(&self.farm)?;
+
+        emit!(LpUnstaked {
+            farm: self.farm.key(),
+            owner: self.user.key(),
+            amount,
+            staked: self.stake.amount,
+        });
+        Ok(())
+    }
+
+    fn settle(&mut self) -> Result<()> {
+        self.farm.update(Clock::get()?.unix_timestamp)?;
+        self.stake.settle(&self.farm)
+    }
+
+    fn deposit_lp(&self, amount: u64) -> Result<()> {
+        let cpi_accounts = TransferChecked {
+            from: self.user_lp.to_account_info(),
+            mint: self.mint_lp.to_account_info(),
+            to: self.farm_lp.to_account_info(),
+            authority: self.user.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        transfer_checked(cpi_context, amount, self.mint_lp.decimals)
+    }
+
+    fn withdraw_lp(&self, amount: u64) -> Result<()> {
+        let cpi_accounts = TransferChecked {
+            from: self.farm_lp.to_account_info(),
+            mint: self.mint_lp.to_account_info(),
+            to: self.user_lp.to_account_info(),
+            authority: self.farm.to_account_info(),
+        };
+
+        let config = self.config.key();
+        let seeds = &[b"farm", config.as_ref(), &[self.farm.bump]];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(cpi_context, amount, self.mint_lp.decimals)
+    }
+}
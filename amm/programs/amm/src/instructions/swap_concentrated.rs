@@ -0,0 +1,206 @@
+use amm_math::concentrated::{Tick, MAX_SQRT_PRICE, MIN_SQRT_PRICE};
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
+};
+
+use crate::{
+    error::AmmError,
+    events::ConcentratedSwapped,
+    helpers::{amount_after_transfer_fee, amount_before_transfer_fee},
+    state::{ConcentratedPool, TickState},
+};
+
+#[derive(Accounts)]
+pub struct SwapConcentrated<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        has_one = mint_x,
+        has_one = mint_y,
+        seeds = [b"concentrated", pool.seed.to_le_bytes().as_ref()],
+        bump = pool.bump,
+    )]
+    pub pool: Account<'info, ConcentratedPool>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_x,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint_y,
+        associated_token::authority = pool,
+        associated_token::token_program = token_program,
+    )]
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_x,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        associated_token::mint = mint_y,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SwapConcentrated<'info> {
+    // Stops early once the price reaches `sqrt_price_limit`, taking only the input it used
+    pub fn swap_concentrated(
+        &mut self,
+        is_x: bool,
+        amount_in: u64,
+        min_amount_out: u64,
+        sqrt_price_limit: Option<u128>,
+    ) -> Result<()> {
+        require!(amount_in > 0, AmmError::InvalidAmount);
+
+        let (mint_in, mint_out) = match is_x {
+            true => (&self.mint_x, &self.mint_y),
+            false => (&self.mint_y, &self.mint_x),
+        };
+        // Without a limit the swap may run up to the last tick
+        let sqrt_price_limit = match (sqrt_price_limit, is_x) {
+            (Some(limit), _) => limit,
+            (None, true) => MIN_SQRT_PRICE + 1,
+            (None, false) => MAX_SQRT_PRICE - 1,
+        };
+
+        // Price the swap on what the vault actually receives after any transfer fee
+        let received = amount_after_transfer_fee(mint_in, amount_in)?;
+
+        let mut state = self.pool.state();
+        let mut ticks: Vec<Tick> = self.pool.ticks.iter().map(|tick| (*tick).into()).collect();
+        let quote =
+            amm_math::concentrated::swap(&mut state, &mut ticks, is_x, received, sqrt_price_limit)
+                .map_err(AmmError::from)?;
+
+        require!(quote.amount_out != 0, AmmError::InvalidAmount);
+        require!(
+            amount_after_transfer_fee(mint_out, quote.amount_out)? >= min_amount_out,
+            AmmError::SlippageExceeded
+        );
+
+        let amount_in = match quote.amount_in < received {
+            true => amount_before_transfer_fee(mint_in, quote.amount_in)?,
+            false => amount_in,
+        };
+
+        self.pool.apply(&state);
+        self.pool.ticks = ticks.into_iter().map(TickState::from).collect();
+
+        self.deposit_token(is_x, amount_in)?;
+        self.withdraw_token(!is_x, quote.amount_out)?;
+
+        emit!(ConcentratedSwapped {
+            pool: self.pool.key(),
+            user: self.user.key(),
+            is_x,
+            amount_in,
+            amount_out: quote.amount_out,
+            fee: quote.fee,
+            sqrt_price: state.sqrt_price,
+            tick_current: state.tick_current,
+            liquidity: state.liquidity,
+        });
+        Ok(())
+    }
+
+    fn deposit_token(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, decimals) = match is_x {
+            true => (
+                self.user_x.to_account_info(),
+                self.vault_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+            ),
+            false => (
+                self.user_y.to_account_info(),
+                self.vault_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.user.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new(self.token_program.to_account_info(), cpi_accounts);
+
+        transfer_checked(cpi_context, amount, decimals)
+    }
+
+    fn withdraw_token(&self, is_x: bool, amount: u64) -> Result<()> {
+        let (from, to, mint, decimals) = match is_x {
+            true => (
+                self.vault_x.to_account_info(),
+                self.user_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
+            ),
+            false => (
+                self.vault_y.to_account_info(),
+                self.user_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
+            ),
+        };
+
+        let cpi_accounts = TransferChecked {
+            from,
+            mint,
+            to,
+            authority: self.pool.to_account_info(),
+        };
+
+        let seeds = &[
+            b"concentrated",
+            &self.pool.seed.to_le_bytes()[..],
+            &[self.pool.bump],
+        ];
+
+        let signer_seeds = &[&seeds[..]];
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            cpi_accounts,
+            signer_seeds,
+        );
+
+        transfer_checked(cpi_context, amount, decimals)
+    }
+}
@@ -1,20 +1,34 @@
+use amm_math::Snapshot;
 use anchor_lang::prelude::*;
 use anchor_spl::{
     associated_token::AssociatedToken,
-    token::{burn, transfer, Burn, Mint, Token, TokenAccount, Transfer},
+    token_interface::{
+        burn, transfer_checked, Burn, Mint, TokenAccount, TokenInterface, TransferChecked,
+    },
 };
-use constant_product_curve::{ConstantProduct, XYAmounts};
 
-use crate::{error::AmmError, state::Config};
+use crate::{
+    error::AmmError,
+    events::{LiquidityRemoved, PoolState},
+    helpers::amount_after_transfer_fee,
+    state::Config,
+};
 
 #[derive(Accounts)]
 
 pub struct Withdraw<'info> {
     #[account(mut)]
     pub user: Signer<'info>,
-    pub mint_x: Account<'info, Mint>,
-    pub mint_y: Account<'info, Mint>,
     #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_x: InterfaceAccount<'info, Mint>,
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_y: InterfaceAccount<'info, Mint>,
+    #[account(
+        mut,
         has_one = mint_x,
         has_one = mint_y,
         seeds = [b"config", config.seed.to_le_bytes().as_ref()],
@@ -26,97 +40,213 @@ pub struct Withdraw<'info> {
         mut,
         seeds = [b"lp", config.key().as_ref()],
         bump = config.lp_bump,
+        mint::token_program = token_program,
     )]
-    pub mint_lp: Account<'info, Mint>,
+    pub mint_lp: InterfaceAccount<'info, Mint>,
 
     #[account(
         mut,
         associated_token::mint = mint_x,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program,
     )]
-    pub vault_x: Account<'info, TokenAccount>,
+    pub vault_x: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = mint_y,
-        associated_token::authority = config
+        associated_token::authority = config,
+        associated_token::token_program = token_program,
     )]
-    pub vault_y: Account<'info, TokenAccount>,
+    pub vault_y: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = mint_x,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
     )]
-    pub user_x: Account<'info, TokenAccount>,
+    pub user_x: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         mut,
         associated_token::mint = mint_y,
-        associated_token::authority = user
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
     )]
-    pub user_y: Account<'info, TokenAccount>,
+    pub user_y: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
         init_if_needed,
         payer = user,
         associated_token::mint = mint_lp,
         associated_token::authority = user,
+        associated_token::token_program = token_program,
     )]
-    pub user_lp: Account<'info, TokenAccount>,
+    pub user_lp: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
 }
 
 impl<'info> Withdraw<'info> {
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            vault_x: self.vault_x.amount,
+            vault_y: self.vault_y.amount,
+            lp_supply: self.mint_lp.supply,
+        }
+    }
+
     pub fn withdraw(&mut self, amount: u64, min_x: u64, min_y: u64) -> Result<()> {
-        require!(self.config.locked == false, AmmError::PoolLocked);
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(
+            self.config.flash_loan_amount == 0,
+            AmmError::FlashLoanActive
+        );
         require!(amount > 0, AmmError::InvalidAmount);
         require!(
             self.mint_lp.supply >= amount,
             AmmError::InsufficientLiquidity
         );
 
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+        let before = self.pool_state()?;
+
+        self.config
+            .update_oracle(reserve_x, reserve_y, Clock::get()?.unix_timestamp)?;
+
         let (x, y) = {
-            let amount: XYAmounts = ConstantProduct::xy_withdraw_amounts_from_l(
-                self.vault_x.amount,
-                self.vault_y.amount,
-                self.mint_lp.supply,
-                amount,
-                6,
-            )
-            .unwrap();
-            (amount.x, amount.y)
+            let quote =
+                amm_math::quote_withdraw(&self.config.pool_config(), &self.snapshot(), amount)
+                    .map_err(AmmError::from)?;
+            (quote.x, quote.y)
+        };
+
+        // Check for slippage against what the user actually receives
+        require!(
+            min_x <= amount_after_transfer_fee(&self.mint_x, x)?
+                && min_y <= amount_after_transfer_fee(&self.mint_y, y)?,
+            AmmError::SlippageExceeded
+        );
+
+        self.withdraw_tokens(true, x)?;
+        self.withdraw_tokens(false, y)?;
+
+        self.burn_lp_tokens(amount)?;
+
+        self.emit_liquidity_removed(x, y, amount, before)
+    }
+
+    pub fn withdraw_single(&mut self, is_x: bool, lp_amount: u64, min_out: u64) -> Result<()> {
+        require!(!self.config.locked, AmmError::PoolLocked);
+        require!(
+            self.config.flash_loan_amount == 0,
+            AmmError::FlashLoanActive
+        );
+        require!(lp_amount > 0, AmmError::InvalidAmount);
+        require!(
+            self.mint_lp.supply > lp_amount,
+            AmmError::InsufficientLiquidity
+        );
+
+        let (reserve_x, reserve_y) = self
+            .config
+            .reserves(self.vault_x.amount, self.vault_y.amount)?;
+        let before = self.pool_state()?;
+
+        let now = Clock::get()?.unix_timestamp;
+        self.config.update_oracle(reserve_x, reserve_y, now)?;
+
+        // The other side of the withdrawal is swapped back into the pool at the pool fee
+        let quote = amm_math::quote_withdraw_single(
+            &self.config.pool_config(),
+            &self.snapshot(),
+            is_x,
+            lp_amount,
+            now,
+        )
+        .map_err(AmmError::from)?;
+
+        let mint_out = match is_x {
+            true => &self.mint_x,
+            false => &self.mint_y,
         };
 
-        // Check for slippage
-        require!(min_x <= x && min_y <= y, AmmError::SlippageExceeded);
+        require!(quote.amount_out != 0, AmmError::InvalidAmount);
+        require!(
+            amount_after_transfer_fee(mint_out, quote.amount_out)? >= min_out,
+            AmmError::SlippageExceeded
+        );
+
+        self.config
+            .accrue_protocol_fee(!is_x, quote.swap.protocol_fee)?;
+
+        self.withdraw_tokens(is_x, quote.amount_out)?;
+
+        self.burn_lp_tokens(lp_amount)?;
 
-        let _ = self.withdraw_tokens(true, x);
-        let _ = self.withdraw_tokens(false, y);
+        let (x, y) = match is_x {
+            true => (quote.amount_out, 0),
+            false => (0, quote.amount_out),
+        };
+        self.emit_liquidity_removed(x, y, lp_amount, before)?;
+
+        // The internal swap moves the price like any other
+        let after = self.pool_state()?;
+        self.config
+            .record_price_move(before.reserves(), after.reserves(), now)
+    }
+
+    fn pool_state(&self) -> Result<PoolState> {
+        PoolState::new(
+            &self.config,
+            self.vault_x.amount,
+            self.vault_y.amount,
+            self.mint_lp.supply,
+        )
+    }
 
-        let _ = self.burn_lp_tokens(amount);
+    fn emit_liquidity_removed(&mut self, x: u64, y: u64, lp: u64, before: PoolState) -> Result<()> {
+        self.vault_x.reload()?;
+        self.vault_y.reload()?;
+        self.mint_lp.reload()?;
 
+        emit!(LiquidityRemoved {
+            config: self.config.key(),
+            user: self.user.key(),
+            x,
+            y,
+            lp,
+            before,
+            after: self.pool_state()?,
+        });
         Ok(())
     }
     pub fn withdraw_tokens(&mut self, is_x: bool, amount: u64) -> Result<()> {
-        let (from, to) = match is_x {
+        let (from, to, mint, decimals) = match is_x {
             true => (
                 self.vault_x.to_account_info(),
                 self.user_x.to_account_info(),
+                self.mint_x.to_account_info(),
+                self.mint_x.decimals,
             ),
             false => (
                 self.vault_y.to_account_info(),
                 self.user_y.to_account_info(),
+                self.mint_y.to_account_info(),
+                self.mint_y.decimals,
             ),
         };
 
         let cpi_program = self.token_program.to_account_info();
 
-        let cpi_accounts = Transfer {
+        let cpi_accounts = TransferChecked {
             from,
+            mint,
             to,
             authority: self.config.to_account_info(),
         };
@@ -131,7 +261,7 @@ impl<'info> Withdraw<'info> {
 
         let cpi_ctx = CpiContext::new_with_signer(cpi_program, cpi_accounts, signer_seeds);
 
-        transfer(cpi_ctx, amount)
+        transfer_checked(cpi_ctx, amount, decimals)
     }
 
     pub fn burn_lp_tokens(&mut self, amount: u64) -> Result<()> {
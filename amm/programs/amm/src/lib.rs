@@ -1,5 +1,7 @@
 #![allow(unexpected_cfgs,deprecated)]
 pub mod error;
+pub mod events;
+pub mod helpers;
 pub mod instructions;
 pub mod state;
 
@@ -19,8 +21,11 @@ pub mod amm {
         seed: u64,
         fee: u16,
         authority: Option<Pubkey>,
+        curve: CurveType,
+        amp: u64,
     ) -> Result<()> {
-        ctx.accounts.init(seed, fee, authority, ctx.bumps)?;
+        ctx.accounts
+            .init(seed, fee, authority, curve, amp, ctx.bumps)?;
         Ok(())
     }
 
@@ -33,4 +38,186 @@ pub mod amm {
         ctx.accounts.withdraw(amount, min_x, min_y)?;
         Ok(())
     }
+
+    pub fn deposit_single(
+        ctx: Context<Deposit>,
+        is_x: bool,
+        amount_in: u64,
+        min_lp_out: u64,
+    ) -> Result<()> {
+        ctx.accounts.deposit_single(is_x, amount_in, min_lp_out)
+    }
+
+    pub fn withdraw_single(
+        ctx: Context<Withdraw>,
+        is_x: bool,
+        lp_amount: u64,
+        min_out: u64,
+    ) -> Result<()> {
+        ctx.accounts.withdraw_single(is_x, lp_amount, min_out)
+    }
+
+    pub fn swap(ctx: Context<Swap>, is_x: bool, amount_in: u64, min_amount_out: u64) -> Result<()> {
+        ctx.accounts.swap(is_x, amount_in, min_amount_out)?;
+        Ok(())
+    }
+
+    pub fn route_swap<'info>(
+        ctx: Context<'_, '_, 'info, 'info, RouteSwap<'info>>,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .route_swap(ctx.remaining_accounts, amount_in, min_amount_out)
+    }
+
+    pub fn flash_borrow(ctx: Context<FlashLoan>, is_x: bool, amount: u64) -> Result<()> {
+        ctx.accounts.flash_borrow(is_x, amount)
+    }
+
+    pub fn flash_repay(ctx: Context<FlashLoan>) -> Result<()> {
+        ctx.accounts.flash_repay()
+    }
+
+    pub fn place_order(
+        ctx: Context<PlaceOrder>,
+        id: u64,
+        amount_in: u64,
+        min_amount_out: u64,
+        bounty: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .place_order(id, amount_in, min_amount_out, bounty, ctx.bumps)
+    }
+
+    pub fn cancel_order(ctx: Context<CancelOrder>) -> Result<()> {
+        ctx.accounts.cancel_order()
+    }
+
+    pub fn fill_orders<'info>(ctx: Context<'_, '_, 'info, 'info, FillOrders<'info>>) -> Result<()> {
+        ctx.accounts.fill_orders(ctx.remaining_accounts)
+    }
+
+    pub fn init_farm(ctx: Context<InitFarm>, reward_rate: u64) -> Result<()> {
+        ctx.accounts.init_farm(reward_rate, ctx.bumps)
+    }
+
+    pub fn fund_farm(ctx: Context<FundFarm>, amount: u64) -> Result<()> {
+        ctx.accounts.fund_farm(amount)
+    }
+
+    pub fn set_reward_rate(ctx: Context<FundFarm>, reward_rate: u64) -> Result<()> {
+        ctx.accounts.set_reward_rate(reward_rate)
+    }
+
+    pub fn stake_lp(ctx: Context<StakeLp>, amount: u64) -> Result<()> {
+        ctx.accounts.stake_lp(amount, ctx.bumps)
+    }
+
+    pub fn unstake_lp(ctx: Context<StakeLp>, amount: u64) -> Result<()> {
+        ctx.accounts.unstake_lp(amount)
+    }
+
+    pub fn harvest(ctx: Context<Harvest>) -> Result<()> {
+        ctx.accounts.harvest()
+    }
+
+    pub fn init_concentrated(
+        ctx: Context<InitConcentrated>,
+        seed: u64,
+        fee: u16,
+        tick_spacing: u16,
+        sqrt_price: u128,
+    ) -> Result<()> {
+        ctx.accounts
+            .init_concentrated(seed, fee, tick_spacing, sqrt_price, ctx.bumps)
+    }
+
+    pub fn increase_liquidity(
+        ctx: Context<ModifyPosition>,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+        max_x: u64,
+        max_y: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .increase_liquidity(tick_lower, tick_upper, liquidity, max_x, max_y, ctx.bumps)
+    }
+
+    pub fn decrease_liquidity(
+        ctx: Context<ModifyPosition>,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+        min_x: u64,
+        min_y: u64,
+    ) -> Result<()> {
+        ctx.accounts
+            .decrease_liquidity(tick_lower, tick_upper, liquidity, min_x, min_y)
+    }
+
+    pub fn collect_fees(
+        ctx: Context<ModifyPosition>,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> Result<()> {
+        ctx.accounts.collect_fees(tick_lower, tick_upper)
+    }
+
+    pub fn swap_concentrated(
+        ctx: Context<SwapConcentrated>,
+        is_x: bool,
+        amount_in: u64,
+        min_amount_out: u64,
+        sqrt_price_limit: Option<u128>,
+    ) -> Result<()> {
+        ctx.accounts
+            .swap_concentrated(is_x, amount_in, min_amount_out, sqrt_price_limit)
+    }
+
+    pub fn observe(ctx: Context<Observe>, seconds_ago: u32) -> Result<Observation> {
+        ctx.accounts.observe(seconds_ago)
+    }
+
+    pub fn lock(ctx: Context<Admin>) -> Result<()> {
+        ctx.accounts.lock()
+    }
+
+    pub fn unlock(ctx: Context<Admin>) -> Result<()> {
+        ctx.accounts.unlock()
+    }
+
+    pub fn update_fee(ctx: Context<Admin>, fee: u16) -> Result<()> {
+        ctx.accounts.update_fee(fee)
+    }
+
+    pub fn update_protocol_fee(ctx: Context<Admin>, protocol_fee: u16) -> Result<()> {
+        ctx.accounts.update_protocol_fee(protocol_fee)
+    }
+
+    pub fn set_dynamic_fee(
+        ctx: Context<Admin>,
+        enabled: bool,
+        fee_min: u16,
+        fee_max: u16,
+    ) -> Result<()> {
+        ctx.accounts.set_dynamic_fee(enabled, fee_min, fee_max)
+    }
+
+    pub fn ramp_amp(ctx: Context<Admin>, target_amp: u64, stop_ts: i64) -> Result<()> {
+        ctx.accounts.ramp_amp(target_amp, stop_ts)
+    }
+
+    pub fn transfer_authority(ctx: Context<Admin>, new_authority: Option<Pubkey>) -> Result<()> {
+        ctx.accounts.transfer_authority(new_authority)
+    }
+
+    pub fn collect_protocol_fees(ctx: Context<CollectProtocolFees>) -> Result<()> {
+        ctx.accounts.collect_protocol_fees()
+    }
+
+    pub fn close_pool(ctx: Context<ClosePool>) -> Result<()> {
+        ctx.accounts.close_pool()
+    }
 }
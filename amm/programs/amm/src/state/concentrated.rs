@@ -0,0 +1,134 @@
+use amm_math::concentrated::{ConcentratedState, Tick};
+use anchor_lang::prelude::*;
+
+use crate::error::AmmError;
+
+// Initialized ticks a concentrated pool can hold; each position range uses up to two
+pub const MAX_TICKS: usize = 64;
+// Least amount of x or y an open position must be worth at the current price. Liquidity alone
+// says little, since a one-tick range holds almost no tokens, so this keeps anyone from filling
+// the tick slots without locking up real capital
+pub const MIN_POSITION_AMOUNT: u64 = 1_000_000;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct TickState {
+    pub index: i32,
+    pub liquidity_net: i128,
+    pub liquidity_gross: u128,
+    pub fee_growth_outside_x: u128,
+    pub fee_growth_outside_y: u128,
+}
+
+// Pool whose liquidity is provided over price ranges, at seeds [b"concentrated", seed].
+// Reserves sit in the pool's own ATAs and there is no authority or protocol fee
+#[account]
+#[derive(InitSpace)]
+pub struct ConcentratedPool {
+    pub seed: u64,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub fee: u16,
+    pub tick_spacing: u16, // position bounds must be multiples of it
+    pub sqrt_price: u128,  // square root of the price of x in y, Q64.64
+    pub tick_current: i32,
+    pub liquidity: u128,           // liquidity in range at the current price
+    pub fee_growth_global_x: u128, // fees per unit of liquidity, Q64.64, wrapping
+    pub fee_growth_global_y: u128,
+    pub bump: u8,
+    #[max_len(MAX_TICKS)]
+    pub ticks: Vec<TickState>, // sorted by index
+}
+
+// Liquidity an owner provides over `tick_lower..tick_upper`, at seeds
+// [b"position", pool, owner, tick_lower, tick_upper]
+#[account]
+#[derive(InitSpace)]
+pub struct Position {
+    pub pool: Pubkey,
+    pub owner: Pubkey,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    // Fee growth inside the range when the position was last settled
+    pub fee_growth_inside_x_last: u128,
+    pub fee_growth_inside_y_last: u128,
+    pub fees_owed_x: u64, // settled but not yet collected
+    pub fees_owed_y: u64,
+    pub bump: u8,
+}
+
+impl From<TickState> for Tick {
+    fn from(tick: TickState) -> Tick {
+        Tick {
+            index: tick.index,
+            liquidity_net: tick.liquidity_net,
+            liquidity_gross: tick.liquidity_gross,
+            fee_growth_outside_x: tick.fee_growth_outside_x,
+            fee_growth_outside_y: tick.fee_growth_outside_y,
+        }
+    }
+}
+
+impl From<Tick> for TickState {
+    fn from(tick: Tick) -> TickState {
+        TickState {
+            index: tick.index,
+            liquidity_net: tick.liquidity_net,
+            liquidity_gross: tick.liquidity_gross,
+            fee_growth_outside_x: tick.fee_growth_outside_x,
+            fee_growth_outside_y: tick.fee_growth_outside_y,
+        }
+    }
+}
+
+impl ConcentratedPool {
+    // Inputs for `amm_math::concentrated`, which prices every position change and swap
+    pub fn state(&self) -> ConcentratedState {
+        ConcentratedState {
+            fee: self.fee,
+            sqrt_price: self.sqrt_price,
+            tick_current: self.tick_current,
+            liquidity: self.liquidity,
+            fee_growth_global_x: self.fee_growth_global_x,
+            fee_growth_global_y: self.fee_growth_global_y,
+        }
+    }
+
+    pub fn apply(&mut self, state: &ConcentratedState) {
+        self.sqrt_price = state.sqrt_price;
+        self.tick_current = state.tick_current;
+        self.liquidity = state.liquidity;
+        self.fee_growth_global_x = state.fee_growth_global_x;
+        self.fee_growth_global_y = state.fee_growth_global_y;
+    }
+
+    pub fn tick(&self, index: i32) -> Option<Tick> {
+        self.ticks
+            .binary_search_by_key(&index, |tick| tick.index)
+            .ok()
+            .map(|slot| self.ticks[slot].into())
+    }
+
+    // Position of tick `index`, inserting an empty one in order if it isn't initialized yet
+    pub fn tick_slot(&mut self, index: i32) -> Result<usize> {
+        match self.ticks.binary_search_by_key(&index, |tick| tick.index) {
+            Ok(slot) => Ok(slot),
+            Err(slot) => {
+                require!(self.ticks.len() < MAX_TICKS, AmmError::TooManyTicks);
+                self.ticks.insert(
+                    slot,
+                    TickState {
+                        index,
+                        ..Default::default()
+                    },
+                );
+                Ok(slot)
+            }
+        }
+    }
+
+    // Drops ticks no position is bounded by anymore, freeing their slots
+    pub fn prune_ticks(&mut self) {
+        self.ticks.retain(|tick| tick.liquidity_gross > 0);
+    }
+}
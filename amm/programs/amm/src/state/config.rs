@@ -0,0 +1,162 @@
+use amm_math::{
+    dynamic_fee::{self, MAX_VOLATILITY},
+    Curve, PoolConfig,
+};
+use anchor_lang::prelude::*;
+
+use crate::{
+    error::AmmError,
+    state::{Observation, OBSERVATIONS},
+};
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum CurveType {
+    ConstantProduct,
+    StableSwap,
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct Config {
+    pub seed: u64,
+    pub authority: Option<Pubkey>,
+    pub mint_x: Pubkey,
+    pub mint_y: Pubkey,
+    pub fee: u16,
+    pub protocol_fee: u16, // share of `fee` kept by the protocol, in basis points
+    pub protocol_fees_x: u64,
+    pub protocol_fees_y: u64,
+    pub curve: CurveType,
+    pub amp_initial: u64, // StableSwap amplification, ramped linearly towards `amp_target`
+    pub amp_target: u64,
+    pub ramp_start_ts: i64,
+    pub ramp_stop_ts: i64,
+    pub locked: bool,
+    pub config_bump: u8,
+    pub lp_bump: u8,
+    pub price_x_cumulative: u128, // sum of (price of x in y, Q64.64) * seconds
+    pub price_y_cumulative: u128, // sum of (price of y in x, Q64.64) * seconds
+    pub last_update_ts: i64,
+    pub observation_index: u16, // slot of the latest entry in `observations`
+    pub observations: [Observation; OBSERVATIONS],
+    pub flash_loan_amount: u64, // outstanding flash loan, 0 when none is active
+    pub flash_loan_is_x: bool,
+    pub dynamic_fee: bool, // when set, swaps pay between `fee_min` and `fee_max` depending on `volatility`
+    pub fee_min: u16,
+    pub fee_max: u16,
+    pub volatility: u64, // recent price movement in basis points, decaying from `volatility_ts`
+    pub volatility_ts: i64,
+}
+
+impl From<CurveType> for Curve {
+    fn from(curve: CurveType) -> Curve {
+        match curve {
+            CurveType::ConstantProduct => Curve::ConstantProduct,
+            CurveType::StableSwap => Curve::StableSwap,
+        }
+    }
+}
+
+impl Config {
+    // Pricing inputs for `amm_math`, which executes and quotes every deposit, withdraw and swap
+    pub fn pool_config(&self) -> PoolConfig {
+        PoolConfig {
+            curve: self.curve.into(),
+            fee: self.fee,
+            protocol_fee: self.protocol_fee,
+            protocol_fees_x: self.protocol_fees_x,
+            protocol_fees_y: self.protocol_fees_y,
+            amp_initial: self.amp_initial,
+            amp_target: self.amp_target,
+            ramp_start_ts: self.ramp_start_ts,
+            ramp_stop_ts: self.ramp_stop_ts,
+            dynamic_fee: self.dynamic_fee,
+            fee_min: self.fee_min,
+            fee_max: self.fee_max,
+            volatility: self.volatility,
+            volatility_ts: self.volatility_ts,
+        }
+    }
+
+    // Protocol fees sit in the vaults until collected, so they are excluded from the pool reserves
+    pub fn reserves(&self, vault_x: u64, vault_y: u64) -> Result<(u64, u64)> {
+        Ok(self
+            .pool_config()
+            .reserves(vault_x, vault_y)
+            .map_err(AmmError::from)?)
+    }
+
+    // Marginal prices of x in y and of y in x on the pool's curve at `now`, Q64.64
+    pub fn spot_prices(&self, reserve_x: u64, reserve_y: u64, now: i64) -> Result<(u128, u128)> {
+        Ok(self
+            .pool_config()
+            .spot_prices(reserve_x, reserve_y, now)
+            .map_err(AmmError::from)?)
+    }
+
+    pub fn accrue_protocol_fee(&mut self, is_x: bool, protocol_fee: u64) -> Result<()> {
+        let accrued = match is_x {
+            true => &mut self.protocol_fees_x,
+            false => &mut self.protocol_fees_y,
+        };
+        *accrued = accrued
+            .checked_add(protocol_fee)
+            .ok_or(AmmError::Overflow)?;
+        Ok(())
+    }
+
+    // Adds the movement of the curve price between two sets of reserves to the decayed
+    // volatility, so pegged StableSwap pairs aren't charged for the reserve ratio drifting.
+    // Tracked whether or not the dynamic fee is enabled, so enabling it starts from recent history
+    pub fn record_price_move(
+        &mut self,
+        before: (u64, u64),
+        after: (u64, u64),
+        now: i64,
+    ) -> Result<()> {
+        // An empty pool has no price to move
+        if before.0 == 0 || before.1 == 0 || after.0 == 0 || after.1 == 0 {
+            return Ok(());
+        }
+
+        let (price_before, _) = self.spot_prices(before.0, before.1, now)?;
+        let (price_after, _) = self.spot_prices(after.0, after.1, now)?;
+        let price_move =
+            dynamic_fee::price_move(price_before, price_after).map_err(AmmError::from)?;
+        self.volatility = self
+            .pool_config()
+            .volatility(now)
+            .saturating_add(price_move)
+            .min(MAX_VOLATILITY);
+        self.volatility_ts = now;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(curve: CurveType) -> Config {
+        let mut config = Config::deserialize(&mut &[0; Config::INIT_SPACE][..]).unwrap();
+        config.curve = curve;
+        config.amp_initial = 100;
+        config.amp_target = 100;
+        config
+    }
+
+    #[test]
+    fn stable_pools_record_the_move_of_the_curve_price() {
+        // A trade that leaves half as much y again as x
+        let (before, after) = ((1_000_000, 1_000_000), (800_000, 1_200_000));
+
+        let mut config = pool(CurveType::ConstantProduct);
+        config.record_price_move(before, after, 0).unwrap();
+        assert_eq!(config.volatility, 5_000);
+
+        // Around the peg the same trade barely moves the StableSwap price
+        let mut config = pool(CurveType::StableSwap);
+        config.record_price_move(before, after, 0).unwrap();
+        assert!(config.volatility > 0 && config.volatility < 30);
+    }
+}
@@ -0,0 +1,140 @@
+use anchor_lang::prelude::*;
+
+use crate::error::AmmError;
+
+// Scale of `Farm.reward_per_share`, so small emissions over a large stake still register
+pub const REWARD_PRECISION: u128 = 1_000_000_000_000;
+
+// LP staking for one pool, at seeds [b"farm", config]. Staked LP and funded rewards sit in the
+// farm's own ATAs for `mint_lp` and `reward_mint`
+#[account]
+#[derive(InitSpace)]
+pub struct Farm {
+    pub config: Pubkey,
+    pub reward_mint: Pubkey,
+    pub reward_rate: u64, // reward tokens emitted per second across all stakers
+    pub reward_per_share: u128, // rewards per staked LP since the farm started, scaled by REWARD_PRECISION
+    pub rewards_remaining: u64, // funded but not yet emitted
+    pub reward_carry: u128, // emitted, scaled by REWARD_PRECISION, but rounded off `reward_per_share`
+    pub total_staked: u64,
+    pub last_update_ts: i64,
+    pub bump: u8,
+}
+
+// One user's stake in a farm, at seeds [b"stake", farm, owner]
+#[account]
+#[derive(InitSpace)]
+pub struct Stake {
+    pub farm: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub reward_debt: u128, // part of `amount * reward_per_share` already settled
+    pub rewards_owed: u64, // settled but not yet harvested
+    pub bump: u8,
+}
+
+impl Farm {
+    // Emits rewards for the time since the last update, never more than the farm holds
+    pub fn update(&mut self, now: i64) -> Result<()> {
+        let elapsed = now.saturating_sub(self.last_update_ts).max(0) as u64;
+        // Nothing is emitted while nobody is staked, so those rewards stay in the farm
+        if self.total_staked > 0 && elapsed > 0 {
+            let emitted = self
+                .reward_rate
+                .saturating_mul(elapsed)
+                .min(self.rewards_remaining);
+            // What doesn't divide evenly across the stake is carried into the next update
+            // rather than dropped, so every emitted reward is eventually paid out
+            let scaled = (emitted as u128)
+                .checked_mul(REWARD_PRECISION)
+                .and_then(|scaled| scaled.checked_add(self.reward_carry))
+                .ok_or(AmmError::Overflow)?;
+            let per_share = scaled / self.total_staked as u128;
+
+            self.reward_per_share = self
+                .reward_per_share
+                .checked_add(per_share)
+                .ok_or(AmmError::Overflow)?;
+            self.reward_carry = scaled - per_share * self.total_staked as u128;
+            self.rewards_remaining -= emitted;
+        }
+        self.last_update_ts = now;
+        Ok(())
+    }
+}
+
+impl Stake {
+    // Moves rewards accrued at the farm's current `reward_per_share` into `rewards_owed`.
+    // Call after `Farm::update` and before changing `amount`
+    pub fn settle(&mut self, farm: &Farm) -> Result<()> {
+        let accrued = (self.amount as u128)
+            .checked_mul(farm.reward_per_share)
+            .ok_or(AmmError::Overflow)?;
+        let pending = accrued
+            .checked_sub(self.reward_debt)
+            .ok_or(AmmError::Overflow)?
+            / REWARD_PRECISION;
+
+        self.rewards_owed = self
+            .rewards_owed
+            .checked_add(u64::try_from(pending).map_err(|_| AmmError::Overflow)?)
+            .ok_or(AmmError::Overflow)?;
+        // Only what was paid out is consumed, so the rounded-off fraction keeps accruing
+        self.reward_debt += pending * REWARD_PRECISION;
+        Ok(())
+    }
+
+    // Resets the debt after `amount` changed, so only later emissions accrue to it
+    pub fn checkpoint(&mut self, farm: &Farm) -> Result<()> {
+        self.reward_debt = (self.amount as u128)
+            .checked_mul(farm.reward_per_share)
+            .ok_or(AmmError::Overflow)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewards_that_round_off_reward_per_share_go_out_with_later_updates() {
+        // 10 rewards a second over 3 * REWARD_PRECISION staked LP is 3.33 per share, so a
+        // whole reward rounds off every second
+        let total_staked = 3 * REWARD_PRECISION as u64;
+        let mut farm = Farm {
+            config: Pubkey::default(),
+            reward_mint: Pubkey::default(),
+            reward_rate: 10,
+            reward_per_share: 0,
+            rewards_remaining: 1_000,
+            reward_carry: 0,
+            total_staked,
+            last_update_ts: 0,
+            bump: 0,
+        };
+        let mut stake = Stake {
+            farm: Pubkey::default(),
+            owner: Pubkey::default(),
+            amount: total_staked,
+            reward_debt: 0,
+            rewards_owed: 0,
+            bump: 0,
+        };
+
+        for now in 1..=100 {
+            farm.update(now).unwrap();
+        }
+        stake.settle(&farm).unwrap();
+
+        // Dropping the remainder would have paid out 900. Only the last reward, which can't be
+        // split three ways, is still waiting
+        assert_eq!(farm.rewards_remaining, 0);
+        assert_eq!(stake.rewards_owed, 999);
+        assert_eq!(farm.reward_carry, REWARD_PRECISION);
+        assert_eq!(
+            farm.reward_per_share * total_staked as u128 + farm.reward_carry,
+            1_000 * REWARD_PRECISION
+        );
+    }
+}
@@ -1,14 +1,13 @@
-use anchor_lang::prelude::*;
+pub mod concentrated;
+pub mod config;
+pub mod farm;
+pub mod oracle;
+pub mod order;
+pub mod registry;
 
-#[account]
-#[derive(InitSpace)]
-pub struct Config {
-    pub seed: u64,
-    pub authority: Option<Pubkey>,
-    pub mint_x: Pubkey,
-    pub mint_y: Pubkey,
-    pub fee: u16,
-    pub locked: bool,
-    pub config_bump: u8,
-    pub lp_bump: u8,
-}
+pub use concentrated::*;
+pub use config::*;
+pub use farm::*;
+pub use oracle::*;
+pub use order::*;
+pub use registry::*;
@@ -0,0 +1,334 @@
+use anchor_lang::prelude::*;
+
+use crate::{error::AmmError, state::Config};
+
+// Number of observations kept in the ring buffer on each pool
+pub const OBSERVATIONS: usize = 16;
+
+// Minimum seconds between two stored observations, so the ring buffer spans at least
+// `(OBSERVATIONS - 1) * OBSERVATION_INTERVAL` seconds (75 minutes) however busy the pool is
+pub const OBSERVATION_INTERVAL: i64 = 300;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, InitSpace)]
+pub struct Observation {
+    pub timestamp: i64,
+    pub price_x_cumulative: u128,
+    pub price_y_cumulative: u128,
+}
+
+impl Config {
+    pub fn init_oracle(&mut self, now: i64) {
+        self.last_update_ts = now;
+        self.observation_index = 0;
+        self.observations = [Observation::default(); OBSERVATIONS];
+        self.observations[0] = Observation {
+            timestamp: now,
+            price_x_cumulative: 0,
+            price_y_cumulative: 0,
+        };
+    }
+
+    // Must be called with the reserves from before the pool state changes, since those are the
+    // prices that held since the last update. Prices come from the pool's curve, so a StableSwap
+    // pool accumulates its marginal price rather than the reserve ratio. Accumulators wrap on
+    // overflow like Uniswap V2, so
+    // consumers should only ever look at wrapping differences between two observations.
+    // The accumulators move on every update, but a new observation is only stored once
+    // `OBSERVATION_INTERVAL` has passed since the latest one.
+    pub fn update_oracle(&mut self, reserve_x: u64, reserve_y: u64, now: i64) -> Result<()> {
+        let elapsed = now.saturating_sub(self.last_update_ts);
+        if elapsed <= 0 {
+            return Ok(());
+        }
+
+        let (price_x_cumulative, price_y_cumulative) =
+            self.cumulatives_at(reserve_x, reserve_y, now, elapsed)?;
+
+        self.price_x_cumulative = price_x_cumulative;
+        self.price_y_cumulative = price_y_cumulative;
+        self.last_update_ts = now;
+
+        let latest = self.observations[self.observation_index as usize];
+        if now.saturating_sub(latest.timestamp) < OBSERVATION_INTERVAL {
+            return Ok(());
+        }
+
+        self.observation_index = (self.observation_index + 1) % OBSERVATIONS as u16;
+        self.observations[self.observation_index as usize] = Observation {
+            timestamp: now,
+            price_x_cumulative,
+            price_y_cumulative,
+        };
+        Ok(())
+    }
+
+    // Cumulative prices as of `seconds_ago` seconds before `now`. The TWAP over a window is the
+    // wrapping difference of two observations divided by the time between them.
+    pub fn observe(
+        &self,
+        reserve_x: u64,
+        reserve_y: u64,
+        now: i64,
+        seconds_ago: u32,
+    ) -> Result<Observation> {
+        let target = now
+            .checked_sub(seconds_ago as i64)
+            .ok_or(AmmError::Overflow)?;
+
+        // Extrapolate from the latest update using the current reserves
+        if target >= self.last_update_ts {
+            let (price_x_cumulative, price_y_cumulative) =
+                self.cumulatives_at(reserve_x, reserve_y, now, target - self.last_update_ts)?;
+            return Ok(Observation {
+                timestamp: target,
+                price_x_cumulative,
+                price_y_cumulative,
+            });
+        }
+
+        // Walk back from the latest update through the ring buffer, newest to oldest, and
+        // interpolate between the surrounding pair
+        let mut after = Observation {
+            timestamp: self.last_update_ts,
+            price_x_cumulative: self.price_x_cumulative,
+            price_y_cumulative: self.price_y_cumulative,
+        };
+        for i in 0..OBSERVATIONS {
+            let index = (self.observation_index as usize + OBSERVATIONS - i) % OBSERVATIONS;
+            let before = self.observations[index];
+            if before.timestamp == 0 {
+                break;
+            }
+            if before.timestamp <= target {
+                return Ok(interpolate(&before, &after, target));
+            }
+            after = before;
+        }
+
+        err!(AmmError::ObservationTooOld)
+    }
+
+    fn cumulatives_at(
+        &self,
+        reserve_x: u64,
+        reserve_y: u64,
+        now: i64,
+        elapsed: i64,
+    ) -> Result<(u128, u128)> {
+        if reserve_x == 0 || reserve_y == 0 || elapsed <= 0 {
+            return Ok((self.price_x_cumulative, self.price_y_cumulative));
+        }
+
+        let (price_x, price_y) = self.spot_prices(reserve_x, reserve_y, now)?;
+        Ok((
+            self.price_x_cumulative
+                .wrapping_add(price_x.wrapping_mul(elapsed as u128)),
+            self.price_y_cumulative
+                .wrapping_add(price_y.wrapping_mul(elapsed as u128)),
+        ))
+    }
+}
+
+fn interpolate(before: &Observation, after: &Observation, target: i64) -> Observation {
+    let span = (after.timestamp - before.timestamp) as u128;
+    let elapsed = (target - before.timestamp) as u128;
+
+    let average_x = after
+        .price_x_cumulative
+        .wrapping_sub(before.price_x_cumulative)
+        / span;
+    let average_y = after
+        .price_y_cumulative
+        .wrapping_sub(before.price_y_cumulative)
+        / span;
+
+    Observation {
+        timestamp: target,
+        price_x_cumulative: before
+            .price_x_cumulative
+            .wrapping_add(average_x.wrapping_mul(elapsed)),
+        price_y_cumulative: before
+            .price_y_cumulative
+            .wrapping_add(average_y.wrapping_mul(elapsed)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use amm_math::stable_swap;
+
+    use super::*;
+    use crate::state::CurveType;
+
+    const START: i64 = 1_700_000_000;
+
+    fn pool() -> Config {
+        let mut config = Config::deserialize(&mut &[0; Config::INIT_SPACE][..]).unwrap();
+        config.init_oracle(START);
+        config
+    }
+
+    fn error(result: Result<Observation>) -> Error {
+        match result {
+            Ok(_) => panic!("expected an error"),
+            Err(error) => error,
+        }
+    }
+
+    #[test]
+    fn updates_within_the_interval_only_move_the_accumulators() {
+        let mut config = pool();
+        for now in START + 1..START + OBSERVATION_INTERVAL {
+            config.update_oracle(1_000, 1_000, now).unwrap();
+        }
+        assert_eq!(config.observation_index, 0);
+        assert_eq!(
+            config.price_x_cumulative,
+            (OBSERVATION_INTERVAL as u128 - 1) << 64
+        );
+
+        config
+            .update_oracle(1_000, 1_000, START + OBSERVATION_INTERVAL)
+            .unwrap();
+        assert_eq!(config.observation_index, 1);
+        assert_eq!(
+            config.observations[1].timestamp,
+            START + OBSERVATION_INTERVAL
+        );
+    }
+
+    #[test]
+    fn observe_returns_a_stored_observation_exactly() {
+        let mut config = pool();
+        config
+            .update_oracle(1_000, 4_000, START + OBSERVATION_INTERVAL)
+            .unwrap();
+        config
+            .update_oracle(1_000, 1_000, START + 2 * OBSERVATION_INTERVAL)
+            .unwrap();
+
+        let now = START + 2 * OBSERVATION_INTERVAL;
+        let observation = config
+            .observe(1_000, 1_000, now, OBSERVATION_INTERVAL as u32)
+            .unwrap();
+        let stored = config.observations[1];
+        assert_eq!(observation.timestamp, stored.timestamp);
+        assert_eq!(observation.price_x_cumulative, stored.price_x_cumulative);
+        assert_eq!(observation.price_y_cumulative, stored.price_y_cumulative);
+        assert_eq!(
+            observation.price_x_cumulative,
+            (4 * OBSERVATION_INTERVAL as u128) << 64
+        );
+    }
+
+    #[test]
+    fn observe_interpolates_between_observations() {
+        let mut config = pool();
+        // x is worth 4 y for the first interval, then 1 y
+        config
+            .update_oracle(1_000, 4_000, START + OBSERVATION_INTERVAL)
+            .unwrap();
+        config
+            .update_oracle(1_000, 1_000, START + 2 * OBSERVATION_INTERVAL)
+            .unwrap();
+
+        let now = START + 2 * OBSERVATION_INTERVAL;
+        let observation = config
+            .observe(1_000, 1_000, now, (OBSERVATION_INTERVAL + 100) as u32)
+            .unwrap();
+        assert_eq!(observation.timestamp, START + OBSERVATION_INTERVAL - 100);
+        assert_eq!(
+            observation.price_x_cumulative,
+            (4 * (OBSERVATION_INTERVAL as u128 - 100)) << 64
+        );
+
+        // Between the newest observation and an update that did not store one
+        config.update_oracle(1_000, 1_000, now + 60).unwrap();
+        let observation = config.observe(1_000, 1_000, now + 60, 30).unwrap();
+        assert_eq!(
+            observation.price_x_cumulative,
+            (5 * OBSERVATION_INTERVAL as u128 + 30) << 64
+        );
+    }
+
+    #[test]
+    fn observe_extrapolates_from_the_latest_update() {
+        let mut config = pool();
+        config.update_oracle(1_000, 2_000, START + 10).unwrap();
+
+        let observation = config.observe(1_000, 2_000, START + 40, 0).unwrap();
+        assert_eq!(observation.timestamp, START + 40);
+        assert_eq!(observation.price_x_cumulative, (2 * 40) << 64);
+        assert_eq!(observation.price_y_cumulative, 40 << 63);
+    }
+
+    #[test]
+    fn stable_pools_accumulate_the_curve_price() {
+        let mut config = pool();
+        config.curve = CurveType::StableSwap;
+        config.amp_initial = 100;
+        config.amp_target = 100;
+
+        // Half as much y again as x, which a reserve ratio would price at 1.5 y per x
+        config
+            .update_oracle(1_000_000, 1_500_000, START + 100)
+            .unwrap();
+        let average = config.price_x_cumulative / 100;
+        assert_eq!(
+            average,
+            stable_swap::spot_prices(100, 1_000_000, 1_500_000)
+                .unwrap()
+                .0
+        );
+        assert!(average > 1 << 64 && average < (1 << 64) / 1_000 * 1_003);
+    }
+
+    #[test]
+    fn observe_after_the_ring_buffer_wraps() {
+        let mut config = pool();
+        let updates = OBSERVATIONS as i64 + 4;
+        for i in 1..=updates {
+            config
+                .update_oracle(1_000, 1_000, START + i * OBSERVATION_INTERVAL)
+                .unwrap();
+        }
+        let now = START + updates * OBSERVATION_INTERVAL;
+        assert_eq!(
+            config.observation_index as i64,
+            updates % OBSERVATIONS as i64
+        );
+
+        // The oldest observation still stored is the one after the write position
+        let oldest = (updates - OBSERVATIONS as i64 + 1) * OBSERVATION_INTERVAL;
+        let span = (now - START - oldest) as u32;
+        let observation = config.observe(1_000, 1_000, now, span).unwrap();
+        assert_eq!(observation.timestamp, START + oldest);
+        assert_eq!(observation.price_x_cumulative, (oldest as u128) << 64);
+
+        let observation = config.observe(1_000, 1_000, now, span - 1).unwrap();
+        assert_eq!(observation.price_x_cumulative, (oldest as u128 + 1) << 64);
+    }
+
+    #[test]
+    fn observe_past_the_oldest_observation_fails() {
+        let mut config = pool();
+        let now = START + OBSERVATION_INTERVAL;
+        config.update_oracle(1_000, 1_000, now).unwrap();
+        assert_eq!(
+            error(config.observe(1_000, 1_000, now, OBSERVATION_INTERVAL as u32 + 1)),
+            AmmError::ObservationTooOld.into()
+        );
+
+        // Overwritten observations are gone for good
+        for i in 2..=OBSERVATIONS as i64 {
+            config
+                .update_oracle(1_000, 1_000, START + i * OBSERVATION_INTERVAL)
+                .unwrap();
+        }
+        let now = START + OBSERVATIONS as i64 * OBSERVATION_INTERVAL;
+        assert_eq!(
+            error(config.observe(1_000, 1_000, now, (now - START) as u32)),
+            AmmError::ObservationTooOld.into()
+        );
+    }
+}
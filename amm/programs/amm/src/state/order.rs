@@ -0,0 +1,18 @@
+use anchor_lang::prelude::*;
+
+// Resting "sell `amount_in` of `mint_in` for at least `min_amount_out` of `mint_out`" order,
+// at seeds [b"order", config, owner, id]. The tokens sit in the order's own ATA for `mint_in`
+#[account]
+#[derive(InitSpace)]
+pub struct Order {
+    pub config: Pubkey,
+    pub owner: Pubkey,
+    pub id: u64,
+    pub mint_in: Pubkey,
+    pub mint_out: Pubkey,
+    pub is_x: bool, // true when selling the pool's x for y
+    pub amount_in: u64,
+    pub min_amount_out: u64, // what the owner must receive, after any transfer fee
+    pub bounty: u64,         // lamports paid to whoever fills the order
+    pub bump: u8,
+}
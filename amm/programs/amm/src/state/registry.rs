@@ -0,0 +1,14 @@
+use anchor_lang::prelude::*;
+
+// One per mint pair and fee tier, at seeds [b"registry", mint_a, mint_b, fee_tier], so clients
+// can derive the pool for a pair without knowing its seed. The tier is the fee passed to `init`
+// and never changes, while `update_fee` and the dynamic fee move the pool's live fee
+#[account]
+#[derive(InitSpace)]
+pub struct Registry {
+    pub config: Pubkey,
+    pub mint_a: Pubkey, // the smaller of the pair's mints, so both orders map to one registry
+    pub mint_b: Pubkey,
+    pub fee_tier: u16, // fee the pool was initialized with, not its current fee
+    pub bump: u8,
+}
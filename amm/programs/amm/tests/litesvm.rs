@@ -0,0 +1,2114 @@
+// Integration tests running the compiled program on LiteSVM; no validator or Node toolchain is
+// needed. The first test to start builds `target/deploy/amm.so` with `cargo build-sbf`, so a plain
+// `cargo test` always runs the current source.
+#![allow(deprecated)]
+
+use std::{process::Command, sync::OnceLock};
+
+use amm::{error::AmmError, CurveType};
+use amm_math::{Snapshot, SwapQuote};
+use anchor_lang::{
+    error::ErrorCode, prelude::Pubkey, solana_program::sysvar, system_program, AccountDeserialize,
+    InstructionData, ToAccountMetas,
+};
+use anchor_spl::{
+    associated_token::{
+        self, get_associated_token_address_with_program_id, spl_associated_token_account,
+    },
+    token::spl_token,
+    token_2022,
+    token_interface::spl_token_2022::{
+        self,
+        extension::{
+            transfer_fee::{instruction::initialize_transfer_fee_config, TransferFeeConfig},
+            BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+        },
+    },
+};
+use litesvm::LiteSVM;
+use solana_sdk::{
+    clock::Clock,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    signature::{Keypair, Signer},
+    system_instruction,
+    transaction::{Transaction, TransactionError},
+};
+
+const MANIFEST_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml");
+const PROGRAM_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../target/deploy/amm.so");
+const SEED: u64 = 42;
+const FEE: u16 = 30;
+const DECIMALS: u8 = 6;
+const MINIMUM_LIQUIDITY: u64 = amm_math::MINIMUM_LIQUIDITY;
+// Square-root price of 1 as Q64.64
+const PRICE_ONE: u128 = 1 << 64;
+
+// Program binary, built once per test run; the build is a no-op when the source hasn't changed
+fn program() -> &'static [u8] {
+    static PROGRAM: OnceLock<Vec<u8>> = OnceLock::new();
+    PROGRAM.get_or_init(|| {
+        let status = Command::new("cargo")
+            .args(["build-sbf", "--manifest-path", MANIFEST_PATH])
+            .status()
+            .expect("failed to run cargo");
+        assert!(
+            status.success(),
+            "`cargo build-sbf` failed, is the Solana CLI installed?"
+        );
+        std::fs::read(PROGRAM_PATH).expect("amm.so not found after `cargo build-sbf`")
+    })
+}
+
+struct Pool {
+    svm: LiteSVM,
+    authority: Keypair,
+    user: Keypair,
+    mint_x: Pubkey,
+    mint_y: Pubkey,
+    config: Pubkey,
+    mint_lp: Pubkey,
+    token_program: Pubkey,
+}
+
+impl Pool {
+    // Fresh SVM with two mints, a pool over them and a user holding 1,000 of each token
+    fn new() -> Self {
+        Self::with_mints(spl_token::ID, None)
+    }
+
+    // Same as `new`, but over Token-2022 mints that charge `fee_bps` on every transfer
+    fn with_transfer_fee(fee_bps: u16) -> Self {
+        Self::with_mints(token_2022::ID, Some(fee_bps))
+    }
+
+    fn with_mints(token_program: Pubkey, transfer_fee_bps: Option<u16>) -> Self {
+        let mut svm = LiteSVM::new();
+        svm.add_program(amm::ID, program());
+
+        let authority = Keypair::new();
+        let user = Keypair::new();
+        svm.airdrop(&authority.pubkey(), 10_000_000_000).unwrap();
+        svm.airdrop(&user.pubkey(), 10_000_000_000).unwrap();
+
+        let config = Pubkey::find_program_address(&[b"config", &SEED.to_le_bytes()], &amm::ID).0;
+        let mint_lp = Pubkey::find_program_address(&[b"lp", config.as_ref()], &amm::ID).0;
+
+        let mut pool = Self {
+            svm,
+            authority,
+            user,
+            mint_x: Pubkey::default(),
+            mint_y: Pubkey::default(),
+            config,
+            mint_lp,
+            token_program,
+        };
+
+        pool.mint_x = pool.create_mint_with(token_program, DECIMALS, transfer_fee_bps);
+        pool.mint_y = pool.create_mint_with(token_program, DECIMALS, transfer_fee_bps);
+        pool.mint_to(pool.mint_x, 1_000_000_000);
+        pool.mint_to(pool.mint_y, 1_000_000_000);
+
+        pool.init().unwrap();
+        pool
+    }
+
+    // Points the helpers at the pool with `seed` over `mint_x` and `mint_y`
+    fn select(&mut self, seed: u64, mint_x: Pubkey, mint_y: Pubkey) {
+        self.config = Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &amm::ID).0;
+        self.mint_lp = Pubkey::find_program_address(&[b"lp", self.config.as_ref()], &amm::ID).0;
+        self.mint_x = mint_x;
+        self.mint_y = mint_y;
+    }
+
+    fn send(&mut self, ix: Instruction, signer: &Keypair) -> Result<(), TransactionError> {
+        self.send_all(&[ix], signer)
+    }
+
+    fn send_all(&mut self, ixs: &[Instruction], signer: &Keypair) -> Result<(), TransactionError> {
+        let tx = Transaction::new_signed_with_payer(
+            ixs,
+            Some(&signer.pubkey()),
+            &[signer],
+            self.svm.latest_blockhash(),
+        );
+        let result = self.svm.send_transaction(tx).map(|_| ()).map_err(|e| e.err);
+        // Lets an identical transaction be sent again
+        self.svm.expire_blockhash();
+        result
+    }
+
+    fn create_mint(&mut self) -> Pubkey {
+        self.create_mint_with(self.token_program, DECIMALS, None)
+    }
+
+    fn create_mint_with(
+        &mut self,
+        token_program: Pubkey,
+        decimals: u8,
+        transfer_fee_bps: Option<u16>,
+    ) -> Pubkey {
+        let mint = Keypair::new();
+        let payer = self.authority.insecure_clone();
+        let extensions: &[ExtensionType] = match transfer_fee_bps {
+            Some(_) => &[ExtensionType::TransferFeeConfig],
+            None => &[],
+        };
+        let space =
+            ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(extensions)
+                .unwrap();
+        let rent = self.svm.minimum_balance_for_rent_exemption(space);
+
+        let mut ixs = vec![system_instruction::create_account(
+            &payer.pubkey(),
+            &mint.pubkey(),
+            rent,
+            space as u64,
+            &token_program,
+        )];
+        if let Some(fee_bps) = transfer_fee_bps {
+            ixs.push(
+                initialize_transfer_fee_config(
+                    &token_program,
+                    &mint.pubkey(),
+                    Some(&payer.pubkey()),
+                    Some(&payer.pubkey()),
+                    fee_bps,
+                    u64::MAX,
+                )
+                .unwrap(),
+            );
+        }
+        ixs.push(
+            spl_token_2022::instruction::initialize_mint2(
+                &token_program,
+                &mint.pubkey(),
+                &payer.pubkey(),
+                None,
+                decimals,
+            )
+            .unwrap(),
+        );
+
+        let tx = Transaction::new_signed_with_payer(
+            &ixs,
+            Some(&payer.pubkey()),
+            &[&payer, &mint],
+            self.svm.latest_blockhash(),
+        );
+        self.svm.send_transaction(tx).unwrap();
+        mint.pubkey()
+    }
+
+    fn mint_to(&mut self, mint: Pubkey, amount: u64) {
+        let owner = self.user.pubkey();
+        self.mint_to_owner(mint, owner, amount);
+    }
+
+    fn mint_to_owner(&mut self, mint: Pubkey, owner: Pubkey, amount: u64) {
+        let payer = self.authority.insecure_clone();
+        let ata = self.ata(&owner, &mint);
+
+        let tx = Transaction::new_signed_with_payer(
+            &[
+                spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                    &payer.pubkey(),
+                    &owner,
+                    &mint,
+                    &self.token_program,
+                ),
+                spl_token_2022::instruction::mint_to(
+                    &self.token_program,
+                    &mint,
+                    &ata,
+                    &payer.pubkey(),
+                    &[],
+                    amount,
+                )
+                .unwrap(),
+            ],
+            Some(&payer.pubkey()),
+            &[&payer],
+            self.svm.latest_blockhash(),
+        );
+        self.svm.send_transaction(tx).unwrap();
+    }
+
+    fn ata(&self, owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        get_associated_token_address_with_program_id(owner, mint, &self.token_program)
+    }
+
+    fn balance(&self, owner: &Pubkey, mint: &Pubkey) -> u64 {
+        let ata = self.ata(owner, mint);
+        self.svm
+            .get_account(&ata)
+            .map(|account| {
+                StateWithExtensions::<spl_token_2022::state::Account>::unpack(&account.data)
+                    .unwrap()
+                    .base
+                    .amount
+            })
+            .unwrap_or(0)
+    }
+
+    fn lp_supply(&self) -> u64 {
+        let account = self.svm.get_account(&self.mint_lp).unwrap();
+        StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&account.data)
+            .unwrap()
+            .base
+            .supply
+    }
+
+    // Fee the token program withholds when `amount` of `mint` is transferred
+    fn transfer_fee(&self, mint: &Pubkey, amount: u64) -> u64 {
+        let account = self.svm.get_account(mint).unwrap();
+        let mint =
+            StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&account.data).unwrap();
+        let epoch = self.svm.get_sysvar::<Clock>().epoch;
+        mint.get_extension::<TransferFeeConfig>()
+            .map_or(0, |config| {
+                config.calculate_epoch_fee(epoch, amount).unwrap()
+            })
+    }
+
+    fn config(&self) -> amm::Config {
+        let account = self.svm.get_account(&self.config).unwrap();
+        amm::Config::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    fn init(&mut self) -> Result<(), TransactionError> {
+        self.init_with(SEED, FEE)
+    }
+
+    // Initializes another pool over the same mints, without touching `self.config`
+    fn init_with(&mut self, seed: u64, fee: u16) -> Result<(), TransactionError> {
+        self.init_curve(seed, fee, CurveType::ConstantProduct, 0)
+    }
+
+    fn init_curve(
+        &mut self,
+        seed: u64,
+        fee: u16,
+        curve: CurveType,
+        amp: u64,
+    ) -> Result<(), TransactionError> {
+        let config = Pubkey::find_program_address(&[b"config", &seed.to_le_bytes()], &amm::ID).0;
+        let mint_lp = Pubkey::find_program_address(&[b"lp", config.as_ref()], &amm::ID).0;
+        let ix = Instruction {
+            program_id: amm::ID,
+            accounts: amm::accounts::Initialize {
+                initializer: self.authority.pubkey(),
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                mint_lp,
+                vault_lp: self.ata(&config, &mint_lp),
+                vault_x: self.ata(&config, &self.mint_x),
+                vault_y: self.ata(&config, &self.mint_y),
+                config,
+                registry: self.registry(fee),
+                token_program: self.token_program,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: amm::instruction::Init {
+                seed,
+                fee,
+                authority: Some(self.authority.pubkey()),
+                curve,
+                amp,
+            }
+            .data(),
+        };
+        let authority = self.authority.insecure_clone();
+        self.send(ix, &authority)
+    }
+
+    // Same address whichever way round the mints are given
+    fn registry(&self, fee: u16) -> Pubkey {
+        let (mint_a, mint_b) = match self.mint_x < self.mint_y {
+            true => (self.mint_x, self.mint_y),
+            false => (self.mint_y, self.mint_x),
+        };
+        Pubkey::find_program_address(
+            &[
+                b"registry",
+                mint_a.as_ref(),
+                mint_b.as_ref(),
+                &fee.to_le_bytes(),
+            ],
+            &amm::ID,
+        )
+        .0
+    }
+
+    fn deposit(&mut self, amount: u64, max_x: u64, max_y: u64) -> Result<(), TransactionError> {
+        let ix = self.deposit_ix(amount, max_x, max_y);
+        let user = self.user.insecure_clone();
+        self.send(ix, &user)
+    }
+
+    fn deposit_ix(&self, amount: u64, max_x: u64, max_y: u64) -> Instruction {
+        let user = self.user.pubkey();
+        Instruction {
+            program_id: amm::ID,
+            accounts: amm::accounts::Deposit {
+                user,
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                config: self.config,
+                mint_lp: self.mint_lp,
+                vault_lp: self.ata(&self.config, &self.mint_lp),
+                vault_x: self.ata(&self.config, &self.mint_x),
+                vault_y: self.ata(&self.config, &self.mint_y),
+                user_x: self.ata(&user, &self.mint_x),
+                user_y: self.ata(&user, &self.mint_y),
+                user_lp: self.ata(&user, &self.mint_lp),
+                token_program: self.token_program,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: amm::instruction::Deposit {
+                amount,
+                max_x,
+                max_y,
+            }
+            .data(),
+        }
+    }
+
+    fn withdraw(&mut self, amount: u64, min_x: u64, min_y: u64) -> Result<(), TransactionError> {
+        let ix = self.withdraw_ix(amount, min_x, min_y);
+        let user = self.user.insecure_clone();
+        self.send(ix, &user)
+    }
+
+    fn withdraw_ix(&self, amount: u64, min_x: u64, min_y: u64) -> Instruction {
+        let user = self.user.pubkey();
+        Instruction {
+            program_id: amm::ID,
+            accounts: amm::accounts::Withdraw {
+                user,
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                config: self.config,
+                mint_lp: self.mint_lp,
+                vault_x: self.ata(&self.config, &self.mint_x),
+                vault_y: self.ata(&self.config, &self.mint_y),
+                user_x: self.ata(&user, &self.mint_x),
+                user_y: self.ata(&user, &self.mint_y),
+                user_lp: self.ata(&user, &self.mint_lp),
+                token_program: self.token_program,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: amm::instruction::Withdraw {
+                amount,
+                min_x,
+                min_y,
+            }
+            .data(),
+        }
+    }
+
+    fn swap(
+        &mut self,
+        is_x: bool,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> Result<(), TransactionError> {
+        let ix = self.swap_ix(is_x, amount_in, min_amount_out);
+        let user = self.user.insecure_clone();
+        self.send(ix, &user)
+    }
+
+    fn swap_ix(&self, is_x: bool, amount_in: u64, min_amount_out: u64) -> Instruction {
+        let user = self.user.pubkey();
+        Instruction {
+            program_id: amm::ID,
+            accounts: amm::accounts::Swap {
+                user,
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                config: self.config,
+                mint_lp: self.mint_lp,
+                vault_x: self.ata(&self.config, &self.mint_x),
+                vault_y: self.ata(&self.config, &self.mint_y),
+                user_x: self.ata(&user, &self.mint_x),
+                user_y: self.ata(&user, &self.mint_y),
+                token_program: self.token_program,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: amm::instruction::Swap {
+                is_x,
+                amount_in,
+                min_amount_out,
+            }
+            .data(),
+        }
+    }
+
+    // What the program should pay out for a swap against the pool's current vaults
+    fn quote_swap(&self, is_x: bool, amount_in: u64) -> SwapQuote {
+        let snapshot = Snapshot {
+            vault_x: self.balance(&self.config, &self.mint_x),
+            vault_y: self.balance(&self.config, &self.mint_y),
+            lp_supply: self.lp_supply(),
+        };
+        let now = self.svm.get_sysvar::<Clock>().unix_timestamp;
+        amm_math::quote_swap(
+            &self.config().pool_config(),
+            &snapshot,
+            is_x,
+            amount_in,
+            now,
+        )
+        .unwrap()
+    }
+
+    // Each hop is a `(config, mint_in, mint_out)`
+    fn route_swap_ix(
+        &self,
+        hops: &[(Pubkey, Pubkey, Pubkey)],
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> Instruction {
+        let user = self.user.pubkey();
+        let mut accounts = amm::accounts::RouteSwap {
+            user,
+            user_in: self.ata(&user, &hops[0].1),
+            user_out: self.ata(&user, &hops[hops.len() - 1].2),
+            token_program: self.token_program,
+        }
+        .to_account_metas(None);
+        for &(config, mint_in, mint_out) in hops {
+            let mint_lp = Pubkey::find_program_address(&[b"lp", config.as_ref()], &amm::ID).0;
+            accounts.extend([
+                AccountMeta::new(config, false),
+                AccountMeta::new_readonly(mint_lp, false),
+                AccountMeta::new_readonly(mint_in, false),
+                AccountMeta::new_readonly(mint_out, false),
+                AccountMeta::new(self.ata(&config, &mint_in), false),
+                AccountMeta::new(self.ata(&config, &mint_out), false),
+            ]);
+        }
+        Instruction {
+            program_id: amm::ID,
+            accounts,
+            data: amm::instruction::RouteSwap {
+                amount_in,
+                min_amount_out,
+            }
+            .data(),
+        }
+    }
+
+    // `flash_borrow` or `flash_repay` against the pool at `config`, over the same mints
+    fn flash_loan_ix(&self, config: Pubkey, data: Vec<u8>) -> Instruction {
+        let user = self.user.pubkey();
+        Instruction {
+            program_id: amm::ID,
+            accounts: amm::accounts::FlashLoan {
+                user,
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                config,
+                vault_x: self.ata(&config, &self.mint_x),
+                vault_y: self.ata(&config, &self.mint_y),
+                user_x: self.ata(&user, &self.mint_x),
+                user_y: self.ata(&user, &self.mint_y),
+                instruction_sysvar: sysvar::instructions::ID,
+                token_program: self.token_program,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data,
+        }
+    }
+
+    fn flash_borrow_ix(&self, is_x: bool, amount: u64) -> Instruction {
+        self.flash_loan_ix(
+            self.config,
+            amm::instruction::FlashBorrow { is_x, amount }.data(),
+        )
+    }
+
+    fn flash_repay_ix(&self, config: Pubkey) -> Instruction {
+        self.flash_loan_ix(config, amm::instruction::FlashRepay {}.data())
+    }
+
+    fn admin(&mut self, data: Vec<u8>, signer: &Keypair) -> Result<(), TransactionError> {
+        let ix = Instruction {
+            program_id: amm::ID,
+            accounts: amm::accounts::Admin {
+                authority: signer.pubkey(),
+                config: self.config,
+            }
+            .to_account_metas(None),
+            data,
+        };
+        self.send(ix, signer)
+    }
+
+    fn lock(&mut self) -> Result<(), TransactionError> {
+        let authority = self.authority.insecure_clone();
+        self.admin(amm::instruction::Lock {}.data(), &authority)
+    }
+
+    fn unlock(&mut self) -> Result<(), TransactionError> {
+        let authority = self.authority.insecure_clone();
+        self.admin(amm::instruction::Unlock {}.data(), &authority)
+    }
+
+    fn collect_protocol_fees(
+        &mut self,
+        signer: &Keypair,
+        recipient: Pubkey,
+    ) -> Result<(), TransactionError> {
+        let ix = Instruction {
+            program_id: amm::ID,
+            accounts: amm::accounts::CollectProtocolFees {
+                authority: signer.pubkey(),
+                recipient,
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                config: self.config,
+                vault_x: self.ata(&self.config, &self.mint_x),
+                vault_y: self.ata(&self.config, &self.mint_y),
+                recipient_x: self.ata(&recipient, &self.mint_x),
+                recipient_y: self.ata(&recipient, &self.mint_y),
+                token_program: self.token_program,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: amm::instruction::CollectProtocolFees {}.data(),
+        };
+        self.send(ix, signer)
+    }
+
+    fn order(&self, id: u64) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                b"order",
+                self.config.as_ref(),
+                self.user.pubkey().as_ref(),
+                &id.to_le_bytes(),
+            ],
+            &amm::ID,
+        )
+        .0
+    }
+
+    // Sells x for y on behalf of the user
+    fn place_order(
+        &mut self,
+        id: u64,
+        amount_in: u64,
+        min_amount_out: u64,
+        bounty: u64,
+    ) -> Result<(), TransactionError> {
+        let user = self.user.pubkey();
+        let order = self.order(id);
+        let ix = Instruction {
+            program_id: amm::ID,
+            accounts: amm::accounts::PlaceOrder {
+                owner: user,
+                mint_in: self.mint_x,
+                mint_out: self.mint_y,
+                config: self.config,
+                order,
+                order_vault: self.ata(&order, &self.mint_x),
+                owner_in: self.ata(&user, &self.mint_x),
+                token_program: self.token_program,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: amm::instruction::PlaceOrder {
+                id,
+                amount_in,
+                min_amount_out,
+                bounty,
+            }
+            .data(),
+        };
+        let user = self.user.insecure_clone();
+        self.send(ix, &user)
+    }
+
+    fn cancel_order(&mut self, id: u64) -> Result<(), TransactionError> {
+        let user = self.user.pubkey();
+        let order = self.order(id);
+        let ix = Instruction {
+            program_id: amm::ID,
+            accounts: amm::accounts::CancelOrder {
+                owner: user,
+                mint_in: self.mint_x,
+                order,
+                order_vault: self.ata(&order, &self.mint_x),
+                owner_in: self.ata(&user, &self.mint_x),
+                token_program: self.token_program,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: amm::instruction::CancelOrder {}.data(),
+        };
+        let user = self.user.insecure_clone();
+        self.send(ix, &user)
+    }
+
+    fn fill_orders(&mut self, ids: &[u64], keeper: &Keypair) -> Result<(), TransactionError> {
+        let user = self.user.pubkey();
+        let mut accounts = amm::accounts::FillOrders {
+            keeper: keeper.pubkey(),
+            mint_x: self.mint_x,
+            mint_y: self.mint_y,
+            config: self.config,
+            mint_lp: self.mint_lp,
+            vault_x: self.ata(&self.config, &self.mint_x),
+            vault_y: self.ata(&self.config, &self.mint_y),
+            token_program: self.token_program,
+        }
+        .to_account_metas(None);
+        for id in ids {
+            let order = self.order(*id);
+            accounts.extend([
+                AccountMeta::new(order, false),
+                AccountMeta::new(self.ata(&order, &self.mint_x), false),
+                AccountMeta::new(user, false),
+                AccountMeta::new(self.ata(&user, &self.mint_y), false),
+            ]);
+        }
+        let ix = Instruction {
+            program_id: amm::ID,
+            accounts,
+            data: amm::instruction::FillOrders {}.data(),
+        };
+        self.send(ix, keeper)
+    }
+
+    fn warp(&mut self, seconds: i64) {
+        let mut clock = self.svm.get_sysvar::<Clock>();
+        clock.unix_timestamp += seconds;
+        self.svm.set_sysvar::<Clock>(&clock);
+    }
+
+    fn farm(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"farm", self.config.as_ref()], &amm::ID).0
+    }
+
+    fn stake(&self) -> Pubkey {
+        let farm = self.farm();
+        Pubkey::find_program_address(
+            &[b"stake", farm.as_ref(), self.user.pubkey().as_ref()],
+            &amm::ID,
+        )
+        .0
+    }
+
+    // Creates a reward mint, a farm paying `reward_rate` of it per second, and funds it
+    fn init_farm(&mut self, reward_rate: u64, funding: u64) -> Pubkey {
+        let reward_mint = self.create_mint();
+        let authority = self.authority.insecure_clone();
+        let farm = self.farm();
+
+        let ix = Instruction {
+            program_id: amm::ID,
+            accounts: amm::accounts::InitFarm {
+                authority: authority.pubkey(),
+                config: self.config,
+                mint_lp: self.mint_lp,
+                reward_mint,
+                farm,
+                farm_lp: self.ata(&farm, &self.mint_lp),
+                farm_reward: self.ata(&farm, &reward_mint),
+                token_program: self.token_program,
+                reward_token_program: self.token_program,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: amm::instruction::InitFarm { reward_rate }.data(),
+        };
+        self.send(ix, &authority).unwrap();
+
+        self.mint_to_owner(reward_mint, authority.pubkey(), funding);
+        let ix = Instruction {
+            program_id: amm::ID,
+            accounts: amm::accounts::FundFarm {
+                authority: authority.pubkey(),
+                config: self.config,
+                farm,
+                reward_mint,
+                farm_reward: self.ata(&farm, &reward_mint),
+                authority_reward: self.ata(&authority.pubkey(), &reward_mint),
+                reward_token_program: self.token_program,
+            }
+            .to_account_metas(None),
+            data: amm::instruction::FundFarm { amount: funding }.data(),
+        };
+        self.send(ix, &authority).unwrap();
+        reward_mint
+    }
+
+    fn stake_lp(&mut self, data: Vec<u8>) -> Result<(), TransactionError> {
+        let user = self.user.pubkey();
+        let farm = self.farm();
+        let ix = Instruction {
+            program_id: amm::ID,
+            accounts: amm::accounts::StakeLp {
+                user,
+                config: self.config,
+                mint_lp: self.mint_lp,
+                farm,
+                stake: self.stake(),
+                farm_lp: self.ata(&farm, &self.mint_lp),
+                user_lp: self.ata(&user, &self.mint_lp),
+                token_program: self.token_program,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data,
+        };
+        let user = self.user.insecure_clone();
+        self.send(ix, &user)
+    }
+
+    fn harvest(&mut self, reward_mint: Pubkey) -> Result<(), TransactionError> {
+        let user = self.user.pubkey();
+        let farm = self.farm();
+        let ix = Instruction {
+            program_id: amm::ID,
+            accounts: amm::accounts::Harvest {
+                user,
+                farm,
+                stake: self.stake(),
+                reward_mint,
+                farm_reward: self.ata(&farm, &reward_mint),
+                user_reward: self.ata(&user, &reward_mint),
+                reward_token_program: self.token_program,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: amm::instruction::Harvest {}.data(),
+        };
+        let user = self.user.insecure_clone();
+        self.send(ix, &user)
+    }
+
+    fn concentrated(&self) -> Pubkey {
+        Pubkey::find_program_address(&[b"concentrated", &SEED.to_le_bytes()], &amm::ID).0
+    }
+
+    fn concentrated_pool(&self) -> amm::ConcentratedPool {
+        let account = self.svm.get_account(&self.concentrated()).unwrap();
+        amm::ConcentratedPool::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    fn position(&self, tick_lower: i32, tick_upper: i32) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                b"position",
+                self.concentrated().as_ref(),
+                self.user.pubkey().as_ref(),
+                &tick_lower.to_le_bytes(),
+                &tick_upper.to_le_bytes(),
+            ],
+            &amm::ID,
+        )
+        .0
+    }
+
+    // Concentrated pool over the same mints with a tick spacing of 60, starting at `sqrt_price`
+    fn init_concentrated(&mut self, sqrt_price: u128) -> Result<(), TransactionError> {
+        let pool = self.concentrated();
+        let ix = Instruction {
+            program_id: amm::ID,
+            accounts: amm::accounts::InitConcentrated {
+                initializer: self.authority.pubkey(),
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                pool,
+                vault_x: self.ata(&pool, &self.mint_x),
+                vault_y: self.ata(&pool, &self.mint_y),
+                token_program: self.token_program,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: amm::instruction::InitConcentrated {
+                seed: SEED,
+                fee: FEE,
+                tick_spacing: 60,
+                sqrt_price,
+            }
+            .data(),
+        };
+        let authority = self.authority.insecure_clone();
+        self.send(ix, &authority)
+    }
+
+    // Sends `increase_liquidity`, `decrease_liquidity` or `collect_fees` for the user's position
+    fn modify_position(
+        &mut self,
+        tick_lower: i32,
+        tick_upper: i32,
+        data: Vec<u8>,
+    ) -> Result<(), TransactionError> {
+        let user = self.user.pubkey();
+        let pool = self.concentrated();
+        let ix = Instruction {
+            program_id: amm::ID,
+            accounts: amm::accounts::ModifyPosition {
+                owner: user,
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                pool,
+                position: self.position(tick_lower, tick_upper),
+                vault_x: self.ata(&pool, &self.mint_x),
+                vault_y: self.ata(&pool, &self.mint_y),
+                owner_x: self.ata(&user, &self.mint_x),
+                owner_y: self.ata(&user, &self.mint_y),
+                token_program: self.token_program,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data,
+        };
+        let user = self.user.insecure_clone();
+        self.send(ix, &user)
+    }
+
+    fn swap_concentrated(
+        &mut self,
+        is_x: bool,
+        amount_in: u64,
+        min_amount_out: u64,
+    ) -> Result<(), TransactionError> {
+        let user = self.user.pubkey();
+        let pool = self.concentrated();
+        let ix = Instruction {
+            program_id: amm::ID,
+            accounts: amm::accounts::SwapConcentrated {
+                user,
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                pool,
+                vault_x: self.ata(&pool, &self.mint_x),
+                vault_y: self.ata(&pool, &self.mint_y),
+                user_x: self.ata(&user, &self.mint_x),
+                user_y: self.ata(&user, &self.mint_y),
+                token_program: self.token_program,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: amm::instruction::SwapConcentrated {
+                is_x,
+                amount_in,
+                min_amount_out,
+                sqrt_price_limit: None,
+            }
+            .data(),
+        };
+        let user = self.user.insecure_clone();
+        self.send(ix, &user)
+    }
+
+    fn close_pool(&mut self, signer: &Keypair) -> Result<(), TransactionError> {
+        let authority = signer.pubkey();
+        let ix = Instruction {
+            program_id: amm::ID,
+            accounts: amm::accounts::ClosePool {
+                authority,
+                mint_x: self.mint_x,
+                mint_y: self.mint_y,
+                config: self.config,
+                registry: self.registry(FEE),
+                farm: self.farm(),
+                mint_lp: self.mint_lp,
+                vault_lp: self.ata(&self.config, &self.mint_lp),
+                vault_x: self.ata(&self.config, &self.mint_x),
+                vault_y: self.ata(&self.config, &self.mint_y),
+                authority_x: self.ata(&authority, &self.mint_x),
+                authority_y: self.ata(&authority, &self.mint_y),
+                token_program: self.token_program,
+                associated_token_program: associated_token::ID,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: amm::instruction::ClosePool {}.data(),
+        };
+        self.send(ix, signer)
+    }
+}
+
+fn assert_error(result: Result<(), TransactionError>, error: AmmError) {
+    assert_error_at(result, 0, error);
+}
+
+// For transactions with several instructions, `index` is the one expected to fail
+fn assert_error_at(result: Result<(), TransactionError>, index: u8, error: AmmError) {
+    assert_eq!(
+        result,
+        Err(TransactionError::InstructionError(
+            index,
+            InstructionError::Custom(error.into())
+        ))
+    );
+}
+
+#[test]
+fn init_creates_an_empty_pool() {
+    let pool = Pool::new();
+
+    let config = pool.config();
+    assert_eq!(config.seed, SEED);
+    assert_eq!(config.fee, FEE);
+    assert_eq!(config.mint_x, pool.mint_x);
+    assert_eq!(config.mint_y, pool.mint_y);
+    assert_eq!(config.authority, Some(pool.authority.pubkey()));
+    assert!(!config.locked);
+    assert_eq!(pool.lp_supply(), 0);
+}
+
+#[test]
+fn init_registers_the_pool_under_its_mint_pair_and_fee() {
+    let pool = Pool::new();
+
+    let account = pool.svm.get_account(&pool.registry(FEE)).unwrap();
+    let registry = amm::Registry::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert_eq!(registry.config, pool.config);
+    assert_eq!(registry.mint_a, pool.mint_x.min(pool.mint_y));
+    assert_eq!(registry.mint_b, pool.mint_x.max(pool.mint_y));
+    assert_eq!(registry.fee_tier, FEE);
+}
+
+#[test]
+fn init_rejects_a_second_pool_for_the_same_pair_and_fee() {
+    let mut pool = Pool::new();
+
+    assert!(pool.init_with(SEED + 1, FEE).is_err());
+    pool.init_with(SEED + 1, FEE + 1).unwrap();
+}
+
+#[test]
+fn stable_swap_init_rejects_mints_with_different_decimals() {
+    let mut pool = Pool::new();
+    pool.mint_y = pool.create_mint_with(spl_token::ID, DECIMALS + 3, None);
+
+    assert_error(
+        pool.init_curve(SEED + 1, FEE, CurveType::StableSwap, 100),
+        AmmError::InvalidConfig,
+    );
+    // Constant product pools price the ratio of the reserves, so any decimals work
+    pool.init_curve(SEED + 1, FEE, CurveType::ConstantProduct, 0)
+        .unwrap();
+
+    pool.mint_y = pool.create_mint_with(spl_token::ID, DECIMALS, None);
+    pool.init_curve(SEED + 2, FEE, CurveType::StableSwap, 100)
+        .unwrap();
+}
+
+#[test]
+fn init_twice_fails() {
+    let mut pool = Pool::new();
+    assert!(pool.init().is_err());
+}
+
+#[test]
+fn first_deposit_mints_geometric_mean_and_locks_minimum_liquidity() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+
+    let user = pool.user.pubkey();
+    assert_eq!(
+        pool.balance(&user, &pool.mint_lp),
+        2_000_000 - MINIMUM_LIQUIDITY
+    );
+    assert_eq!(pool.balance(&pool.config, &pool.mint_lp), MINIMUM_LIQUIDITY);
+    assert_eq!(pool.lp_supply(), 2_000_000);
+    assert_eq!(pool.balance(&pool.config, &pool.mint_x), 4_000_000);
+    assert_eq!(pool.balance(&pool.config, &pool.mint_y), 1_000_000);
+}
+
+#[test]
+fn first_deposit_below_minimum_liquidity_fails() {
+    let mut pool = Pool::new();
+    assert_error(
+        pool.deposit(1, 1_000, 1_000),
+        AmmError::InitialLiquidityTooLow,
+    );
+    assert_eq!(pool.lp_supply(), 0);
+}
+
+#[test]
+fn deposit_is_pro_rata_and_grows_lp_supply() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+
+    // 10% of the supply needs 10% of each reserve
+    pool.deposit(200_000, 400_000, 100_000).unwrap();
+
+    assert_eq!(pool.lp_supply(), 2_200_000);
+    assert_eq!(pool.balance(&pool.config, &pool.mint_x), 4_400_000);
+    assert_eq!(pool.balance(&pool.config, &pool.mint_y), 1_100_000);
+}
+
+#[test]
+fn deposit_above_max_amounts_fails() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+
+    assert_error(
+        pool.deposit(200_000, 399_999, 100_000),
+        AmmError::SlippageExceeded,
+    );
+    assert_error(
+        pool.deposit(200_000, 400_000, 99_999),
+        AmmError::SlippageExceeded,
+    );
+    // The first depositor gets fewer LP tokens than asked for
+    let mut empty = Pool::new();
+    assert_error(
+        empty.deposit(2_000_000, 4_000_000, 1_000_000),
+        AmmError::SlippageExceeded,
+    );
+}
+
+#[test]
+fn zero_deposit_fails() {
+    let mut pool = Pool::new();
+    assert_error(
+        pool.deposit(0, 1_000_000, 1_000_000),
+        AmmError::InvalidAmount,
+    );
+}
+
+#[test]
+fn withdraw_is_pro_rata_and_burns_lp() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+
+    let user = pool.user.pubkey();
+    let (x_before, y_before) = (
+        pool.balance(&user, &pool.mint_x),
+        pool.balance(&user, &pool.mint_y),
+    );
+
+    pool.withdraw(1_000_000, 2_000_000, 500_000).unwrap();
+
+    assert_eq!(pool.lp_supply(), 1_000_000);
+    assert_eq!(
+        pool.balance(&user, &pool.mint_lp),
+        1_000_000 - MINIMUM_LIQUIDITY
+    );
+    assert_eq!(pool.balance(&user, &pool.mint_x), x_before + 2_000_000);
+    assert_eq!(pool.balance(&user, &pool.mint_y), y_before + 500_000);
+}
+
+#[test]
+fn withdraw_everything_leaves_the_locked_liquidity() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+
+    let user_lp = pool.balance(&pool.user.pubkey(), &pool.mint_lp);
+    pool.withdraw(user_lp, 0, 0).unwrap();
+
+    assert_eq!(pool.lp_supply(), MINIMUM_LIQUIDITY);
+    assert_eq!(pool.balance(&pool.config, &pool.mint_x), 2_000);
+    assert_eq!(pool.balance(&pool.config, &pool.mint_y), 500);
+}
+
+#[test]
+fn withdraw_below_min_amounts_fails() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+
+    assert_error(
+        pool.withdraw(1_000_000, 2_000_001, 500_000),
+        AmmError::SlippageExceeded,
+    );
+    assert_error(
+        pool.withdraw(1_000_000, 2_000_000, 500_001),
+        AmmError::SlippageExceeded,
+    );
+    assert_eq!(pool.lp_supply(), 2_000_000);
+}
+
+#[test]
+fn withdraw_more_than_lp_supply_fails() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+
+    assert_error(
+        pool.withdraw(2_000_001, 0, 0),
+        AmmError::InsufficientLiquidity,
+    );
+}
+
+#[test]
+fn swap_pays_the_quoted_amount_in_both_directions() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+    let user = pool.user.pubkey();
+
+    for is_x in [true, false] {
+        let (mint_in, mint_out) = match is_x {
+            true => (pool.mint_x, pool.mint_y),
+            false => (pool.mint_y, pool.mint_x),
+        };
+        let quote = pool.quote_swap(is_x, 100_000);
+        let vault_in = pool.balance(&pool.config, &mint_in);
+        let vault_out = pool.balance(&pool.config, &mint_out);
+        let user_in = pool.balance(&user, &mint_in);
+        let user_out = pool.balance(&user, &mint_out);
+
+        pool.swap(is_x, 100_000, quote.amount_out).unwrap();
+
+        assert!(quote.amount_out > 0);
+        assert_eq!(pool.balance(&pool.config, &mint_in), vault_in + 100_000);
+        assert_eq!(
+            pool.balance(&pool.config, &mint_out),
+            vault_out - quote.amount_out
+        );
+        assert_eq!(pool.balance(&user, &mint_in), user_in - 100_000);
+        assert_eq!(pool.balance(&user, &mint_out), user_out + quote.amount_out);
+        // The fee stays in the pool, so the product of the reserves can only grow
+        assert!(
+            (vault_in + 100_000) as u128 * (vault_out - quote.amount_out) as u128
+                >= vault_in as u128 * vault_out as u128
+        );
+    }
+    assert_eq!(pool.lp_supply(), 2_000_000);
+}
+
+#[test]
+fn swap_below_min_amount_out_fails() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+
+    for is_x in [true, false] {
+        let quote = pool.quote_swap(is_x, 100_000);
+        assert_error(
+            pool.swap(is_x, 100_000, quote.amount_out + 1),
+            AmmError::SlippageExceeded,
+        );
+    }
+    assert_eq!(pool.balance(&pool.config, &pool.mint_x), 4_000_000);
+    assert_eq!(pool.balance(&pool.config, &pool.mint_y), 1_000_000);
+}
+
+#[test]
+fn swap_on_a_locked_pool_fails() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+
+    pool.lock().unwrap();
+    assert_error(pool.swap(true, 100_000, 0), AmmError::PoolLocked);
+    assert_error(pool.swap(false, 100_000, 0), AmmError::PoolLocked);
+
+    pool.unlock().unwrap();
+    pool.swap(true, 100_000, 0).unwrap();
+}
+
+#[test]
+fn flash_loan_repaid_in_the_same_transaction_pays_the_fee_to_lps() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+    let user = pool.user.insecure_clone();
+    let user_x = pool.balance(&user.pubkey(), &pool.mint_x);
+    let fee = pool.config().pool_config().flash_loan_fee(1_000_000);
+
+    let ixs = [
+        pool.flash_borrow_ix(true, 1_000_000),
+        pool.flash_repay_ix(pool.config),
+    ];
+    pool.send_all(&ixs, &user).unwrap();
+
+    let config = pool.config();
+    assert!(fee > 0);
+    assert_eq!(config.flash_loan_amount, 0);
+    assert_eq!(config.protocol_fees_x, 0);
+    assert_eq!(pool.balance(&user.pubkey(), &pool.mint_x), user_x - fee);
+    assert_eq!(pool.balance(&pool.config, &pool.mint_x), 4_000_000 + fee);
+    assert_eq!(
+        config.reserves(4_000_000 + fee, 1_000_000).unwrap(),
+        (4_000_000 + fee, 1_000_000)
+    );
+    assert_eq!(pool.lp_supply(), 2_000_000);
+}
+
+#[test]
+fn flash_borrow_without_a_repay_fails() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+    let user = pool.user.insecure_clone();
+
+    let ix = pool.flash_borrow_ix(true, 1_000_000);
+    assert_error(pool.send(ix, &user), AmmError::FlashLoanNotRepaid);
+
+    // A repay against another pool does not count
+    pool.init_with(SEED + 1, FEE + 1).unwrap();
+    let other = Pubkey::find_program_address(&[b"config", &(SEED + 1).to_le_bytes()], &amm::ID).0;
+    let ixs = [
+        pool.flash_borrow_ix(true, 1_000_000),
+        pool.flash_repay_ix(other),
+    ];
+    assert_error(pool.send_all(&ixs, &user), AmmError::FlashLoanNotRepaid);
+
+    assert_eq!(pool.config().flash_loan_amount, 0);
+    assert_eq!(pool.balance(&pool.config, &pool.mint_x), 4_000_000);
+}
+
+#[test]
+fn pool_rejects_swaps_and_liquidity_changes_during_a_flash_loan() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+    let user = pool.user.insecure_clone();
+
+    for ix in [
+        pool.swap_ix(true, 100_000, 0),
+        pool.deposit_ix(200_000, 500_000, 100_000),
+        pool.withdraw_ix(200_000, 0, 0),
+    ] {
+        let ixs = [
+            pool.flash_borrow_ix(false, 500_000),
+            ix,
+            pool.flash_repay_ix(pool.config),
+        ];
+        assert_error_at(pool.send_all(&ixs, &user), 1, AmmError::FlashLoanActive);
+    }
+
+    assert_eq!(pool.lp_supply(), 2_000_000);
+    assert_eq!(pool.balance(&pool.config, &pool.mint_x), 4_000_000);
+    assert_eq!(pool.balance(&pool.config, &pool.mint_y), 1_000_000);
+}
+
+// Pools over x/y at `SEED` and y/z at `SEED + 1`, with `pool` left on the first one
+fn route_pools() -> (Pool, Pubkey) {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+
+    let (mint_x, mint_y) = (pool.mint_x, pool.mint_y);
+    let mint_z = pool.create_mint();
+    pool.mint_to(mint_z, 1_000_000_000);
+
+    pool.select(SEED + 1, mint_y, mint_z);
+    pool.init_with(SEED + 1, FEE).unwrap();
+    pool.deposit(1, 1_000_000, 2_000_000).unwrap();
+
+    pool.select(SEED, mint_x, mint_y);
+    (pool, mint_z)
+}
+
+#[test]
+fn route_swap_through_two_pools_matches_chained_quotes() {
+    let (mut pool, mint_z) = route_pools();
+    let (mint_x, mint_y, first) = (pool.mint_x, pool.mint_y, pool.config);
+    let user = pool.user.insecure_clone();
+
+    let first_quote = pool.quote_swap(true, 100_000);
+    pool.select(SEED + 1, mint_y, mint_z);
+    let second = pool.config;
+    let second_quote = pool.quote_swap(true, first_quote.amount_out);
+    pool.select(SEED, mint_x, mint_y);
+
+    let user_x = pool.balance(&user.pubkey(), &mint_x);
+    let user_z = pool.balance(&user.pubkey(), &mint_z);
+    let ix = pool.route_swap_ix(
+        &[(first, mint_x, mint_y), (second, mint_y, mint_z)],
+        100_000,
+        second_quote.amount_out,
+    );
+    pool.send(ix, &user).unwrap();
+
+    assert_eq!(pool.balance(&user.pubkey(), &mint_x), user_x - 100_000);
+    assert_eq!(
+        pool.balance(&user.pubkey(), &mint_z),
+        user_z + second_quote.amount_out
+    );
+    assert_eq!(pool.balance(&first, &mint_x), 4_000_000 + 100_000);
+    assert_eq!(
+        pool.balance(&first, &mint_y),
+        1_000_000 - first_quote.amount_out
+    );
+    assert_eq!(
+        pool.balance(&second, &mint_y),
+        1_000_000 + first_quote.amount_out
+    );
+    assert_eq!(
+        pool.balance(&second, &mint_z),
+        2_000_000 - second_quote.amount_out
+    );
+}
+
+#[test]
+fn route_swap_below_min_amount_out_fails() {
+    let (mut pool, mint_z) = route_pools();
+    let (mint_x, mint_y, first) = (pool.mint_x, pool.mint_y, pool.config);
+    let user = pool.user.insecure_clone();
+
+    let first_quote = pool.quote_swap(true, 100_000);
+    pool.select(SEED + 1, mint_y, mint_z);
+    let second = pool.config;
+    let second_quote = pool.quote_swap(true, first_quote.amount_out);
+    pool.select(SEED, mint_x, mint_y);
+
+    let ix = pool.route_swap_ix(
+        &[(first, mint_x, mint_y), (second, mint_y, mint_z)],
+        100_000,
+        second_quote.amount_out + 1,
+    );
+    assert_error(pool.send(ix, &user), AmmError::SlippageExceeded);
+    assert_eq!(pool.balance(&first, &mint_x), 4_000_000);
+    assert_eq!(pool.balance(&second, &mint_z), 2_000_000);
+}
+
+#[test]
+fn route_swap_through_the_same_pool_twice_fails() {
+    let (mut pool, _) = route_pools();
+    let (mint_x, mint_y, config) = (pool.mint_x, pool.mint_y, pool.config);
+    let user = pool.user.insecure_clone();
+
+    let ix = pool.route_swap_ix(
+        &[(config, mint_x, mint_y), (config, mint_y, mint_x)],
+        100_000,
+        0,
+    );
+    assert_error(pool.send(ix, &user), AmmError::InvalidRoute);
+}
+
+#[test]
+fn route_swap_with_another_pools_vault_fails() {
+    let (mut pool, mint_z) = route_pools();
+    let (mint_x, mint_y, first) = (pool.mint_x, pool.mint_y, pool.config);
+    let second = Pubkey::find_program_address(&[b"config", &(SEED + 1).to_le_bytes()], &amm::ID).0;
+    let user = pool.user.insecure_clone();
+
+    // The first hop pays out of the second pool's y vault. Its `vault_out` is the last of the
+    // hop's accounts, which follow the four `RouteSwap` accounts
+    let mut ix = pool.route_swap_ix(
+        &[(first, mint_x, mint_y), (second, mint_y, mint_z)],
+        100_000,
+        0,
+    );
+    ix.accounts[4 + 5].pubkey = pool.ata(&second, &mint_y);
+    assert_error(pool.send(ix, &user), AmmError::InvalidConfig);
+    assert_eq!(pool.balance(&second, &mint_y), 1_000_000);
+}
+
+#[test]
+fn transfer_fee_pool_reserves_match_what_the_vaults_received() {
+    let mut pool = Pool::with_transfer_fee(100);
+    let user = pool.user.pubkey();
+
+    pool.deposit(1, 4_040_000, 1_010_000).unwrap();
+
+    let x = 4_040_000 - pool.transfer_fee(&pool.mint_x, 4_040_000);
+    let y = 1_010_000 - pool.transfer_fee(&pool.mint_y, 1_010_000);
+    assert_eq!(pool.balance(&pool.config, &pool.mint_x), x);
+    assert_eq!(pool.balance(&pool.config, &pool.mint_y), y);
+    assert_eq!(pool.config().reserves(x, y).unwrap(), (x, y));
+    // LP is minted on the received amounts, not on what the user sent
+    assert_eq!(
+        pool.balance(&user, &pool.mint_lp),
+        amm_math::quote_initial_deposit(x, y).unwrap().lp
+    );
+
+    // Swaps are priced on the amount that reaches the vault
+    let fee_in = pool.transfer_fee(&pool.mint_x, 100_000);
+    let quote = pool.quote_swap(true, 100_000 - fee_in);
+    let user_y = pool.balance(&user, &pool.mint_y);
+    pool.swap(true, 100_000, 0).unwrap();
+
+    let (x, y) = (x + 100_000 - fee_in, y - quote.amount_out);
+    assert_eq!(pool.balance(&pool.config, &pool.mint_x), x);
+    assert_eq!(pool.balance(&pool.config, &pool.mint_y), y);
+    assert_eq!(
+        pool.balance(&user, &pool.mint_y),
+        user_y + quote.amount_out - pool.transfer_fee(&pool.mint_y, quote.amount_out)
+    );
+
+    // Deposits are grossed up so the vaults receive at least what the curve requires
+    let snapshot = Snapshot {
+        vault_x: x,
+        vault_y: y,
+        lp_supply: pool.lp_supply(),
+    };
+    let quote = amm_math::quote_deposit(&pool.config().pool_config(), &snapshot, 200_000).unwrap();
+    let (user_x, user_y) = (
+        pool.balance(&user, &pool.mint_x),
+        pool.balance(&user, &pool.mint_y),
+    );
+    pool.deposit(200_000, u64::MAX, u64::MAX).unwrap();
+
+    let sent_x = user_x - pool.balance(&user, &pool.mint_x);
+    let sent_y = user_y - pool.balance(&user, &pool.mint_y);
+    let received_x = pool.balance(&pool.config, &pool.mint_x) - x;
+    let received_y = pool.balance(&pool.config, &pool.mint_y) - y;
+    assert_eq!(received_x, sent_x - pool.transfer_fee(&pool.mint_x, sent_x));
+    assert_eq!(received_y, sent_y - pool.transfer_fee(&pool.mint_y, sent_y));
+    assert!(received_x >= quote.x && received_y >= quote.y);
+    assert_eq!(
+        pool.config()
+            .reserves(x + received_x, y + received_y)
+            .unwrap(),
+        (x + received_x, y + received_y)
+    );
+}
+
+#[test]
+fn init_rejects_mints_from_different_token_programs() {
+    let mut pool = Pool::new();
+    pool.mint_y = pool.create_mint_with(token_2022::ID, DECIMALS, None);
+
+    // Both mints have to belong to the one `token_program` the pool is created with
+    for token_program in [spl_token::ID, token_2022::ID] {
+        pool.token_program = token_program;
+        assert_eq!(
+            pool.init_with(SEED + 1, FEE),
+            Err(TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(ErrorCode::ConstraintMintTokenProgram.into())
+            ))
+        );
+    }
+}
+
+#[test]
+fn locked_pool_rejects_deposits_and_withdrawals() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+
+    pool.lock().unwrap();
+    assert!(pool.config().locked);
+    assert_error(
+        pool.deposit(200_000, 400_000, 100_000),
+        AmmError::PoolLocked,
+    );
+    assert_error(pool.withdraw(1_000, 0, 0), AmmError::PoolLocked);
+
+    pool.unlock().unwrap();
+    pool.deposit(200_000, 400_000, 100_000).unwrap();
+    pool.withdraw(1_000, 0, 0).unwrap();
+}
+
+#[test]
+fn only_the_authority_can_lock() {
+    let mut pool = Pool::new();
+    let user = pool.user.insecure_clone();
+
+    assert_error(
+        pool.admin(amm::instruction::Lock {}.data(), &user),
+        AmmError::InvalidAuthority,
+    );
+    assert!(!pool.config().locked);
+}
+
+// Every instruction gated on `Admin`. The account constraints reject the signer before the
+// arguments are looked at, so they only need to deserialize
+fn admin_instructions() -> Vec<Vec<u8>> {
+    vec![
+        amm::instruction::Lock {}.data(),
+        amm::instruction::Unlock {}.data(),
+        amm::instruction::UpdateFee { fee: 50 }.data(),
+        amm::instruction::UpdateProtocolFee {
+            protocol_fee: 1_000,
+        }
+        .data(),
+        amm::instruction::SetDynamicFee {
+            enabled: true,
+            fee_min: 10,
+            fee_max: 100,
+        }
+        .data(),
+        amm::instruction::RampAmp {
+            target_amp: 100,
+            stop_ts: i64::MAX,
+        }
+        .data(),
+        amm::instruction::TransferAuthority {
+            new_authority: Some(Pubkey::new_unique()),
+        }
+        .data(),
+    ]
+}
+
+#[test]
+fn admin_instructions_reject_other_signers() {
+    let mut pool = Pool::new();
+    let user = pool.user.insecure_clone();
+
+    for data in admin_instructions() {
+        assert_error(pool.admin(data, &user), AmmError::InvalidAuthority);
+    }
+    let config = pool.config();
+    assert_eq!(config.authority, Some(pool.authority.pubkey()));
+    assert_eq!(config.fee, FEE);
+    assert!(!config.locked);
+}
+
+#[test]
+fn transfer_authority_hands_over_the_admin_instructions() {
+    let mut pool = Pool::new();
+    let authority = pool.authority.insecure_clone();
+    let user = pool.user.insecure_clone();
+
+    let data = amm::instruction::TransferAuthority {
+        new_authority: Some(user.pubkey()),
+    }
+    .data();
+    pool.admin(data, &authority).unwrap();
+
+    assert_error(pool.lock(), AmmError::InvalidAuthority);
+    pool.admin(amm::instruction::Lock {}.data(), &user).unwrap();
+    assert!(pool.config().locked);
+}
+
+#[test]
+fn renounced_authority_makes_the_pool_immutable() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+    let authority = pool.authority.insecure_clone();
+
+    let data = amm::instruction::TransferAuthority {
+        new_authority: None,
+    }
+    .data();
+    pool.admin(data, &authority).unwrap();
+    assert_eq!(pool.config().authority, None);
+
+    for data in admin_instructions() {
+        assert_error(pool.admin(data, &authority), AmmError::PoolImmutable);
+    }
+    assert_error(pool.close_pool(&authority), AmmError::PoolImmutable);
+
+    // Trading carries on without an authority
+    pool.swap(true, 100_000, 0).unwrap();
+    pool.deposit(200_000, 500_000, 100_000).unwrap();
+}
+
+#[test]
+fn protocol_fees_accrue_apart_from_the_reserves_until_collected() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+    let authority = pool.authority.insecure_clone();
+
+    // Half of every swap fee goes to the protocol
+    let data = amm::instruction::UpdateProtocolFee {
+        protocol_fee: 5_000,
+    }
+    .data();
+    pool.admin(data, &authority).unwrap();
+
+    let quote_x = pool.quote_swap(true, 1_000_000);
+    pool.swap(true, 1_000_000, 0).unwrap();
+    let quote_y = pool.quote_swap(false, 500_000);
+    pool.swap(false, 500_000, 0).unwrap();
+
+    let config = pool.config();
+    assert!(quote_x.protocol_fee > 0 && quote_y.protocol_fee > 0);
+    assert_eq!(quote_x.protocol_fee, quote_x.fee / 2);
+    assert_eq!(config.protocol_fees_x, quote_x.protocol_fee);
+    assert_eq!(config.protocol_fees_y, quote_y.protocol_fee);
+
+    // The protocol's share sits in the vaults but is not part of the LP reserves
+    let vault_x = pool.balance(&pool.config, &pool.mint_x);
+    let vault_y = pool.balance(&pool.config, &pool.mint_y);
+    let reserves = config.reserves(vault_x, vault_y).unwrap();
+    assert_eq!(
+        reserves,
+        (
+            vault_x - quote_x.protocol_fee,
+            vault_y - quote_y.protocol_fee
+        )
+    );
+
+    let recipient = Keypair::new().pubkey();
+    pool.collect_protocol_fees(&authority, recipient).unwrap();
+
+    let config = pool.config();
+    assert_eq!((config.protocol_fees_x, config.protocol_fees_y), (0, 0));
+    assert_eq!(pool.balance(&recipient, &pool.mint_x), quote_x.protocol_fee);
+    assert_eq!(pool.balance(&recipient, &pool.mint_y), quote_y.protocol_fee);
+    let vault_x = pool.balance(&pool.config, &pool.mint_x);
+    let vault_y = pool.balance(&pool.config, &pool.mint_y);
+    assert_eq!((vault_x, vault_y), reserves);
+    assert_eq!(config.reserves(vault_x, vault_y).unwrap(), reserves);
+
+    // Nothing left to collect
+    assert_error(
+        pool.collect_protocol_fees(&authority, recipient),
+        AmmError::InvalidAmount,
+    );
+}
+
+#[test]
+fn only_the_authority_can_collect_protocol_fees() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+    let authority = pool.authority.insecure_clone();
+    let user = pool.user.insecure_clone();
+
+    let data = amm::instruction::UpdateProtocolFee {
+        protocol_fee: 5_000,
+    }
+    .data();
+    pool.admin(data, &authority).unwrap();
+    pool.swap(true, 1_000_000, 0).unwrap();
+    let accrued = pool.config().protocol_fees_x;
+
+    assert_error(
+        pool.collect_protocol_fees(&user, user.pubkey()),
+        AmmError::InvalidAuthority,
+    );
+    assert_eq!(pool.config().protocol_fees_x, accrued);
+    assert_eq!(
+        pool.balance(&user.pubkey(), &pool.mint_x),
+        1_000_000_000 - 4_000_000 - 1_000_000
+    );
+}
+
+#[test]
+fn close_pool_before_any_deposit_reclaims_every_account() {
+    let mut pool = Pool::new();
+    let authority = pool.authority.insecure_clone();
+
+    pool.close_pool(&authority).unwrap();
+
+    assert!(pool.svm.get_account(&pool.config).is_none());
+    assert!(pool.svm.get_account(&pool.registry(FEE)).is_none());
+    for mint in [pool.mint_lp, pool.mint_x, pool.mint_y] {
+        let vault = pool.ata(&pool.config, &mint);
+        assert!(pool.svm.get_account(&vault).is_none());
+    }
+
+    // The LP mint can't be closed, but nothing can mint from it anymore
+    let account = pool.svm.get_account(&pool.mint_lp).unwrap();
+    let mint_lp = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&account.data)
+        .unwrap()
+        .base;
+    assert!(mint_lp.mint_authority.is_none());
+
+    // The pair and fee tier are free again
+    pool.init_with(SEED + 1, FEE).unwrap();
+}
+
+#[test]
+fn close_pool_with_the_locked_liquidity_reserves_fails() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+    let user_lp = pool.balance(&pool.user.pubkey(), &pool.mint_lp);
+    pool.withdraw(user_lp, 0, 0).unwrap();
+
+    // The locked minimum liquidity still backs 2,000 x and 500 y that belong to no one
+    let authority = pool.authority.insecure_clone();
+    assert_error(pool.close_pool(&authority), AmmError::PoolNotEmpty);
+    assert!(pool.svm.get_account(&pool.config).is_some());
+}
+
+#[test]
+fn close_pool_with_a_donation_in_the_vaults_fails() {
+    let mut pool = Pool::new();
+    let config = pool.config;
+    pool.mint_to_owner(pool.mint_x, config, 1);
+
+    let authority = pool.authority.insecure_clone();
+    assert_error(pool.close_pool(&authority), AmmError::PoolNotEmpty);
+    assert!(pool.svm.get_account(&pool.config).is_some());
+}
+
+#[test]
+fn close_pool_with_outstanding_lp_fails() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+
+    let authority = pool.authority.insecure_clone();
+    assert_error(pool.close_pool(&authority), AmmError::PoolNotEmpty);
+    assert!(pool.svm.get_account(&pool.config).is_some());
+}
+
+#[test]
+fn close_pool_with_unemitted_farm_rewards_fails() {
+    let mut pool = Pool::new();
+    pool.init_farm(100, 1_000_000);
+
+    let authority = pool.authority.insecure_clone();
+    assert_error(pool.close_pool(&authority), AmmError::PoolNotEmpty);
+    assert!(pool.svm.get_account(&pool.config).is_some());
+}
+
+#[test]
+fn only_the_authority_can_close_a_pool() {
+    let mut pool = Pool::new();
+    let user = pool.user.insecure_clone();
+
+    assert_error(pool.close_pool(&user), AmmError::InvalidAuthority);
+    assert!(pool.svm.get_account(&pool.config).is_some());
+}
+
+#[test]
+fn place_order_escrows_the_tokens_and_bounty() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+    let user = pool.user.pubkey();
+    let x_before = pool.balance(&user, &pool.mint_x);
+
+    pool.place_order(0, 40_000, 9_000, 5_000).unwrap();
+
+    let order = pool.order(0);
+    assert_eq!(pool.balance(&user, &pool.mint_x), x_before - 40_000);
+    assert_eq!(pool.balance(&order, &pool.mint_x), 40_000);
+
+    let account = pool.svm.get_account(&order).unwrap();
+    let state = amm::Order::try_deserialize(&mut account.data.as_slice()).unwrap();
+    assert!(state.is_x);
+    assert_eq!(state.amount_in, 40_000);
+    assert_eq!(state.min_amount_out, 9_000);
+    assert_eq!(
+        account.lamports,
+        pool.svm
+            .minimum_balance_for_rent_exemption(account.data.len())
+            + 5_000
+    );
+}
+
+#[test]
+fn cancel_order_returns_the_tokens() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+    let user = pool.user.pubkey();
+    let x_before = pool.balance(&user, &pool.mint_x);
+
+    pool.place_order(0, 40_000, 9_000, 5_000).unwrap();
+    pool.cancel_order(0).unwrap();
+
+    assert_eq!(pool.balance(&user, &pool.mint_x), x_before);
+    assert!(pool.svm.get_account(&pool.order(0)).is_none());
+}
+
+#[test]
+fn fill_orders_executes_against_the_pool_and_pays_the_keeper() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+    let user = pool.user.pubkey();
+    let y_before = pool.balance(&user, &pool.mint_y);
+
+    pool.place_order(0, 40_000, 9_000, 5_000).unwrap();
+
+    let keeper = Keypair::new();
+    pool.svm.airdrop(&keeper.pubkey(), 1_000_000_000).unwrap();
+    pool.fill_orders(&[0], &keeper).unwrap();
+
+    // 40,000 x less the 0.3% fee against 4,000,000 / 1,000,000 reserves
+    let amount_out = 1_000_000 * 39_880 / (4_000_000 + 39_880);
+    assert_eq!(pool.balance(&user, &pool.mint_y), y_before + amount_out);
+    assert_eq!(pool.balance(&pool.config, &pool.mint_x), 4_040_000);
+    assert!(pool.svm.get_account(&pool.order(0)).is_none());
+    // The bounty covers the fill's signature fee
+    assert_eq!(
+        pool.svm.get_balance(&keeper.pubkey()).unwrap(),
+        1_000_000_000
+    );
+}
+
+#[test]
+fn fill_orders_skips_orders_the_pool_price_does_not_reach() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+
+    pool.place_order(0, 40_000, 9_000, 0).unwrap();
+    pool.place_order(1, 40_000, 10_000, 0).unwrap();
+
+    let keeper = pool.authority.insecure_clone();
+    assert_error(pool.fill_orders(&[1], &keeper), AmmError::OrderNotFillable);
+
+    pool.fill_orders(&[0, 1], &keeper).unwrap();
+    assert!(pool.svm.get_account(&pool.order(0)).is_none());
+    assert_eq!(pool.balance(&pool.order(1), &pool.mint_x), 40_000);
+}
+
+#[test]
+fn set_dynamic_fee_stores_the_bounds() {
+    let mut pool = Pool::new();
+    let authority = pool.authority.insecure_clone();
+
+    let data = amm::instruction::SetDynamicFee {
+        enabled: true,
+        fee_min: 10,
+        fee_max: 100,
+    }
+    .data();
+    pool.admin(data, &authority).unwrap();
+
+    let config = pool.config();
+    assert!(config.dynamic_fee);
+    assert_eq!((config.fee_min, config.fee_max), (10, 100));
+    assert_eq!(config.fee, FEE);
+}
+
+#[test]
+fn set_dynamic_fee_rejects_inverted_bounds() {
+    let mut pool = Pool::new();
+    let authority = pool.authority.insecure_clone();
+
+    let data = amm::instruction::SetDynamicFee {
+        enabled: true,
+        fee_min: 100,
+        fee_max: 10,
+    }
+    .data();
+    assert_error(pool.admin(data, &authority), AmmError::InvalidFee);
+    assert!(!pool.config().dynamic_fee);
+}
+
+#[test]
+fn staked_lp_earns_rewards_per_second() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+    let reward_mint = pool.init_farm(100, 1_000_000);
+    let user = pool.user.pubkey();
+
+    pool.stake_lp(amm::instruction::StakeLp { amount: 1_000_000 }.data())
+        .unwrap();
+    assert_eq!(pool.balance(&pool.farm(), &pool.mint_lp), 1_000_000);
+
+    pool.warp(10);
+    pool.harvest(reward_mint).unwrap();
+    assert_eq!(pool.balance(&user, &reward_mint), 1_000);
+
+    // Nothing new has accrued within the same second
+    assert_error(pool.harvest(reward_mint), AmmError::InvalidAmount);
+}
+
+#[test]
+fn unstake_returns_lp_and_keeps_rewards_for_harvest() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+    let reward_mint = pool.init_farm(100, 1_000_000);
+    let user = pool.user.pubkey();
+    let lp_before = pool.balance(&user, &pool.mint_lp);
+
+    pool.stake_lp(amm::instruction::StakeLp { amount: 1_000_000 }.data())
+        .unwrap();
+    pool.warp(10);
+    pool.stake_lp(amm::instruction::UnstakeLp { amount: 1_000_000 }.data())
+        .unwrap();
+    assert_eq!(pool.balance(&user, &pool.mint_lp), lp_before);
+
+    // Unstaked, so the next ten seconds earn nothing
+    pool.warp(10);
+    pool.harvest(reward_mint).unwrap();
+    assert_eq!(pool.balance(&user, &reward_mint), 1_000);
+}
+
+#[test]
+fn emissions_stop_when_the_farm_runs_out_of_rewards() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+    let reward_mint = pool.init_farm(100, 500);
+
+    pool.stake_lp(amm::instruction::StakeLp { amount: 1_000_000 }.data())
+        .unwrap();
+    pool.warp(10);
+    pool.harvest(reward_mint).unwrap();
+
+    assert_eq!(pool.balance(&pool.user.pubkey(), &reward_mint), 500);
+}
+
+#[test]
+fn unstake_more_than_staked_fails() {
+    let mut pool = Pool::new();
+    pool.deposit(1, 4_000_000, 1_000_000).unwrap();
+    pool.init_farm(100, 1_000_000);
+
+    pool.stake_lp(amm::instruction::StakeLp { amount: 1_000 }.data())
+        .unwrap();
+    assert_error(
+        pool.stake_lp(amm::instruction::UnstakeLp { amount: 1_001 }.data()),
+        AmmError::InsufficientBalance,
+    );
+}
+
+fn increase_liquidity(tick_lower: i32, tick_upper: i32, liquidity: u128) -> Vec<u8> {
+    amm::instruction::IncreaseLiquidity {
+        tick_lower,
+        tick_upper,
+        liquidity,
+        max_x: u64::MAX,
+        max_y: u64::MAX,
+    }
+    .data()
+}
+
+#[test]
+fn position_around_the_price_deposits_both_sides() {
+    let mut pool = Pool::new();
+    pool.init_concentrated(PRICE_ONE).unwrap();
+
+    pool.modify_position(-600, 600, increase_liquidity(-600, 600, 100_000_000))
+        .unwrap();
+
+    let concentrated = pool.concentrated();
+    let x = pool.balance(&concentrated, &pool.mint_x);
+    let y = pool.balance(&concentrated, &pool.mint_y);
+    // 100,000,000 * (1 - 1.0001^-300), rounded up, on both sides of a price of 1
+    assert!(x > 2_950_000 && x < 3_000_000);
+    assert!(x.abs_diff(y) <= 1);
+    assert_eq!(pool.concentrated_pool().liquidity, 100_000_000);
+    assert_eq!(pool.concentrated_pool().ticks.len(), 2);
+}
+
+#[test]
+fn position_above_the_price_holds_only_x() {
+    let mut pool = Pool::new();
+    pool.init_concentrated(PRICE_ONE).unwrap();
+
+    pool.modify_position(600, 1_200, increase_liquidity(600, 1_200, 100_000_000))
+        .unwrap();
+
+    let concentrated = pool.concentrated();
+    assert!(pool.balance(&concentrated, &pool.mint_x) > 0);
+    assert_eq!(pool.balance(&concentrated, &pool.mint_y), 0);
+    // Out of range, so none of it is active
+    assert_eq!(pool.concentrated_pool().liquidity, 0);
+}
+
+#[test]
+fn position_bounds_off_the_tick_spacing_fail() {
+    let mut pool = Pool::new();
+    pool.init_concentrated(PRICE_ONE).unwrap();
+
+    assert_error(
+        pool.modify_position(-600, 610, increase_liquidity(-600, 610, 100_000_000)),
+        AmmError::InvalidTick,
+    );
+    assert_error(
+        pool.modify_position(600, 600, increase_liquidity(600, 600, 100_000_000)),
+        AmmError::InvalidTick,
+    );
+}
+
+#[test]
+fn swap_concentrated_pays_fees_to_the_position_in_range() {
+    let mut pool = Pool::new();
+    pool.init_concentrated(PRICE_ONE).unwrap();
+    pool.modify_position(-600, 600, increase_liquidity(-600, 600, 100_000_000))
+        .unwrap();
+    let user = pool.user.pubkey();
+
+    let x_before = pool.balance(&user, &pool.mint_x);
+    let y_before = pool.balance(&user, &pool.mint_y);
+    pool.swap_concentrated(false, 1_000_000, 980_000).unwrap();
+
+    // Close to the constant-product output at this liquidity, less the 0.3% fee
+    let amount_out = pool.balance(&user, &pool.mint_x) - x_before;
+    assert!(amount_out > 987_000 && amount_out < 997_000);
+    assert_eq!(pool.balance(&user, &pool.mint_y), y_before - 1_000_000);
+    assert!(pool.concentrated_pool().sqrt_price > PRICE_ONE);
+
+    // The only position in range earned the whole fee, less rounding
+    let data = amm::instruction::CollectFees {
+        tick_lower: -600,
+        tick_upper: 600,
+    }
+    .data();
+    pool.modify_position(-600, 600, data).unwrap();
+    let fees = pool.balance(&user, &pool.mint_y) - (y_before - 1_000_000);
+    assert!((2_999..=3_000).contains(&fees));
+}
+
+#[test]
+fn swap_concentrated_crosses_into_the_next_range() {
+    let mut pool = Pool::new();
+    pool.init_concentrated(PRICE_ONE).unwrap();
+    pool.modify_position(-60, 60, increase_liquidity(-60, 60, 400_000_000))
+        .unwrap();
+    pool.modify_position(60, 600, increase_liquidity(60, 600, 100_000_000))
+        .unwrap();
+
+    // More y than the first range holds pushes the price past tick 60
+    pool.swap_concentrated(false, 2_000_000, 0).unwrap();
+
+    let concentrated = pool.concentrated_pool();
+    assert!(concentrated.tick_current >= 60);
+    assert_eq!(concentrated.liquidity, 100_000_000);
+}
+
+#[test]
+fn decrease_liquidity_returns_tokens_and_collect_closes_the_position() {
+    let mut pool = Pool::new();
+    pool.init_concentrated(PRICE_ONE).unwrap();
+    let user = pool.user.pubkey();
+    let x_before = pool.balance(&user, &pool.mint_x);
+
+    pool.modify_position(-600, 600, increase_liquidity(-600, 600, 100_000_000))
+        .unwrap();
+    let data = amm::instruction::DecreaseLiquidity {
+        tick_lower: -600,
+        tick_upper: 600,
+        liquidity: 100_000_000,
+        min_x: 0,
+        min_y: 0,
+    }
+    .data();
+    pool.modify_position(-600, 600, data).unwrap();
+
+    // Deposits round up and withdrawals down, so at most one unit stays behind
+    assert!(x_before - pool.balance(&user, &pool.mint_x) <= 1);
+    assert_eq!(pool.concentrated_pool().liquidity, 0);
+    assert!(pool.concentrated_pool().ticks.is_empty());
+
+    let data = amm::instruction::CollectFees {
+        tick_lower: -600,
+        tick_upper: 600,
+    }
+    .data();
+    pool.modify_position(-600, 600, data).unwrap();
+    assert!(pool.svm.get_account(&pool.position(-600, 600)).is_none());
+}
+
+#[test]
+fn positions_worth_less_than_the_minimum_fail() {
+    let mut pool = Pool::new();
+    pool.init_concentrated(PRICE_ONE).unwrap();
+
+    // A one-tick-spacing range needs far more liquidity than a wide one to hold the same tokens:
+    // 300,000,000 * (1 - 1.0001^-30) is about 900,000 of each token, under the minimum
+    assert_error(
+        pool.modify_position(-60, 60, increase_liquidity(-60, 60, 300_000_000)),
+        AmmError::PositionTooSmall,
+    );
+    pool.modify_position(-60, 60, increase_liquidity(-60, 60, 400_000_000))
+        .unwrap();
+
+    // Leaving less than the minimum behind fails, emptying the position does not
+    let decrease = |liquidity| {
+        amm::instruction::DecreaseLiquidity {
+            tick_lower: -60,
+            tick_upper: 60,
+            liquidity,
+            min_x: 0,
+            min_y: 0,
+        }
+        .data()
+    };
+    assert_error(
+        pool.modify_position(-60, 60, decrease(100_000_000)),
+        AmmError::PositionTooSmall,
+    );
+    pool.modify_position(-60, 60, decrease(400_000_000))
+        .unwrap();
+    assert!(pool.concentrated_pool().ticks.is_empty());
+}
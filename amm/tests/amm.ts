@@ -27,6 +27,36 @@ describe("AMM Production Tests", () => {
       assert.exists(program.methods.init, "init instruction missing");
       assert.exists(program.methods.deposit, "deposit instruction missing");
       assert.exists(program.methods.withdraw, "withdraw instruction missing");
+      assert.exists(program.methods.depositSingle, "depositSingle instruction missing");
+      assert.exists(program.methods.withdrawSingle, "withdrawSingle instruction missing");
+      assert.exists(program.methods.swap, "swap instruction missing");
+      assert.exists(program.methods.routeSwap, "routeSwap instruction missing");
+      assert.exists(program.methods.placeOrder, "placeOrder instruction missing");
+      assert.exists(program.methods.cancelOrder, "cancelOrder instruction missing");
+      assert.exists(program.methods.fillOrders, "fillOrders instruction missing");
+      assert.exists(program.methods.stakeLp, "stakeLp instruction missing");
+      assert.exists(program.methods.unstakeLp, "unstakeLp instruction missing");
+      assert.exists(program.methods.harvest, "harvest instruction missing");
+      assert.exists(program.methods.initConcentrated, "initConcentrated instruction missing");
+      assert.exists(program.methods.increaseLiquidity, "increaseLiquidity instruction missing");
+      assert.exists(program.methods.decreaseLiquidity, "decreaseLiquidity instruction missing");
+      assert.exists(program.methods.collectFees, "collectFees instruction missing");
+      assert.exists(program.methods.swapConcentrated, "swapConcentrated instruction missing");
+      assert.exists(program.methods.observe, "observe instruction missing");
+      assert.exists(program.methods.flashBorrow, "flashBorrow instruction missing");
+      assert.exists(program.methods.flashRepay, "flashRepay instruction missing");
+      assert.exists(program.methods.lock, "lock instruction missing");
+      assert.exists(program.methods.unlock, "unlock instruction missing");
+      assert.exists(program.methods.updateFee, "updateFee instruction missing");
+      assert.exists(program.methods.transferAuthority, "transferAuthority instruction missing");
+      assert.exists(program.methods.setDynamicFee, "setDynamicFee instruction missing");
+      assert.exists(program.methods.rampAmp, "rampAmp instruction missing");
+      assert.exists(program.methods.updateProtocolFee, "updateProtocolFee instruction missing");
+      assert.exists(program.methods.collectProtocolFees, "collectProtocolFees instruction missing");
+      assert.exists(program.methods.closePool, "closePool instruction missing");
+      assert.exists(program.methods.initFarm, "initFarm instruction missing");
+      assert.exists(program.methods.fundFarm, "fundFarm instruction missing");
+      assert.exists(program.methods.setRewardRate, "setRewardRate instruction missing");
       console.log("✓ All instructions available");
     });
 
@@ -43,8 +73,46 @@ describe("AMM Production Tests", () => {
         program.programId
       );
 
+      // Registry PDA, keyed by the sorted mint pair and fee tier (the fee passed to init)
+      const [mintA, mintB] = [Keypair.generate().publicKey, Keypair.generate().publicKey].sort(
+        (a, b) => Buffer.compare(a.toBuffer(), b.toBuffer())
+      );
+      const [registryPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("registry"),
+          mintA.toBuffer(),
+          mintB.toBuffer(),
+          new anchor.BN(fee).toArrayLike(Buffer, "le", 2),
+        ],
+        program.programId
+      );
+
+      // Concentrated pool and position PDAs, keyed by the position's tick range
+      const [concentratedPDA] = PublicKey.findProgramAddressSync(
+        [Buffer.from("concentrated"), seed.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      const tickBytes = (tick: number) => {
+        const buffer = Buffer.alloc(4);
+        buffer.writeInt32LE(tick);
+        return buffer;
+      };
+      const [positionPDA] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("position"),
+          concentratedPDA.toBuffer(),
+          Keypair.generate().publicKey.toBuffer(),
+          tickBytes(-600),
+          tickBytes(600),
+        ],
+        program.programId
+      );
+
       assert.exists(configPDA);
       assert.exists(lpMintPDA);
+      assert.exists(registryPDA);
+      assert.exists(concentratedPDA);
+      assert.exists(positionPDA);
       console.log("✓ PDA derivation logic validated");
     });
   });
@@ -59,15 +127,78 @@ describe("AMM Production Tests", () => {
         "InsufficientTokenY",
         "InsufficientBalance",
         "SlippageExceeded",
-        "InsufficientLiquidity"
+        "InsufficientLiquidity",
+        "InvalidFee",
+        "InvalidAuthority",
+        "PoolImmutable",
+        "Overflow",
+        "ObservationTooOld",
+        "FlashLoanActive",
+        "NoFlashLoan",
+        "FlashLoanNotRepaid",
+        "InvalidCurve",
+        "InvalidAmp",
+        "InvalidRamp",
+        "InitialLiquidityTooLow",
+        "InvalidRoute",
+        "PoolNotEmpty",
+        "InvalidOrder",
+        "OrderNotFillable",
+        "InvalidTick",
+        "InvalidPrice",
+        "TooManyTicks",
+        "PositionTooSmall"
       ];
 
       // Verify error count matches expectations
-      assert.equal(expectedErrors.length, 8);
+      assert.equal(expectedErrors.length, 28);
       console.log("✓ Error definitions validated:", expectedErrors.join(", "));
     });
   });
 
+  describe("Event Validation", () => {
+    it("Validates all events are declared in the IDL", async () => {
+      const expectedEvents = [
+        "PoolInitialized",
+        "LiquidityAdded",
+        "LiquidityRemoved",
+        "Swapped",
+        "FlashLoanBorrowed",
+        "FlashLoanRepaid",
+        "PoolLocked",
+        "FeeUpdated",
+        "DynamicFeeUpdated",
+        "AmpRampStarted",
+        "AuthorityTransferred",
+        "ProtocolFeesCollected",
+        "PoolClosed",
+        "OrderPlaced",
+        "OrderCancelled",
+        "OrderFilled",
+        "FarmInitialized",
+        "FarmFunded",
+        "RewardRateUpdated",
+        "LpStaked",
+        "LpUnstaked",
+        "RewardsHarvested",
+        "ConcentratedPoolInitialized",
+        "PositionModified",
+        "PositionFeesCollected",
+        "ConcentratedSwapped",
+      ];
+
+      const declared = (program.idl.events ?? []).map((event) => event.name);
+      expectedEvents.forEach((name) => {
+        const camel = name.charAt(0).toLowerCase() + name.slice(1);
+        assert.isTrue(
+          declared.includes(name) || declared.includes(camel),
+          `${name} event missing`
+        );
+      });
+      console.log("✓ Event definitions validated:", expectedEvents.join(", "));
+    });
+  });
+
   describe("Initialize Instruction Tests", () => {
     it("Should validate seed parameter bounds", async () => {
       const validSeeds = [
@@ -223,7 +354,9 @@ describe("AMM Production Tests", () => {
       // Bootstrap case
       const initialX = 1000;
       const initialY = 2000;
-      const initialLP = Math.sqrt(initialX * initialY); // Geometric mean
+      const minimumLiquidity = 1000; // Locked in the pool on the first deposit
+      const initialLP = Math.sqrt(initialX * initialY) - minimumLiquidity; // Geometric mean
+      assert.isAbove(initialLP, 0);
 
       // Subsequent deposit
       const currentX = 1500;
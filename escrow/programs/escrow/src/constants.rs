@@ -2,3 +2,15 @@ use anchor_lang::prelude::*;
 
 #[constant]
 pub const SEED: &str = "anchor";
+
+// Lamports from the escrow's rent paid to whoever cranks an expired escrow
+#[constant]
+pub const CRANK_TIP: u64 = 100_000;
+
+// Recorded as `Escrow.mint_a` or `mint_b` for a side of the trade paid in native SOL
+#[constant]
+pub const NATIVE_SOL: Pubkey = anchor_lang::system_program::ID;
+
+// Further mints a basket escrow can offer, and further mints it can request, beside A and B
+#[constant]
+pub const MAX_BASKET_LEGS: usize = 4;
@@ -2,6 +2,26 @@ use anchor_lang::prelude::*;
 
 #[error_code]
 pub enum ErrorCode {
-    #[msg("Custom error message")]
-    CustomError,
+    #[msg("Taker is not allowed to take this escrow")]
+    TakerNotAllowed,
+    #[msg("Invalid Amount")]
+    InvalidAmount,
+    #[msg("Expiry is not in the future")]
+    InvalidExpiry,
+    #[msg("Escrow has expired")]
+    EscrowExpired,
+    #[msg("Escrow has not expired")]
+    EscrowNotExpired,
+    #[msg("Mint does not match the escrow")]
+    InvalidMint,
+    #[msg("Escrow cannot trade native SOL for native SOL")]
+    NativeOnBothSides,
+    #[msg("An account this escrow settles through is missing")]
+    MissingAccount,
+    #[msg("Basket has too many legs")]
+    TooManyLegs,
+    #[msg("Remaining accounts do not match the escrow's basket legs")]
+    InvalidLegAccounts,
+    #[msg("Basket escrows cannot be partially filled")]
+    BasketPartialFill,
 }
@@ -0,0 +1,220 @@
+use anchor_lang::prelude::*;
+
+use anchor_spl::{
+    associated_token::{create, get_associated_token_address_with_program_id, Create},
+    token_interface::{
+        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TransferChecked,
+    },
+};
+
+use crate::{constants::MAX_BASKET_LEGS, error::ErrorCode, state::Leg};
+
+// Settlement of the basket legs of an escrow. Each leg is passed as remaining accounts in
+// the order the legs are stored, as a `[mint, from, to]` triple for every transfer
+
+// Splits the triples for `count` legs off the front of `accounts`
+pub fn split_legs<'info>(
+    accounts: &'info [AccountInfo<'info>],
+    count: usize,
+) -> Result<(&'info [AccountInfo<'info>], &'info [AccountInfo<'info>])> {
+    require!(accounts.len() >= count * 3, ErrorCode::InvalidLegAccounts);
+    Ok(accounts.split_at(count * 3))
+}
+
+fn leg_mint<'info>(
+    mint: &'info AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+) -> Result<InterfaceAccount<'info, Mint>> {
+    require_keys_eq!(
+        *mint.owner,
+        token_program.key(),
+        ErrorCode::InvalidLegAccounts
+    );
+    InterfaceAccount::try_from(mint)
+}
+
+fn require_ata(
+    account: &AccountInfo,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Result<()> {
+    require_keys_eq!(
+        account.key(),
+        get_associated_token_address_with_program_id(owner, mint, token_program),
+        ErrorCode::InvalidLegAccounts
+    );
+    Ok(())
+}
+
+// Creates the escrow's vault for every offered leg and fills it from `[mint, maker_ata, vault]`
+pub fn open_vaults<'info>(
+    amounts: &[u64],
+    accounts: &'info [AccountInfo<'info>],
+    maker: &AccountInfo<'info>,
+    escrow: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    associated_token_program: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<Vec<Leg>> {
+    require!(amounts.len() <= MAX_BASKET_LEGS, ErrorCode::TooManyLegs);
+    require!(
+        accounts.len() == amounts.len() * 3,
+        ErrorCode::InvalidLegAccounts
+    );
+
+    let mut legs = Vec::with_capacity(amounts.len());
+    for (&amount, leg) in amounts.iter().zip(accounts.chunks_exact(3)) {
+        let [mint, maker_ata, vault] = leg else {
+            return err!(ErrorCode::InvalidLegAccounts);
+        };
+        require!(amount > 0, ErrorCode::InvalidAmount);
+
+        let mint_account = leg_mint(mint, token_program)?;
+        require_ata(vault, escrow.key, mint.key, token_program.key)?;
+
+        // Fails if the vault exists, so no mint can be offered twice
+        let accounts = Create {
+            payer: maker.clone(),
+            associated_token: vault.clone(),
+            authority: escrow.clone(),
+            mint: mint.clone(),
+            system_program: system_program.clone(),
+            token_program: token_program.clone(),
+        };
+
+        create(CpiContext::new(associated_token_program.clone(), accounts))?;
+
+        let transfer_accounts = TransferChecked {
+            from: maker_ata.clone(),
+            mint: mint.clone(),
+            to: vault.clone(),
+            authority: maker.clone(),
+        };
+
+        let cpi_context = CpiContext::new(token_program.clone(), transfer_accounts);
+
+        transfer_checked(cpi_context, amount, mint_account.decimals)?;
+
+        legs.push(Leg {
+            mint: mint.key(),
+            amount,
+        });
+    }
+
+    Ok(legs)
+}
+
+// Requested legs only need their mint, passed alone rather than as a triple
+pub fn requested_legs<'info>(
+    amounts: &[u64],
+    mints: &'info [AccountInfo<'info>],
+    token_program: &AccountInfo<'info>,
+) -> Result<Vec<Leg>> {
+    require!(amounts.len() <= MAX_BASKET_LEGS, ErrorCode::TooManyLegs);
+    require!(mints.len() == amounts.len(), ErrorCode::InvalidLegAccounts);
+
+    amounts
+        .iter()
+        .zip(mints)
+        .map(|(&amount, mint)| {
+            require!(amount > 0, ErrorCode::InvalidAmount);
+            leg_mint(mint, token_program)?;
+            Ok(Leg {
+                mint: mint.key(),
+                amount,
+            })
+        })
+        .collect()
+}
+
+// Pays every requested leg from `[mint, taker_ata, maker_ata]`. The maker's ATAs must already
+// exist; takers can create them idempotently in the same transaction
+pub fn pay_legs<'info>(
+    legs: &[Leg],
+    accounts: &'info [AccountInfo<'info>],
+    taker: &AccountInfo<'info>,
+    maker: &Pubkey,
+    token_program: &AccountInfo<'info>,
+) -> Result<()> {
+    require!(
+        accounts.len() == legs.len() * 3,
+        ErrorCode::InvalidLegAccounts
+    );
+
+    for (leg, accounts) in legs.iter().zip(accounts.chunks_exact(3)) {
+        let [mint, taker_ata, maker_ata] = accounts else {
+            return err!(ErrorCode::InvalidLegAccounts);
+        };
+        require_keys_eq!(mint.key(), leg.mint, ErrorCode::InvalidLegAccounts);
+
+        let mint_account = leg_mint(mint, token_program)?;
+        require_ata(maker_ata, maker, mint.key, token_program.key)?;
+
+        let transfer_accounts = TransferChecked {
+            from: taker_ata.clone(),
+            mint: mint.clone(),
+            to: maker_ata.clone(),
+            authority: taker.clone(),
+        };
+
+        let cpi_context = CpiContext::new(token_program.clone(), transfer_accounts);
+
+        transfer_checked(cpi_context, leg.amount, mint_account.decimals)?;
+    }
+
+    Ok(())
+}
+
+// Empties every offered leg's vault into `recipient`'s ATA from `[mint, vault, recipient_ata]`
+// and closes the vault to the maker
+pub fn empty_vaults<'info>(
+    legs: &[Leg],
+    accounts: &'info [AccountInfo<'info>],
+    escrow: &AccountInfo<'info>,
+    recipient: &Pubkey,
+    maker: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    signer_seeds: &[&[&[u8]]],
+) -> Result<()> {
+    require!(
+        accounts.len() == legs.len() * 3,
+        ErrorCode::InvalidLegAccounts
+    );
+
+    for (leg, accounts) in legs.iter().zip(accounts.chunks_exact(3)) {
+        let [mint, vault, recipient_ata] = accounts else {
+            return err!(ErrorCode::InvalidLegAccounts);
+        };
+        require_keys_eq!(mint.key(), leg.mint, ErrorCode::InvalidLegAccounts);
+
+        let mint_account = leg_mint(mint, token_program)?;
+        require_ata(vault, escrow.key, mint.key, token_program.key)?;
+        require_ata(recipient_ata, recipient, mint.key, token_program.key)?;
+
+        let vault_account = InterfaceAccount::<TokenAccount>::try_from(vault)?;
+
+        let account = TransferChecked {
+            from: vault.clone(),
+            mint: mint.clone(),
+            to: recipient_ata.clone(),
+            authority: escrow.clone(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(token_program.clone(), account, signer_seeds);
+
+        transfer_checked(cpi_context, vault_account.amount, mint_account.decimals)?;
+
+        let accounts = CloseAccount {
+            account: vault.clone(),
+            destination: maker.clone(),
+            authority: escrow.clone(),
+        };
+
+        let cpi_cxt = CpiContext::new_with_signer(token_program.clone(), accounts, signer_seeds);
+
+        close_account(cpi_cxt)?;
+    }
+
+    Ok(())
+}
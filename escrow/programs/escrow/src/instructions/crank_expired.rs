@@ -0,0 +1,172 @@
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+        TransferChecked,
+    },
+};
+
+use crate::{
+    constants::{CRANK_TIP, NATIVE_SOL},
+    error::ErrorCode,
+    instructions::basket::empty_vaults,
+    state::Escrow,
+};
+
+// Anyone can return an expired escrow to its maker, for a tip out of the escrow's rent
+#[derive(Accounts)]
+pub struct CrankExpired<'info> {
+    #[account(mut)]
+    pub cranker: Signer<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_a: Option<InterfaceAccount<'info, Mint>>,
+
+    #[account(
+        init_if_needed,
+        payer = cranker,
+        associated_token::mint = mint_a,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        has_one = maker,
+        constraint = escrow.mint_a == mint_a.as_ref().map_or(NATIVE_SOL, |mint| mint.key()) @ ErrorCode::InvalidMint,
+        seeds = [b"escrow",escrow.maker.key().as_ref(),escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"sol_vault",escrow.key().as_ref()],
+        bump = escrow.sol_vault_bump,
+    )]
+    pub sol_vault: Option<SystemAccount<'info>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> CrankExpired<'info> {
+    // `remaining` holds a `[mint, vault, maker_ata]` triple per offered leg
+    pub fn crank_expired(&mut self, remaining: &'info [AccountInfo<'info>]) -> Result<()> {
+        require!(
+            self.escrow.is_expired(Clock::get()?.unix_timestamp),
+            ErrorCode::EscrowNotExpired
+        );
+
+        self.return_deposit()?;
+        self.return_basket(remaining)?;
+
+        // The escrow's rent goes back to the maker, less the cranker's tip
+        let escrow = self.escrow.to_account_info();
+        let tip = CRANK_TIP.min(escrow.lamports());
+        escrow.sub_lamports(tip)?;
+        self.cranker.add_lamports(tip)?;
+
+        self.escrow.close(self.maker.to_account_info())
+    }
+
+    fn return_deposit(&mut self) -> Result<()> {
+        let (Some(mint_a), Some(maker_ata_a), Some(vault)) =
+            (&self.mint_a, &self.maker_ata_a, &self.vault)
+        else {
+            return self.return_sol();
+        };
+
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"escrow",
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes()[..],
+            &[self.escrow.bump],
+        ]];
+
+        let account = TransferChecked {
+            from: vault.to_account_info(),
+            mint: mint_a.to_account_info(),
+            to: maker_ata_a.to_account_info(),
+            authority: self.escrow.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            account,
+            &signer_seeds,
+        );
+
+        transfer_checked(cpi_context, vault.amount, mint_a.decimals)?;
+
+        let accounts = CloseAccount {
+            account: vault.to_account_info(),
+            destination: self.maker.to_account_info(),
+            authority: self.escrow.to_account_info(),
+        };
+
+        let cpi_cxt = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            accounts,
+            &signer_seeds,
+        );
+
+        close_account(cpi_cxt)
+    }
+
+    fn return_basket(&mut self, remaining: &'info [AccountInfo<'info>]) -> Result<()> {
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"escrow",
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes()[..],
+            &[self.escrow.bump],
+        ]];
+
+        empty_vaults(
+            &self.escrow.basket_a,
+            remaining,
+            &self.escrow.to_account_info(),
+            self.maker.key,
+            &self.maker,
+            &self.token_program,
+            &signer_seeds,
+        )
+    }
+
+    fn return_sol(&mut self) -> Result<()> {
+        require!(self.escrow.is_native_a(), ErrorCode::MissingAccount);
+        let sol_vault = self.sol_vault.as_ref().ok_or(ErrorCode::MissingAccount)?;
+
+        let escrow = self.escrow.key();
+        let signer_seeds: [&[&[u8]]; 1] =
+            [&[b"sol_vault", escrow.as_ref(), &[self.escrow.sol_vault_bump]]];
+
+        let accounts = Transfer {
+            from: sol_vault.to_account_info(),
+            to: self.maker.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.system_program.to_account_info(),
+            accounts,
+            &signer_seeds,
+        );
+
+        transfer(cpi_context, sol_vault.lamports())
+    }
+}
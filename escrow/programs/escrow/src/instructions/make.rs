@@ -1,36 +1,58 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
 
 use anchor_spl::{
     associated_token::AssociatedToken,
     token_interface::{transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked},
 };
 
-use crate::state::Escrow;
+use crate::{
+    constants::NATIVE_SOL,
+    error::ErrorCode,
+    instructions::basket::{open_vaults, requested_legs, split_legs},
+    state::{AllowedTakers, Escrow},
+};
+
+// Terms of a new offer; `basket_a` and `basket_b` hold the extra legs' amounts
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MakeArgs {
+    pub seed: u64,
+    pub deposit: u64,
+    pub recieve: u64,
+    pub expiry: Option<i64>,
+    pub allowed_takers: AllowedTakers,
+    pub basket_a: Vec<u64>,
+    pub basket_b: Vec<u64>,
+}
 
+// Leave `mint_a` and its token accounts out to offer native SOL through `sol_vault`,
+// or `mint_b` to ask for native SOL
 #[derive(Accounts)]
-#[instruction(seed:u64)]
+#[instruction(args: MakeArgs)]
 pub struct Make<'info> {
     #[account(mut)]
     pub maker: Signer<'info>,
     #[account(
         mint::token_program = token_program
     )]
-    pub mint_a: InterfaceAccount<'info, Mint>,
+    pub mint_a: Option<InterfaceAccount<'info, Mint>>,
     #[account(
         mint::token_program = token_program
     )]
-    pub mint_b: InterfaceAccount<'info, Mint>,
+    pub mint_b: Option<InterfaceAccount<'info, Mint>>,
     #[account(
         mut,
         associated_token::mint = mint_a,
         associated_token::authority = maker,
         associated_token::token_program = token_program,
     )]
-    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    pub maker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init,
         payer = maker,
-        seeds = [b"escrow",maker.key().as_ref(),seed.to_le_bytes().as_ref()],
+        seeds = [b"escrow",maker.key().as_ref(),args.seed.to_le_bytes().as_ref()],
         space = 8 + Escrow::INIT_SPACE,
         bump,
     )]
@@ -42,7 +64,13 @@ pub struct Make<'info> {
         associated_token::authority = escrow,
         associated_token::token_program = token_program
     )]
-    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"sol_vault",escrow.key().as_ref()],
+        bump,
+    )]
+    pub sol_vault: Option<SystemAccount<'info>>,
 
     pub associated_token: Program<'info, AssociatedToken>,
 
@@ -52,29 +80,120 @@ pub struct Make<'info> {
 }
 
 impl<'info> Make<'info> {
-    pub fn init_escrow(&mut self, seed: u64, recieve: u64, bump: &MakeBumps) -> Result<()> {
+    pub fn init_escrow(
+        &mut self,
+        seed: u64,
+        recieve: u64,
+        expiry: Option<i64>,
+        allowed_takers: AllowedTakers,
+        bump: &MakeBumps,
+    ) -> Result<()> {
+        require!(
+            self.mint_a.is_some() || self.mint_b.is_some(),
+            ErrorCode::NativeOnBothSides
+        );
+        require!(recieve > 0, ErrorCode::InvalidAmount);
+
+        if let Some(expiry) = expiry {
+            require!(
+                expiry > Clock::get()?.unix_timestamp,
+                ErrorCode::InvalidExpiry
+            );
+        }
+
+        // Kept for token escrows too, so clients can always pass the canonical `sol_vault`
+        let (_, sol_vault_bump) =
+            Pubkey::find_program_address(&[b"sol_vault", self.escrow.key().as_ref()], &crate::ID);
+
         self.escrow.set_inner(Escrow {
             seed,
             maker: self.maker.key(),
-            mint_a: self.mint_a.key(),
-            mint_b: self.mint_b.key(),
+            mint_a: self.mint_a.as_ref().map_or(NATIVE_SOL, |mint| mint.key()),
+            mint_b: self.mint_b.as_ref().map_or(NATIVE_SOL, |mint| mint.key()),
+            deposit: 0,
             recieve,
+            expiry,
+            allowed_takers,
+            basket_a: Vec::new(),
+            basket_b: Vec::new(),
             bump: bump.escrow,
+            sol_vault_bump,
         });
 
         Ok(())
     }
 
     pub fn deposit(&mut self, deposit: u64) -> Result<()> {
+        require!(deposit > 0, ErrorCode::InvalidAmount);
+
+        let (Some(mint_a), Some(maker_ata_a), Some(vault)) =
+            (&self.mint_a, &self.maker_ata_a, &mut self.vault)
+        else {
+            return self.deposit_sol(deposit);
+        };
+
         let transfer_account = TransferChecked {
-            from: self.maker_ata_a.to_account_info(),
-            mint: self.mint_a.to_account_info(),
-            to: self.vault.to_account_info(),
+            from: maker_ata_a.to_account_info(),
+            mint: mint_a.to_account_info(),
+            to: vault.to_account_info(),
             authority: self.maker.to_account_info(),
         };
 
         let cpi_context = CpiContext::new(self.token_program.to_account_info(), transfer_account);
 
-        transfer_checked(cpi_context, deposit, self.mint_a.decimals)
+        transfer_checked(cpi_context, deposit, mint_a.decimals)?;
+
+        // Partial fills are priced on what the vault actually received
+        vault.reload()?;
+        self.escrow.deposit = vault.amount;
+
+        Ok(())
+    }
+
+    // `remaining` holds a `[mint, maker_ata, vault]` triple per offered leg, then the mint of
+    // each requested leg
+    pub fn open_basket(
+        &mut self,
+        basket_a: &[u64],
+        basket_b: &[u64],
+        remaining: &'info [AccountInfo<'info>],
+    ) -> Result<()> {
+        let (accounts_a, mints_b) = split_legs(remaining, basket_a.len())?;
+
+        self.escrow.basket_b = requested_legs(basket_b, mints_b, &self.token_program)?;
+        self.escrow.basket_a = open_vaults(
+            basket_a,
+            accounts_a,
+            &self.maker,
+            &self.escrow.to_account_info(),
+            &self.token_program,
+            &self.associated_token_program,
+            &self.system_program,
+        )?;
+
+        Ok(())
+    }
+
+    fn deposit_sol(&mut self, deposit: u64) -> Result<()> {
+        require!(self.mint_a.is_none(), ErrorCode::MissingAccount);
+        let sol_vault = self.sol_vault.as_ref().ok_or(ErrorCode::MissingAccount)?;
+
+        // The vault also holds its own rent so partial fills never leave it below the minimum
+        let reserve = Rent::get()?
+            .minimum_balance(0)
+            .saturating_sub(sol_vault.lamports());
+
+        let accounts = Transfer {
+            from: self.maker.to_account_info(),
+            to: sol_vault.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new(self.system_program.to_account_info(), accounts);
+
+        transfer(cpi_context, deposit + reserve)?;
+
+        self.escrow.deposit = deposit;
+
+        Ok(())
     }
 }
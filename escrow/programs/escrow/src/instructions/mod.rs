@@ -1,9 +1,14 @@
+pub mod basket;
+pub mod crank_expired;
 pub mod initialize;
 pub mod make;
 pub mod refund;
 pub mod take;
+pub mod take_partial;
 
+pub use crank_expired::*;
 pub use initialize::*;
 pub use make::*;
 pub use refund::*;
 pub use take::*;
+pub use take_partial::*;
@@ -1,4 +1,7 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
 
 use anchor_spl::{
     associated_token::AssociatedToken,
@@ -8,7 +11,9 @@ use anchor_spl::{
     },
 };
 
-use crate::state::Escrow;
+use crate::{
+    constants::NATIVE_SOL, error::ErrorCode, instructions::basket::empty_vaults, state::Escrow,
+};
 
 #[derive(Accounts)]
 pub struct Refund<'info> {
@@ -17,7 +22,7 @@ pub struct Refund<'info> {
     #[account(
         mint::token_program = token_program
     )]
-    pub mint_a: InterfaceAccount<'info, Mint>,
+    pub mint_a: Option<InterfaceAccount<'info, Mint>>,
 
     #[account(
         init_if_needed,
@@ -26,9 +31,11 @@ pub struct Refund<'info> {
         associated_token::authority = maker,
         associated_token::token_program = token_program,
     )]
-    pub maker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    pub maker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
+        has_one = maker,
+        constraint = escrow.mint_a == mint_a.as_ref().map_or(NATIVE_SOL, |mint| mint.key()) @ ErrorCode::InvalidMint,
         seeds = [b"escrow",escrow.maker.key().as_ref(),escrow.seed.to_le_bytes().as_ref()],
         bump = escrow.bump,
         close = maker
@@ -40,7 +47,13 @@ pub struct Refund<'info> {
         associated_token::authority = escrow,
         associated_token::token_program = token_program
     )]
-    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"sol_vault",escrow.key().as_ref()],
+        bump = escrow.sol_vault_bump,
+    )]
+    pub sol_vault: Option<SystemAccount<'info>>,
 
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub token_program: Interface<'info, TokenInterface>,
@@ -49,6 +62,12 @@ pub struct Refund<'info> {
 
 impl<'info> Refund<'info> {
     pub fn refund(&mut self) -> Result<()> {
+        let (Some(mint_a), Some(maker_ata_a), Some(vault)) =
+            (&self.mint_a, &self.maker_ata_a, &self.vault)
+        else {
+            return self.refund_sol();
+        };
+
         let signer_seeds: [&[&[u8]]; 1] = [&[
             b"escrow",
             self.maker.to_account_info().key.as_ref(),
@@ -57,9 +76,9 @@ impl<'info> Refund<'info> {
         ]];
 
         let account = TransferChecked {
-            from: self.vault.to_account_info(),
-            mint: self.mint_a.to_account_info(),
-            to: self.maker_ata_a.to_account_info(),
+            from: vault.to_account_info(),
+            mint: mint_a.to_account_info(),
+            to: maker_ata_a.to_account_info(),
             authority: self.escrow.to_account_info(),
         };
 
@@ -69,10 +88,10 @@ impl<'info> Refund<'info> {
             &signer_seeds,
         );
 
-        transfer_checked(cpi_context, self.vault.amount, self.mint_a.decimals)?;
+        transfer_checked(cpi_context, vault.amount, mint_a.decimals)?;
 
         let accounts = CloseAccount {
-            account: self.vault.to_account_info(),
+            account: vault.to_account_info(),
             destination: self.maker.to_account_info(),
             authority: self.escrow.to_account_info(),
         };
@@ -87,4 +106,48 @@ impl<'info> Refund<'info> {
 
         Ok(())
     }
+
+    // `remaining` holds a `[mint, vault, maker_ata]` triple per offered leg
+    pub fn refund_basket(&mut self, remaining: &'info [AccountInfo<'info>]) -> Result<()> {
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"escrow",
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes()[..],
+            &[self.escrow.bump],
+        ]];
+
+        empty_vaults(
+            &self.escrow.basket_a,
+            remaining,
+            &self.escrow.to_account_info(),
+            self.maker.key,
+            &self.maker,
+            &self.token_program,
+            &signer_seeds,
+        )
+    }
+
+    fn refund_sol(&mut self) -> Result<()> {
+        require!(self.escrow.is_native_a(), ErrorCode::MissingAccount);
+        let sol_vault = self.sol_vault.as_ref().ok_or(ErrorCode::MissingAccount)?;
+
+        let escrow = self.escrow.key();
+        let signer_seeds: [&[&[u8]]; 1] =
+            [&[b"sol_vault", escrow.as_ref(), &[self.escrow.sol_vault_bump]]];
+
+        let accounts = Transfer {
+            from: sol_vault.to_account_info(),
+            to: self.maker.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.system_program.to_account_info(),
+            accounts,
+            &signer_seeds,
+        );
+
+        transfer(cpi_context, sol_vault.lamports())?;
+
+        Ok(())
+    }
 }
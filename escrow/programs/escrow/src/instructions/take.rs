@@ -1,4 +1,7 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
 
 use anchor_spl::{
     associated_token::AssociatedToken,
@@ -8,8 +11,14 @@ use anchor_spl::{
     },
 };
 
-use crate::state::Escrow;
+use crate::{
+    constants::NATIVE_SOL,
+    error::ErrorCode,
+    instructions::basket::{empty_vaults, pay_legs, split_legs},
+    state::Escrow,
+};
 
+// Mint and token accounts for a native SOL side of the escrow are left out
 #[derive(Accounts)]
 pub struct Take<'info> {
     #[account(mut)]
@@ -19,11 +28,11 @@ pub struct Take<'info> {
     #[account(
         mint::token_program = token_program
     )]
-    pub mint_a: InterfaceAccount<'info, Mint>,
+    pub mint_a: Option<InterfaceAccount<'info, Mint>>,
     #[account(
         mint::token_program = token_program
     )]
-    pub mint_b: InterfaceAccount<'info, Mint>,
+    pub mint_b: Option<InterfaceAccount<'info, Mint>>,
     #[account(
         init_if_needed,
         payer = taker,
@@ -31,23 +40,26 @@ pub struct Take<'info> {
         associated_token::authority = maker,
         associated_token::token_program = token_program,
     )]
-    pub maker_ata_b: InterfaceAccount<'info, TokenAccount>,
+    pub maker_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_a,
         associated_token::authority = taker,
         associated_token::token_program = token_program,
     )]
-    pub taker_ata_a: InterfaceAccount<'info, TokenAccount>,
+    pub taker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         associated_token::mint = mint_b,
         associated_token::authority = taker,
         associated_token::token_program = token_program,
     )]
-    pub taker_ata_b: InterfaceAccount<'info, TokenAccount>,
+    pub taker_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
+        has_one = maker,
+        constraint = escrow.mint_a == mint_a.as_ref().map_or(NATIVE_SOL, |mint| mint.key()) @ ErrorCode::InvalidMint,
+        constraint = escrow.mint_b == mint_b.as_ref().map_or(NATIVE_SOL, |mint| mint.key()) @ ErrorCode::InvalidMint,
         seeds = [b"escrow",escrow.maker.key().as_ref(),escrow.seed.to_le_bytes().as_ref()],
         bump = escrow.bump,
         close = maker
@@ -59,7 +71,13 @@ pub struct Take<'info> {
         associated_token::authority = escrow,
         associated_token::token_program = token_program
     )]
-    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"sol_vault",escrow.key().as_ref()],
+        bump = escrow.sol_vault_bump,
+    )]
+    pub sol_vault: Option<SystemAccount<'info>>,
 
     pub associated_token: Program<'info, AssociatedToken>,
 
@@ -69,20 +87,88 @@ pub struct Take<'info> {
 }
 
 impl<'info> Take<'info> {
+    pub fn check_taker(&self, proof: &[[u8; 32]]) -> Result<()> {
+        require!(
+            !self.escrow.is_expired(Clock::get()?.unix_timestamp),
+            ErrorCode::EscrowExpired
+        );
+        require!(
+            self.escrow.is_allowed(self.taker.key, proof),
+            ErrorCode::TakerNotAllowed
+        );
+        Ok(())
+    }
+
     pub fn deposit(&mut self) -> Result<()> {
+        let (Some(mint_b), Some(taker_ata_b), Some(maker_ata_b)) =
+            (&self.mint_b, &self.taker_ata_b, &self.maker_ata_b)
+        else {
+            return self.deposit_sol();
+        };
+
         let transfer_accounts = TransferChecked {
-            from: self.taker_ata_b.to_account_info(),
-            mint: self.mint_b.to_account_info(),
-            to: self.maker_ata_b.to_account_info(),
+            from: taker_ata_b.to_account_info(),
+            mint: mint_b.to_account_info(),
+            to: maker_ata_b.to_account_info(),
             authority: self.taker.to_account_info(),
         };
 
         let cpi_context = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
 
-        transfer_checked(cpi_context, self.escrow.recieve, self.mint_b.decimals)
+        transfer_checked(cpi_context, self.escrow.recieve, mint_b.decimals)
+    }
+
+    // `remaining` holds a `[mint, vault, taker_ata]` triple per offered leg, then a
+    // `[mint, taker_ata, maker_ata]` triple per requested leg
+    pub fn settle_basket(&mut self, remaining: &'info [AccountInfo<'info>]) -> Result<()> {
+        let (accounts_a, accounts_b) = split_legs(remaining, self.escrow.basket_a.len())?;
+
+        pay_legs(
+            &self.escrow.basket_b,
+            accounts_b,
+            &self.taker,
+            self.maker.key,
+            &self.token_program,
+        )?;
+
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"escrow",
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes()[..],
+            &[self.escrow.bump],
+        ]];
+
+        empty_vaults(
+            &self.escrow.basket_a,
+            accounts_a,
+            &self.escrow.to_account_info(),
+            self.taker.key,
+            &self.maker,
+            &self.token_program,
+            &signer_seeds,
+        )
+    }
+
+    fn deposit_sol(&mut self) -> Result<()> {
+        require!(self.escrow.is_native_b(), ErrorCode::MissingAccount);
+
+        let accounts = Transfer {
+            from: self.taker.to_account_info(),
+            to: self.maker.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new(self.system_program.to_account_info(), accounts);
+
+        transfer(cpi_context, self.escrow.recieve)
     }
 
     pub fn withdraw_and_close_escrow(&mut self) -> Result<()> {
+        let (Some(mint_a), Some(taker_ata_a), Some(vault)) =
+            (&self.mint_a, &self.taker_ata_a, &self.vault)
+        else {
+            return self.withdraw_sol();
+        };
+
         let signer_seeds: [&[&[u8]]; 1] = [&[
             b"escrow",
             self.maker.to_account_info().key.as_ref(),
@@ -91,9 +177,9 @@ impl<'info> Take<'info> {
         ]];
 
         let account = TransferChecked {
-            from: self.vault.to_account_info(),
-            mint: self.mint_a.to_account_info(),
-            to: self.taker_ata_a.to_account_info(),
+            from: vault.to_account_info(),
+            mint: mint_a.to_account_info(),
+            to: taker_ata_a.to_account_info(),
             authority: self.escrow.to_account_info(),
         };
 
@@ -103,10 +189,10 @@ impl<'info> Take<'info> {
             &signer_seeds,
         );
 
-        transfer_checked(cpi_context, self.vault.amount, self.mint_a.decimals)?;
+        transfer_checked(cpi_context, vault.amount, mint_a.decimals)?;
 
         let accounts = CloseAccount {
-            account: self.vault.to_account_info(),
+            account: vault.to_account_info(),
             destination: self.maker.to_account_info(),
             authority: self.escrow.to_account_info(),
         };
@@ -119,4 +205,40 @@ impl<'info> Take<'info> {
 
         close_account(cpi_cxt)
     }
+
+    fn withdraw_sol(&mut self) -> Result<()> {
+        require!(self.escrow.is_native_a(), ErrorCode::MissingAccount);
+        let sol_vault = self.sol_vault.as_ref().ok_or(ErrorCode::MissingAccount)?;
+
+        let escrow = self.escrow.key();
+        let signer_seeds: [&[&[u8]]; 1] =
+            [&[b"sol_vault", escrow.as_ref(), &[self.escrow.sol_vault_bump]]];
+
+        let account = Transfer {
+            from: sol_vault.to_account_info(),
+            to: self.taker.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.system_program.to_account_info(),
+            account,
+            &signer_seeds,
+        );
+
+        transfer(cpi_context, self.escrow.deposit)?;
+
+        // Emptying the vault closes it, handing its rent reserve back to the maker
+        let accounts = Transfer {
+            from: sol_vault.to_account_info(),
+            to: self.maker.to_account_info(),
+        };
+
+        let cpi_cxt = CpiContext::new_with_signer(
+            self.system_program.to_account_info(),
+            accounts,
+            &signer_seeds,
+        );
+
+        transfer(cpi_cxt, sol_vault.lamports())
+    }
 }
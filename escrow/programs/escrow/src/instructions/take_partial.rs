@@ -0,0 +1,251 @@
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+
+use anchor_spl::{
+    associated_token::AssociatedToken,
+    token_interface::{
+        close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+        TransferChecked,
+    },
+};
+
+use crate::{constants::NATIVE_SOL, error::ErrorCode, state::Escrow};
+
+#[derive(Accounts)]
+pub struct TakePartial<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+    #[account(mut)]
+    pub maker: SystemAccount<'info>,
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_a: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mint::token_program = token_program
+    )]
+    pub mint_b: Option<InterfaceAccount<'info, Mint>>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_b,
+        associated_token::authority = maker,
+        associated_token::token_program = token_program,
+    )]
+    pub maker_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = taker,
+        associated_token::mint = mint_a,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_a: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        associated_token::mint = mint_b,
+        associated_token::authority = taker,
+        associated_token::token_program = token_program,
+    )]
+    pub taker_ata_b: Option<InterfaceAccount<'info, TokenAccount>>,
+    // Closed by `withdraw` once the last of `recieve` is paid
+    #[account(
+        mut,
+        has_one = maker,
+        constraint = escrow.mint_a == mint_a.as_ref().map_or(NATIVE_SOL, |mint| mint.key()) @ ErrorCode::InvalidMint,
+        constraint = escrow.mint_b == mint_b.as_ref().map_or(NATIVE_SOL, |mint| mint.key()) @ ErrorCode::InvalidMint,
+        seeds = [b"escrow",escrow.maker.key().as_ref(),escrow.seed.to_le_bytes().as_ref()],
+        bump = escrow.bump,
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        mut,
+        associated_token::mint = mint_a,
+        associated_token::authority = escrow,
+        associated_token::token_program = token_program
+    )]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [b"sol_vault",escrow.key().as_ref()],
+        bump = escrow.sol_vault_bump,
+    )]
+    pub sol_vault: Option<SystemAccount<'info>>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> TakePartial<'info> {
+    pub fn check_taker(&self, proof: &[[u8; 32]]) -> Result<()> {
+        require!(
+            !self.escrow.is_expired(Clock::get()?.unix_timestamp),
+            ErrorCode::EscrowExpired
+        );
+        require!(
+            self.escrow.is_allowed(self.taker.key, proof),
+            ErrorCode::TakerNotAllowed
+        );
+        Ok(())
+    }
+
+    // Records the fill of `amount_b` and returns the mint A it buys, at the offer's price
+    // rounded down in the maker's favour
+    pub fn fill(&mut self, amount_b: u64) -> Result<u64> {
+        require!(!self.escrow.is_basket(), ErrorCode::BasketPartialFill);
+        require!(
+            amount_b > 0 && amount_b <= self.escrow.recieve,
+            ErrorCode::InvalidAmount
+        );
+
+        let amount_a =
+            (self.escrow.deposit as u128 * amount_b as u128 / self.escrow.recieve as u128) as u64;
+        require!(amount_a > 0, ErrorCode::InvalidAmount);
+
+        self.escrow.deposit -= amount_a;
+        self.escrow.recieve -= amount_b;
+
+        Ok(amount_a)
+    }
+
+    pub fn deposit(&mut self, amount_b: u64) -> Result<()> {
+        let (Some(mint_b), Some(taker_ata_b), Some(maker_ata_b)) =
+            (&self.mint_b, &self.taker_ata_b, &self.maker_ata_b)
+        else {
+            return self.deposit_sol(amount_b);
+        };
+
+        let transfer_accounts = TransferChecked {
+            from: taker_ata_b.to_account_info(),
+            mint: mint_b.to_account_info(),
+            to: maker_ata_b.to_account_info(),
+            authority: self.taker.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new(self.token_program.to_account_info(), transfer_accounts);
+
+        transfer_checked(cpi_context, amount_b, mint_b.decimals)
+    }
+
+    fn deposit_sol(&mut self, amount_b: u64) -> Result<()> {
+        require!(self.escrow.is_native_b(), ErrorCode::MissingAccount);
+
+        let accounts = Transfer {
+            from: self.taker.to_account_info(),
+            to: self.maker.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new(self.system_program.to_account_info(), accounts);
+
+        transfer(cpi_context, amount_b)
+    }
+
+    pub fn withdraw(&mut self, amount_a: u64) -> Result<()> {
+        let (Some(mint_a), Some(taker_ata_a), Some(vault)) =
+            (&self.mint_a, &self.taker_ata_a, &self.vault)
+        else {
+            return self.withdraw_sol(amount_a);
+        };
+
+        let signer_seeds: [&[&[u8]]; 1] = [&[
+            b"escrow",
+            self.maker.to_account_info().key.as_ref(),
+            &self.escrow.seed.to_le_bytes()[..],
+            &[self.escrow.bump],
+        ]];
+
+        // The last fill also sweeps whatever rounding left in the vault
+        let filled = self.escrow.recieve == 0;
+        let amount_a = match filled {
+            true => vault.amount,
+            false => amount_a,
+        };
+
+        let account = TransferChecked {
+            from: vault.to_account_info(),
+            mint: mint_a.to_account_info(),
+            to: taker_ata_a.to_account_info(),
+            authority: self.escrow.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            account,
+            &signer_seeds,
+        );
+
+        transfer_checked(cpi_context, amount_a, mint_a.decimals)?;
+
+        if !filled {
+            return Ok(());
+        }
+
+        let accounts = CloseAccount {
+            account: vault.to_account_info(),
+            destination: self.maker.to_account_info(),
+            authority: self.escrow.to_account_info(),
+        };
+
+        let cpi_cxt = CpiContext::new_with_signer(
+            self.token_program.to_account_info(),
+            accounts,
+            &signer_seeds,
+        );
+
+        close_account(cpi_cxt)?;
+
+        self.escrow.close(self.maker.to_account_info())
+    }
+
+    fn withdraw_sol(&mut self, amount_a: u64) -> Result<()> {
+        require!(self.escrow.is_native_a(), ErrorCode::MissingAccount);
+        let sol_vault = self.sol_vault.as_ref().ok_or(ErrorCode::MissingAccount)?;
+
+        let escrow = self.escrow.key();
+        let signer_seeds: [&[&[u8]]; 1] =
+            [&[b"sol_vault", escrow.as_ref(), &[self.escrow.sol_vault_bump]]];
+
+        // The last fill also takes the lamports rounding left unsold
+        let filled = self.escrow.recieve == 0;
+        let amount_a = match filled {
+            true => amount_a + self.escrow.deposit,
+            false => amount_a,
+        };
+
+        let account = Transfer {
+            from: sol_vault.to_account_info(),
+            to: self.taker.to_account_info(),
+        };
+
+        let cpi_context = CpiContext::new_with_signer(
+            self.system_program.to_account_info(),
+            account,
+            &signer_seeds,
+        );
+
+        transfer(cpi_context, amount_a)?;
+
+        if !filled {
+            return Ok(());
+        }
+
+        // Emptying the vault closes it, handing its rent reserve back to the maker
+        let accounts = Transfer {
+            from: sol_vault.to_account_info(),
+            to: self.maker.to_account_info(),
+        };
+
+        let cpi_cxt = CpiContext::new_with_signer(
+            self.system_program.to_account_info(),
+            accounts,
+            &signer_seeds,
+        );
+
+        transfer(cpi_cxt, sol_vault.lamports())?;
+
+        self.escrow.close(self.maker.to_account_info())
+    }
+}
@@ -20,20 +20,56 @@ pub mod escrow {
         initialize::handler(ctx)
     }
 
-    pub fn make(ctx: Context<Make>, seed: u64, deposit: u64, recieve: u64) -> Result<()> {
-        ctx.accounts.init_escrow(seed, recieve, &ctx.bumps)?;
-        ctx.accounts.deposit(deposit)?;
+    pub fn make<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Make<'info>>,
+        args: MakeArgs,
+    ) -> Result<()> {
+        ctx.accounts.init_escrow(
+            args.seed,
+            args.recieve,
+            args.expiry,
+            args.allowed_takers,
+            &ctx.bumps,
+        )?;
+        ctx.accounts.deposit(args.deposit)?;
+        ctx.accounts
+            .open_basket(&args.basket_a, &args.basket_b, ctx.remaining_accounts)?;
         Ok(())
     }
 
-    pub fn take(ctx: Context<Take>) -> Result<()> {
+    pub fn take<'info>(
+        ctx: Context<'_, '_, 'info, 'info, Take<'info>>,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        ctx.accounts.check_taker(&proof)?;
         ctx.accounts.deposit()?;
         ctx.accounts.withdraw_and_close_escrow()?;
+        ctx.accounts.settle_basket(ctx.remaining_accounts)?;
         Ok(())
     }
 
-    pub fn refund(ctx: Context<Refund>) -> Result<()> {
+    pub fn take_partial(
+        ctx: Context<TakePartial>,
+        amount_b: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        ctx.accounts.check_taker(&proof)?;
+        let amount_a = ctx.accounts.fill(amount_b)?;
+        ctx.accounts.deposit(amount_b)?;
+        ctx.accounts.withdraw(amount_a)?;
+        Ok(())
+    }
+
+    pub fn refund<'info>(ctx: Context<'_, '_, 'info, 'info, Refund<'info>>) -> Result<()> {
         ctx.accounts.refund()?;
+        ctx.accounts.refund_basket(ctx.remaining_accounts)?;
+        Ok(())
+    }
+
+    pub fn crank_expired<'info>(
+        ctx: Context<'_, '_, 'info, 'info, CrankExpired<'info>>,
+    ) -> Result<()> {
+        ctx.accounts.crank_expired(ctx.remaining_accounts)?;
         Ok(())
     }
 }
@@ -1,12 +1,76 @@
-use anchor_lang::prelude::*;
+use anchor_lang::{prelude::*, solana_program::hash::hashv};
+
+use crate::constants::{MAX_BASKET_LEGS, NATIVE_SOL};
+
+// Who may take an offer
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub enum AllowedTakers {
+    Anyone,
+    Only { taker: Pubkey },
+    // Merkle root over sha256(0x00 || taker) leaves, with each node sha256(0x01 || a || b)
+    // over its children sorted so proofs need no left/right flags
+    Allowlist { root: [u8; 32] },
+}
+
+// A further mint in a basket escrow, beside mint A or mint B
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, InitSpace)]
+pub struct Leg {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
 
 #[account]
 #[derive(InitSpace)]
 pub struct Escrow {
     pub seed: u64,
     pub maker: Pubkey,
-    pub mint_a: Pubkey,
-    pub mint_b: Pubkey,
-    pub recieve: u64,
+    pub mint_a: Pubkey,      // `NATIVE_SOL` when lamports are offered
+    pub mint_b: Pubkey,      // `NATIVE_SOL` when lamports are requested
+    pub deposit: u64,        // mint A still in the vault for takers
+    pub recieve: u64,        // mint B still owed for `deposit`
+    pub expiry: Option<i64>, // unix timestamp from which the offer can no longer be taken
+    pub allowed_takers: AllowedTakers,
+    #[max_len(MAX_BASKET_LEGS)]
+    pub basket_a: Vec<Leg>, // offered with mint A, each in the escrow's ATA for its mint
+    #[max_len(MAX_BASKET_LEGS)]
+    pub basket_b: Vec<Leg>, // requested with mint B
     pub bump: u8,
+    pub sol_vault_bump: u8,
+}
+
+impl Escrow {
+    pub fn is_native_a(&self) -> bool {
+        self.mint_a == NATIVE_SOL
+    }
+
+    pub fn is_native_b(&self) -> bool {
+        self.mint_b == NATIVE_SOL
+    }
+
+    pub fn is_basket(&self) -> bool {
+        !self.basket_a.is_empty() || !self.basket_b.is_empty()
+    }
+
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expiry.is_some_and(|expiry| now >= expiry)
+    }
+
+    // `proof` holds the sibling hashes from the taker's leaf up to the root of an allowlist
+    pub fn is_allowed(&self, taker: &Pubkey, proof: &[[u8; 32]]) -> bool {
+        match self.allowed_takers {
+            AllowedTakers::Anyone => true,
+            AllowedTakers::Only { taker: only } => *taker == only,
+            AllowedTakers::Allowlist { root } => {
+                let mut hash = hashv(&[&[0], taker.as_ref()]).to_bytes();
+                for sibling in proof {
+                    hash = match hash <= *sibling {
+                        true => hashv(&[&[1], &hash, sibling]),
+                        false => hashv(&[&[1], sibling, &hash]),
+                    }
+                    .to_bytes();
+                }
+                hash == root
+            }
+        }
+    }
 }
@@ -2,9 +2,9 @@ import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
 import { Escrow } from "../target/types/escrow";
 import { BN } from "bn.js";
-import { ASSOCIATED_TOKEN_PROGRAM_ID, Account, TOKEN_PROGRAM_ID, createMint, getAssociatedTokenAddress, getOrCreateAssociatedTokenAccount, mintTo } from "@solana/spl-token";
+import { ASSOCIATED_TOKEN_PROGRAM_ID, Account, TOKEN_PROGRAM_ID, createMint, getAccount, getAssociatedTokenAddress, getOrCreateAssociatedTokenAccount, mintTo } from "@solana/spl-token";
 import { PublicKey, SystemProgram, LAMPORTS_PER_SOL } from '@solana/web3.js';
-import { randomBytes } from "crypto";
+import { createHash, randomBytes } from "crypto";
 import { confirmTransaction } from "@solana-developers/helpers";
 import { expect } from "chai";
 
@@ -103,7 +103,7 @@ describe("Make Instruction:", () => {
   )
 
   it("Make an Escrow!", async () => {
-    await program.methods.make(seed, new BN(deposit_amount), new BN(receive_amount)).accountsPartial({
+    await program.methods.make({ seed, deposit: new BN(deposit_amount), recieve: new BN(receive_amount), expiry: null, allowedTakers: { anyone: {} }, basketA: [], basketB: [] }).accountsPartial({
       maker: maker.publicKey,
       mintA,
       mintB,
@@ -188,7 +188,7 @@ describe("Make Instruction:", () => {
 
       // Create Escrow
       await program.methods
-        .make(seed2, new BN(deposit_amount2), new BN(receive_amount2))
+        .make({ seed: seed2, deposit: new BN(deposit_amount2), recieve: new BN(receive_amount2), expiry: null, allowedTakers: { anyone: {} }, basketA: [], basketB: [] })
         .accountsPartial({
           maker: maker2.publicKey,
           mintA: mintA2,
@@ -210,7 +210,7 @@ describe("Make Instruction:", () => {
 
     it("Take the Escrow!", async () => {
       await program.methods
-        .take()
+        .take([])
         .accountsPartial({
           taker: taker2.publicKey,
           maker: maker2.publicKey,
@@ -232,4 +232,760 @@ describe("Make Instruction:", () => {
       expect(escrowAccount).to.be.null;
     });
   });
+
+  describe("Take Partial Instruction:", () => {
+    const maker3 = anchor.web3.Keypair.generate();
+    const taker3 = anchor.web3.Keypair.generate();
+
+    const seed3 = new BN(randomBytes(8));
+    const deposit_amount3 = 1000;
+    const receive_amount3 = 500;
+
+    let escrow3: PublicKey;
+    let vault3: PublicKey;
+    let mintA3: PublicKey;
+    let mintB3: PublicKey;
+    let takerAtaA3: PublicKey;
+    let takerAtaB3: any;
+    let makerAtaB3: PublicKey;
+
+    before("Create accounts for partial take flow", async () => {
+      const drop1Sig = await provider.connection.requestAirdrop(maker3.publicKey, 2 * LAMPORTS_PER_SOL);
+      await confirmTransaction(connection, drop1Sig, "confirmed");
+
+      const drop2Sig = await provider.connection.requestAirdrop(taker3.publicKey, 2 * LAMPORTS_PER_SOL);
+      await confirmTransaction(connection, drop2Sig, "confirmed");
+
+      mintA3 = await createMint(connection, maker3, maker3.publicKey, null, 6);
+      mintB3 = await createMint(connection, taker3, taker3.publicKey, null, 6);
+
+      const makerAtaA3 = await getOrCreateAssociatedTokenAccount(connection, maker3, mintA3, maker3.publicKey);
+      takerAtaB3 = await getOrCreateAssociatedTokenAccount(connection, taker3, mintB3, taker3.publicKey);
+
+      await mintTo(connection, maker3, mintA3, makerAtaA3.address, maker3, deposit_amount3);
+      await mintTo(connection, taker3, mintB3, takerAtaB3.address, taker3, receive_amount3);
+
+      [escrow3] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("escrow"),
+          maker3.publicKey.toBuffer(),
+          seed3.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+
+      vault3 = await getAssociatedTokenAddress(mintA3, escrow3, true, TOKEN_PROGRAM_ID);
+      // Created by the first fill
+      takerAtaA3 = await getAssociatedTokenAddress(mintA3, taker3.publicKey);
+      makerAtaB3 = await getAssociatedTokenAddress(mintB3, maker3.publicKey);
+
+      await program.methods
+        .make({ seed: seed3, deposit: new BN(deposit_amount3), recieve: new BN(receive_amount3), expiry: null, allowedTakers: { anyone: {} }, basketA: [], basketB: [] })
+        .accountsPartial({
+          maker: maker3.publicKey,
+          mintA: mintA3,
+          mintB: mintB3,
+          makerAtaA: makerAtaA3.address,
+          vault: vault3,
+          escrow: escrow3,
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([maker3])
+        .rpc();
+    });
+
+    const takePartial = (amountB: number) =>
+      program.methods
+        .takePartial(new BN(amountB), [])
+        .accountsPartial({
+          taker: taker3.publicKey,
+          maker: maker3.publicKey,
+          mintA: mintA3,
+          mintB: mintB3,
+          takerAtaA: takerAtaA3,
+          takerAtaB: takerAtaB3.address,
+          makerAtaB: makerAtaB3,
+          escrow: escrow3,
+          vault: vault3,
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([taker3])
+        .rpc();
+
+    it("Fill part of the Escrow!", async () => {
+      await takePartial(200);
+
+      const escrowAccount = await program.account.escrow.fetch(escrow3);
+      expect(escrowAccount.deposit.toNumber()).to.equal(600);
+      expect(escrowAccount.recieve.toNumber()).to.equal(300);
+
+      const takerA = await connection.getTokenAccountBalance(takerAtaA3);
+      expect(takerA.value.amount).to.equal("400");
+      const makerB = await connection.getTokenAccountBalance(makerAtaB3);
+      expect(makerB.value.amount).to.equal("200");
+    });
+
+    it("Reject a fill above the remaining amount!", async () => {
+      try {
+        await takePartial(301);
+        expect.fail("take_partial above the remaining amount should fail");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("InvalidAmount");
+      }
+    });
+
+    it("Fill the rest of the Escrow!", async () => {
+      await takePartial(300);
+
+      const takerA = await connection.getTokenAccountBalance(takerAtaA3);
+      expect(takerA.value.amount).to.equal("1000");
+
+      const escrowAccount = await program.account.escrow.fetchNullable(escrow3);
+      expect(escrowAccount).to.be.null;
+      const vaultAccount = await connection.getAccountInfo(vault3);
+      expect(vaultAccount).to.be.null;
+    });
+  });
+
+  describe("Crank Expired Instruction:", () => {
+    const maker4 = anchor.web3.Keypair.generate();
+    const cranker = anchor.web3.Keypair.generate();
+
+    const seed4 = new BN(randomBytes(8));
+    const deposit_amount4 = 1000;
+    const receive_amount4 = 500;
+
+    let escrow4: PublicKey;
+    let vault4: PublicKey;
+    let mintA4: PublicKey;
+    let mintB4: PublicKey;
+    let makerAtaA4: any;
+
+    // Unix time as the validator's clock sees it
+    const chainTime = async () => (await connection.getBlockTime(await connection.getSlot()))!;
+
+    const crankExpired = () =>
+      program.methods
+        .crankExpired()
+        .accountsPartial({
+          cranker: cranker.publicKey,
+          maker: maker4.publicKey,
+          mintA: mintA4,
+          makerAtaA: makerAtaA4.address,
+          escrow: escrow4,
+          vault: vault4,
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([cranker])
+        .rpc();
+
+    before("Create an escrow that expires in a few seconds", async () => {
+      const drop1Sig = await provider.connection.requestAirdrop(maker4.publicKey, 2 * LAMPORTS_PER_SOL);
+      await confirmTransaction(connection, drop1Sig, "confirmed");
+
+      const drop2Sig = await provider.connection.requestAirdrop(cranker.publicKey, 2 * LAMPORTS_PER_SOL);
+      await confirmTransaction(connection, drop2Sig, "confirmed");
+
+      mintA4 = await createMint(connection, maker4, maker4.publicKey, null, 6);
+      mintB4 = await createMint(connection, maker4, maker4.publicKey, null, 6);
+
+      makerAtaA4 = await getOrCreateAssociatedTokenAccount(connection, maker4, mintA4, maker4.publicKey);
+      await mintTo(connection, maker4, mintA4, makerAtaA4.address, maker4, deposit_amount4);
+
+      [escrow4] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("escrow"),
+          maker4.publicKey.toBuffer(),
+          seed4.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+      vault4 = await getAssociatedTokenAddress(mintA4, escrow4, true, TOKEN_PROGRAM_ID);
+
+      await program.methods
+        .make({ seed: seed4, deposit: new BN(deposit_amount4), recieve: new BN(receive_amount4), expiry: new BN((await chainTime()) + 3), allowedTakers: { anyone: {} }, basketA: [], basketB: [] })
+        .accountsPartial({
+          maker: maker4.publicKey,
+          mintA: mintA4,
+          mintB: mintB4,
+          makerAtaA: makerAtaA4.address,
+          vault: vault4,
+          escrow: escrow4,
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([maker4])
+        .rpc();
+    });
+
+    it("Reject an expiry in the past!", async () => {
+      const seed = new BN(randomBytes(8));
+      const [escrow] = PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), maker4.publicKey.toBuffer(), seed.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      );
+      try {
+        await program.methods
+          .make({ seed, deposit: new BN(1), recieve: new BN(1), expiry: new BN((await chainTime()) - 1), allowedTakers: { anyone: {} }, basketA: [], basketB: [] })
+          .accountsPartial({
+            maker: maker4.publicKey,
+            mintA: mintA4,
+            mintB: mintB4,
+            makerAtaA: makerAtaA4.address,
+            vault: await getAssociatedTokenAddress(mintA4, escrow, true, TOKEN_PROGRAM_ID),
+            escrow,
+            systemProgram: SystemProgram.programId,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([maker4])
+          .rpc();
+        expect.fail("make with a past expiry should fail");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("InvalidExpiry");
+      }
+    });
+
+    for (const [deposit, receive] of [[0, 1], [1, 0]]) {
+      it(`Reject a deposit of ${deposit} for ${receive}!`, async () => {
+        const seed = new BN(randomBytes(8));
+        const [escrow] = PublicKey.findProgramAddressSync(
+          [Buffer.from("escrow"), maker4.publicKey.toBuffer(), seed.toArrayLike(Buffer, "le", 8)],
+          program.programId
+        );
+        try {
+          await program.methods
+            .make({ seed, deposit: new BN(deposit), recieve: new BN(receive), expiry: null, allowedTakers: { anyone: {} }, basketA: [], basketB: [] })
+            .accountsPartial({
+              maker: maker4.publicKey,
+              mintA: mintA4,
+              mintB: mintB4,
+              makerAtaA: makerAtaA4.address,
+              vault: await getAssociatedTokenAddress(mintA4, escrow, true, TOKEN_PROGRAM_ID),
+              escrow,
+              systemProgram: SystemProgram.programId,
+              associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+              tokenProgram: TOKEN_PROGRAM_ID,
+            })
+            .signers([maker4])
+            .rpc();
+          expect.fail("make with a zero amount should fail");
+        } catch (err) {
+          expect(err.error.errorCode.code).to.equal("InvalidAmount");
+        }
+      });
+    }
+
+    it("Reject a crank before expiry!", async () => {
+      try {
+        await crankExpired();
+        expect.fail("crank_expired before expiry should fail");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("EscrowNotExpired");
+      }
+    });
+
+    it("Crank the expired Escrow!", async () => {
+      const expiry = (await program.account.escrow.fetch(escrow4)).expiry.toNumber();
+      while ((await chainTime()) < expiry) {
+        await new Promise((resolve) => setTimeout(resolve, 500));
+      }
+
+      const crankerBefore = await connection.getBalance(cranker.publicKey);
+      await crankExpired();
+
+      const makerA = await connection.getTokenAccountBalance(makerAtaA4.address);
+      expect(makerA.value.amount).to.equal(deposit_amount4.toString());
+
+      const escrowAccount = await program.account.escrow.fetchNullable(escrow4);
+      expect(escrowAccount).to.be.null;
+
+      // The tip more than covers the crank's signature fee
+      const crankerAfter = await connection.getBalance(cranker.publicKey);
+      expect(crankerAfter).to.be.greaterThan(crankerBefore);
+    });
+  });
+
+  describe("Allowed Takers:", () => {
+    const maker5 = anchor.web3.Keypair.generate();
+    const taker5 = anchor.web3.Keypair.generate();
+    const outsider = anchor.web3.Keypair.generate();
+
+    let mintA5: PublicKey;
+    let mintB5: PublicKey;
+    let makerAtaA5: any;
+
+    const sha256 = (...parts: Buffer[]) => createHash("sha256").update(Buffer.concat(parts)).digest();
+    // Leaves and nodes are domain-separated, and children are sorted before hashing
+    const leaf = (key: PublicKey) => sha256(Buffer.from([0]), key.toBuffer());
+    const node = (a: Buffer, b: Buffer) =>
+      Buffer.compare(a, b) <= 0 ? sha256(Buffer.from([1]), a, b) : sha256(Buffer.from([1]), b, a);
+
+    const escrowFor = (seed: BN) =>
+      PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), maker5.publicKey.toBuffer(), seed.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      )[0];
+
+    const make = async (seed: BN, allowedTakers: any) => {
+      const escrow = escrowFor(seed);
+      await program.methods
+        .make({ seed, deposit: new BN(100), recieve: new BN(100), expiry: null, allowedTakers, basketA: [], basketB: [] })
+        .accountsPartial({
+          maker: maker5.publicKey,
+          mintA: mintA5,
+          mintB: mintB5,
+          makerAtaA: makerAtaA5.address,
+          vault: await getAssociatedTokenAddress(mintA5, escrow, true, TOKEN_PROGRAM_ID),
+          escrow,
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([maker5])
+        .rpc();
+    };
+
+    const take = async (seed: BN, taker: anchor.web3.Keypair, proof: number[][]) => {
+      const escrow = escrowFor(seed);
+      await program.methods
+        .take(proof)
+        .accountsPartial({
+          taker: taker.publicKey,
+          maker: maker5.publicKey,
+          mintA: mintA5,
+          mintB: mintB5,
+          takerAtaA: await getAssociatedTokenAddress(mintA5, taker.publicKey),
+          takerAtaB: await getAssociatedTokenAddress(mintB5, taker.publicKey),
+          makerAtaB: await getAssociatedTokenAddress(mintB5, maker5.publicKey),
+          escrow,
+          vault: await getAssociatedTokenAddress(mintA5, escrow, true, TOKEN_PROGRAM_ID),
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([taker])
+        .rpc();
+    };
+
+    before("Fund the maker and both takers", async () => {
+      for (const keypair of [maker5, taker5, outsider]) {
+        const sig = await provider.connection.requestAirdrop(keypair.publicKey, 2 * LAMPORTS_PER_SOL);
+        await confirmTransaction(connection, sig, "confirmed");
+      }
+
+      mintA5 = await createMint(connection, maker5, maker5.publicKey, null, 6);
+      mintB5 = await createMint(connection, maker5, maker5.publicKey, null, 6);
+
+      makerAtaA5 = await getOrCreateAssociatedTokenAccount(connection, maker5, mintA5, maker5.publicKey);
+      await mintTo(connection, maker5, mintA5, makerAtaA5.address, maker5, 1000);
+      await getOrCreateAssociatedTokenAccount(connection, maker5, mintB5, maker5.publicKey);
+
+      for (const taker of [taker5, outsider]) {
+        await getOrCreateAssociatedTokenAccount(connection, taker, mintA5, taker.publicKey);
+        const ataB = await getOrCreateAssociatedTokenAccount(connection, taker, mintB5, taker.publicKey);
+        await mintTo(connection, maker5, mintB5, ataB.address, maker5, 1000);
+      }
+    });
+
+    it("Only the designated taker can take the Escrow!", async () => {
+      const seed = new BN(randomBytes(8));
+      await make(seed, { only: { taker: taker5.publicKey } });
+
+      try {
+        await take(seed, outsider, []);
+        expect.fail("take by another taker should fail");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("TakerNotAllowed");
+      }
+
+      await take(seed, taker5, []);
+      expect(await program.account.escrow.fetchNullable(escrowFor(seed))).to.be.null;
+    });
+
+    it("Only allowlisted takers can take the Escrow!", async () => {
+      // Two-leaf tree: each leaf's proof is the other leaf
+      const other = anchor.web3.Keypair.generate().publicKey;
+      const root = node(leaf(taker5.publicKey), leaf(other));
+      const seed = new BN(randomBytes(8));
+      await make(seed, { allowlist: { root: [...root] } });
+
+      try {
+        await take(seed, outsider, [[...leaf(other)]]);
+        expect.fail("take by a taker outside the allowlist should fail");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("TakerNotAllowed");
+      }
+
+      await take(seed, taker5, [[...leaf(other)]]);
+      expect(await program.account.escrow.fetchNullable(escrowFor(seed))).to.be.null;
+    });
+  });
+
+  describe("Native SOL:", () => {
+    const maker6 = anchor.web3.Keypair.generate();
+    const taker6 = anchor.web3.Keypair.generate();
+
+    const token_amount6 = 500 * 10 ** 6;
+    const sol_amount6 = LAMPORTS_PER_SOL / 2;
+
+    let mint6: PublicKey;
+    let makerAta6: any;
+    let takerAta6: any;
+
+    const findEscrow = (seed: BN) => {
+      const [escrow] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("escrow"),
+          maker6.publicKey.toBuffer(),
+          seed.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+      const [solVault] = PublicKey.findProgramAddressSync(
+        [Buffer.from("sol_vault"), escrow.toBuffer()],
+        program.programId
+      );
+      return { escrow, solVault };
+    };
+
+    before("Create accounts for native SOL escrows", async () => {
+      const drop1Sig = await provider.connection.requestAirdrop(maker6.publicKey, 3 * LAMPORTS_PER_SOL);
+      await confirmTransaction(connection, drop1Sig, "confirmed");
+
+      const drop2Sig = await provider.connection.requestAirdrop(taker6.publicKey, 3 * LAMPORTS_PER_SOL);
+      await confirmTransaction(connection, drop2Sig, "confirmed");
+
+      mint6 = await createMint(connection, taker6, taker6.publicKey, null, 6);
+
+      makerAta6 = await getOrCreateAssociatedTokenAccount(connection, maker6, mint6, maker6.publicKey);
+      takerAta6 = await getOrCreateAssociatedTokenAccount(connection, taker6, mint6, taker6.publicKey);
+
+      await mintTo(connection, taker6, mint6, takerAta6.address, taker6, 2 * token_amount6);
+    });
+
+    it("Swap SOL for tokens!", async () => {
+      const seed6 = new BN(randomBytes(8));
+      const { escrow, solVault } = findEscrow(seed6);
+
+      // Offer lamports: no mint A, so no maker ATA or token vault either
+      await program.methods
+        .make({ seed: seed6, deposit: new BN(sol_amount6), recieve: new BN(token_amount6), expiry: null, allowedTakers: { anyone: {} }, basketA: [], basketB: [] })
+        .accountsPartial({
+          maker: maker6.publicKey,
+          mintA: null,
+          mintB: mint6,
+          makerAtaA: null,
+          vault: null,
+          solVault,
+          escrow,
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([maker6])
+        .rpc();
+
+      const escrowAccount = await program.account.escrow.fetch(escrow);
+      expect(escrowAccount.mintA.equals(SystemProgram.programId)).to.be.true;
+      expect(escrowAccount.deposit.toNumber()).to.equal(sol_amount6);
+
+      const rent = await connection.getMinimumBalanceForRentExemption(0);
+      expect(await connection.getBalance(solVault)).to.equal(sol_amount6 + rent);
+
+      const takerBefore = await connection.getBalance(taker6.publicKey);
+
+      await program.methods
+        .take([])
+        .accountsPartial({
+          taker: taker6.publicKey,
+          maker: maker6.publicKey,
+          mintA: null,
+          mintB: mint6,
+          takerAtaA: null,
+          takerAtaB: takerAta6.address,
+          makerAtaB: makerAta6.address,
+          escrow,
+          vault: null,
+          solVault,
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([taker6])
+        .rpc();
+
+      // The taker also paid the transaction fee
+      const takerAfter = await connection.getBalance(taker6.publicKey);
+      expect(takerAfter - takerBefore).to.be.within(sol_amount6 - 10_000, sol_amount6);
+
+      const makerAta = await getAccount(connection, makerAta6.address);
+      expect(Number(makerAta.amount)).to.equal(token_amount6);
+
+      expect(await connection.getBalance(solVault)).to.equal(0);
+      expect(await program.account.escrow.fetchNullable(escrow)).to.be.null;
+    });
+
+    it("Swap tokens for SOL!", async () => {
+      const seed6 = new BN(randomBytes(8));
+      const { escrow } = findEscrow(seed6);
+      const vault = await getAssociatedTokenAddress(mint6, escrow, true, TOKEN_PROGRAM_ID);
+
+      // Ask for lamports: no mint B, so the taker pays straight from their wallet
+      await program.methods
+        .make({ seed: seed6, deposit: new BN(token_amount6), recieve: new BN(sol_amount6), expiry: null, allowedTakers: { anyone: {} }, basketA: [], basketB: [] })
+        .accountsPartial({
+          maker: maker6.publicKey,
+          mintA: mint6,
+          mintB: null,
+          makerAtaA: makerAta6.address,
+          vault,
+          solVault: null,
+          escrow,
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([maker6])
+        .rpc();
+
+      const makerBefore = await connection.getBalance(maker6.publicKey);
+      const takerTokensBefore = await getAccount(connection, takerAta6.address);
+
+      await program.methods
+        .take([])
+        .accountsPartial({
+          taker: taker6.publicKey,
+          maker: maker6.publicKey,
+          mintA: mint6,
+          mintB: null,
+          takerAtaA: takerAta6.address,
+          takerAtaB: null,
+          makerAtaB: null,
+          escrow,
+          vault,
+          solVault: null,
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([taker6])
+        .rpc();
+
+      // The maker also gets back the rent of the escrow and its vault
+      const makerAfter = await connection.getBalance(maker6.publicKey);
+      expect(makerAfter - makerBefore).to.be.greaterThan(sol_amount6);
+
+      const takerTokensAfter = await getAccount(connection, takerAta6.address);
+      expect(Number(takerTokensAfter.amount - takerTokensBefore.amount)).to.equal(token_amount6);
+
+      expect(await program.account.escrow.fetchNullable(escrow)).to.be.null;
+    });
+  });
+
+  describe("Basket Offers:", () => {
+    const maker7 = anchor.web3.Keypair.generate();
+    const taker7 = anchor.web3.Keypair.generate();
+
+    const amount_a7 = 100;
+    const amount_b7 = 200;
+    const amount_c7 = 300;
+    const amount_d7 = 400;
+
+    // Maker offers A and C, taker pays B and D
+    let mintA7: PublicKey;
+    let mintB7: PublicKey;
+    let mintC7: PublicKey;
+    let mintD7: PublicKey;
+    let makerAtaA7: any;
+    let makerAtaB7: any;
+    let makerAtaC7: any;
+    let makerAtaD7: any;
+    let takerAtaA7: any;
+    let takerAtaB7: any;
+    let takerAtaC7: any;
+    let takerAtaD7: any;
+
+    const writable = (pubkey: PublicKey) => ({ pubkey, isSigner: false, isWritable: true });
+    const readonly = (pubkey: PublicKey) => ({ pubkey, isSigner: false, isWritable: false });
+
+    const makeBasket = async (seed: BN) => {
+      const [escrow] = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("escrow"),
+          maker7.publicKey.toBuffer(),
+          seed.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      );
+      const vault = await getAssociatedTokenAddress(mintA7, escrow, true, TOKEN_PROGRAM_ID);
+      const vaultC = await getAssociatedTokenAddress(mintC7, escrow, true, TOKEN_PROGRAM_ID);
+
+      await program.methods
+        .make({ seed, deposit: new BN(amount_a7), recieve: new BN(amount_b7), expiry: null, allowedTakers: { anyone: {} }, basketA: [new BN(amount_c7)], basketB: [new BN(amount_d7)] })
+        .accountsPartial({
+          maker: maker7.publicKey,
+          mintA: mintA7,
+          mintB: mintB7,
+          makerAtaA: makerAtaA7.address,
+          vault,
+          escrow,
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts([
+          readonly(mintC7),
+          writable(makerAtaC7.address),
+          writable(vaultC),
+          readonly(mintD7),
+        ])
+        .signers([maker7])
+        .rpc();
+
+      return { escrow, vault, vaultC };
+    };
+
+    before("Create accounts for basket escrows", async () => {
+      const drop1Sig = await provider.connection.requestAirdrop(maker7.publicKey, 2 * LAMPORTS_PER_SOL);
+      await confirmTransaction(connection, drop1Sig, "confirmed");
+
+      const drop2Sig = await provider.connection.requestAirdrop(taker7.publicKey, 2 * LAMPORTS_PER_SOL);
+      await confirmTransaction(connection, drop2Sig, "confirmed");
+
+      mintA7 = await createMint(connection, maker7, maker7.publicKey, null, 6);
+      mintC7 = await createMint(connection, maker7, maker7.publicKey, null, 6);
+      mintB7 = await createMint(connection, taker7, taker7.publicKey, null, 6);
+      mintD7 = await createMint(connection, taker7, taker7.publicKey, null, 6);
+
+      makerAtaA7 = await getOrCreateAssociatedTokenAccount(connection, maker7, mintA7, maker7.publicKey);
+      makerAtaB7 = await getOrCreateAssociatedTokenAccount(connection, maker7, mintB7, maker7.publicKey);
+      makerAtaC7 = await getOrCreateAssociatedTokenAccount(connection, maker7, mintC7, maker7.publicKey);
+      makerAtaD7 = await getOrCreateAssociatedTokenAccount(connection, maker7, mintD7, maker7.publicKey);
+      takerAtaA7 = await getOrCreateAssociatedTokenAccount(connection, taker7, mintA7, taker7.publicKey);
+      takerAtaB7 = await getOrCreateAssociatedTokenAccount(connection, taker7, mintB7, taker7.publicKey);
+      takerAtaC7 = await getOrCreateAssociatedTokenAccount(connection, taker7, mintC7, taker7.publicKey);
+      takerAtaD7 = await getOrCreateAssociatedTokenAccount(connection, taker7, mintD7, taker7.publicKey);
+
+      await mintTo(connection, maker7, mintA7, makerAtaA7.address, maker7, 10 * amount_a7);
+      await mintTo(connection, maker7, mintC7, makerAtaC7.address, maker7, 10 * amount_c7);
+      await mintTo(connection, taker7, mintB7, takerAtaB7.address, taker7, 10 * amount_b7);
+      await mintTo(connection, taker7, mintD7, takerAtaD7.address, taker7, 10 * amount_d7);
+    });
+
+    it("Take every leg of a basket!", async () => {
+      const { escrow, vault, vaultC } = await makeBasket(new BN(randomBytes(8)));
+
+      const escrowAccount = await program.account.escrow.fetch(escrow);
+      expect(escrowAccount.basketA[0].mint.equals(mintC7)).to.be.true;
+      expect(escrowAccount.basketB[0].amount.toNumber()).to.equal(amount_d7);
+
+      const take = () =>
+        program.methods
+          .take([])
+          .accountsPartial({
+            taker: taker7.publicKey,
+            maker: maker7.publicKey,
+            mintA: mintA7,
+            mintB: mintB7,
+            takerAtaA: takerAtaA7.address,
+            takerAtaB: takerAtaB7.address,
+            makerAtaB: makerAtaB7.address,
+            escrow,
+            vault,
+            systemProgram: SystemProgram.programId,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([taker7]);
+
+      // Leaving out the requested leg fails the whole trade
+      try {
+        await take()
+          .remainingAccounts([readonly(mintC7), writable(vaultC), writable(takerAtaC7.address)])
+          .rpc();
+        expect.fail("take without every leg should fail");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("InvalidLegAccounts");
+      }
+
+      try {
+        await program.methods
+          .takePartial(new BN(amount_b7 / 2), [])
+          .accountsPartial({
+            taker: taker7.publicKey,
+            maker: maker7.publicKey,
+            mintA: mintA7,
+            mintB: mintB7,
+            takerAtaA: takerAtaA7.address,
+            takerAtaB: takerAtaB7.address,
+            makerAtaB: makerAtaB7.address,
+            escrow,
+            vault,
+            systemProgram: SystemProgram.programId,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+            tokenProgram: TOKEN_PROGRAM_ID,
+          })
+          .signers([taker7])
+          .rpc();
+        expect.fail("partial fill of a basket should fail");
+      } catch (err) {
+        expect(err.error.errorCode.code).to.equal("BasketPartialFill");
+      }
+
+      await take()
+        .remainingAccounts([
+          readonly(mintC7),
+          writable(vaultC),
+          writable(takerAtaC7.address),
+          readonly(mintD7),
+          writable(takerAtaD7.address),
+          writable(makerAtaD7.address),
+        ])
+        .rpc();
+
+      expect(Number((await getAccount(connection, takerAtaA7.address)).amount)).to.equal(amount_a7);
+      expect(Number((await getAccount(connection, takerAtaC7.address)).amount)).to.equal(amount_c7);
+      expect(Number((await getAccount(connection, makerAtaB7.address)).amount)).to.equal(amount_b7);
+      expect(Number((await getAccount(connection, makerAtaD7.address)).amount)).to.equal(amount_d7);
+
+      expect(await connection.getAccountInfo(vaultC)).to.be.null;
+      expect(await program.account.escrow.fetchNullable(escrow)).to.be.null;
+    });
+
+    it("Refund every leg of a basket!", async () => {
+      const { escrow, vault, vaultC } = await makeBasket(new BN(randomBytes(8)));
+
+      const makerCBefore = await getAccount(connection, makerAtaC7.address);
+
+      await program.methods
+        .refund()
+        .accountsPartial({
+          maker: maker7.publicKey,
+          mintA: mintA7,
+          makerAtaA: makerAtaA7.address,
+          escrow,
+          vault,
+          systemProgram: SystemProgram.programId,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts([readonly(mintC7), writable(vaultC), writable(makerAtaC7.address)])
+        .signers([maker7])
+        .rpc();
+
+      const makerCAfter = await getAccount(connection, makerAtaC7.address);
+      expect(Number(makerCAfter.amount - makerCBefore.amount)).to.equal(amount_c7);
+
+      expect(await connection.getAccountInfo(vaultC)).to.be.null;
+      expect(await program.account.escrow.fetchNullable(escrow)).to.be.null;
+    });
+  });
 });
\ No newline at end of file